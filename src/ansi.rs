@@ -0,0 +1,273 @@
+/// The text colours Discord renders in `ansi` code blocks.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Ansi {
+    Grey,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Pink,
+    Cyan,
+    White,
+}
+
+/// The background colours Discord renders in `ansi` code blocks, named after how they look.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum AnsiBackground {
+    DarkBlue,
+    Orange,
+    MarbleBlue,
+    GreyishTurquoise,
+    Grey,
+    Indigo,
+    LightGrey,
+    White,
+}
+
+/// A combination of the formatting Discord supports, built from an [`Ansi`] colour or
+/// [`AnsiStyle::plain`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct AnsiStyle {
+    colour: Option<Ansi>,
+    background: Option<AnsiBackground>,
+    bold: bool,
+    underline: bool,
+}
+
+impl Ansi {
+    fn code(self) -> u8 {
+        match self {
+            Ansi::Grey => 30,
+            Ansi::Red => 31,
+            Ansi::Green => 32,
+            Ansi::Yellow => 33,
+            Ansi::Blue => 34,
+            Ansi::Pink => 35,
+            Ansi::Cyan => 36,
+            Ansi::White => 37,
+        }
+    }
+
+    pub fn bold(self) -> AnsiStyle {
+        AnsiStyle::from(self).bold()
+    }
+
+    pub fn underline(self) -> AnsiStyle {
+        AnsiStyle::from(self).underline()
+    }
+
+    pub fn on(self, background: AnsiBackground) -> AnsiStyle {
+        AnsiStyle::from(self).on(background)
+    }
+}
+
+impl AnsiBackground {
+    fn code(self) -> u8 {
+        match self {
+            AnsiBackground::DarkBlue => 40,
+            AnsiBackground::Orange => 41,
+            AnsiBackground::MarbleBlue => 42,
+            AnsiBackground::GreyishTurquoise => 43,
+            AnsiBackground::Grey => 44,
+            AnsiBackground::Indigo => 45,
+            AnsiBackground::LightGrey => 46,
+            AnsiBackground::White => 47,
+        }
+    }
+}
+
+impl AnsiStyle {
+    /// Unformatted text.
+    pub fn plain() -> Self {
+        Self::default()
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    pub fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    pub fn on(mut self, background: AnsiBackground) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    /// The escape sequence selecting this style, empty for plain text.
+    fn escape(&self) -> String {
+        let codes: Vec<String> = [
+            self.bold.then_some(1),
+            self.underline.then_some(4),
+            self.colour.map(Ansi::code),
+            self.background.map(AnsiBackground::code),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|code| code.to_string())
+        .collect();
+        match codes.is_empty() {
+            true => String::new(),
+            false => format!("{}[{}m", ESC, codes.join(";")),
+        }
+    }
+}
+
+impl From<Ansi> for AnsiStyle {
+    fn from(colour: Ansi) -> Self {
+        Self {
+            colour: Some(colour),
+            ..Self::default()
+        }
+    }
+}
+
+const ESC: char = '\u{1b}';
+const RESET: &str = "\u{1b}[0m";
+const OPEN: &str = "```ansi\n";
+const CLOSE: &str = "\n```";
+
+/// Builds the body of an `ansi` code block from styled pieces of text.
+///
+/// Every styled piece is followed by a reset, so styles never leak into the next piece.
+/// Backticks are broken up with a zero width space so the text can't close the block early.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct AnsiBlock {
+    segments: Vec<(String, String)>,
+}
+
+impl AnsiBlock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push<S: AsRef<str>, T: Into<AnsiStyle>>(mut self, text: S, style: T) -> Self {
+        let text = text.as_ref().replace('`', "`\u{200b}");
+        self.segments.push((style.into().escape(), text));
+        self
+    }
+
+    /// Pushes the text followed by a line break.
+    pub fn push_line<S: AsRef<str>, T: Into<AnsiStyle>>(self, text: S, style: T) -> Self {
+        self.push(text, style).push("\n", AnsiStyle::plain())
+    }
+
+    /// Renders the block, dropping text from the end as needed to fit within `budget`
+    /// characters, e.g. [`MAX_FIELD_VALUE`](crate::validation::MAX_FIELD_VALUE).
+    ///
+    /// Truncated text ends with `…`. Returns an empty string if not even the fences fit.
+    pub fn to_code_block(&self, budget: usize) -> String {
+        let fences = OPEN.chars().count() + CLOSE.chars().count();
+        let Some(mut remaining) = budget.checked_sub(fences) else {
+            return String::new();
+        };
+
+        let mut body = String::new();
+        for (escape, text) in &self.segments {
+            let (escape_len, text_len) = (escape.chars().count(), text.chars().count());
+            let reset_len = if escape.is_empty() { 0 } else { RESET.len() };
+            if escape_len + text_len + reset_len <= remaining {
+                remaining -= escape_len + text_len + reset_len;
+                push_segment(&mut body, escape, text);
+                continue;
+            }
+            // as much of this piece as fits, marked with an ellipsis
+            if let Some(room) = remaining.checked_sub(escape_len + reset_len + 1) {
+                let truncated: String = text.chars().take(room).collect();
+                push_segment(&mut body, escape, &format!("{}…", truncated));
+            }
+            break;
+        }
+        format!("{}{}{}", OPEN, body.trim_end_matches('\n'), CLOSE)
+    }
+}
+
+fn push_segment(body: &mut String, escape: &str, text: &str) {
+    body.push_str(escape);
+    body.push_str(text);
+    if !escape.is_empty() {
+        body.push_str(RESET);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ansi, AnsiBackground, AnsiBlock, AnsiStyle};
+
+    fn block(text: &str, style: impl Into<AnsiStyle>) -> String {
+        AnsiBlock::new().push(text, style).to_code_block(usize::MAX)
+    }
+
+    #[test]
+    fn colour_sequences() {
+        let cases = [
+            (Ansi::Grey, "```ansi\n\u{1b}[30mx\u{1b}[0m\n```"),
+            (Ansi::Red, "```ansi\n\u{1b}[31mx\u{1b}[0m\n```"),
+            (Ansi::Green, "```ansi\n\u{1b}[32mx\u{1b}[0m\n```"),
+            (Ansi::Yellow, "```ansi\n\u{1b}[33mx\u{1b}[0m\n```"),
+            (Ansi::Blue, "```ansi\n\u{1b}[34mx\u{1b}[0m\n```"),
+            (Ansi::Pink, "```ansi\n\u{1b}[35mx\u{1b}[0m\n```"),
+            (Ansi::Cyan, "```ansi\n\u{1b}[36mx\u{1b}[0m\n```"),
+            (Ansi::White, "```ansi\n\u{1b}[37mx\u{1b}[0m\n```"),
+        ];
+        for (colour, expected) in cases {
+            assert_eq!(block("x", colour), expected, "{:?}", colour);
+        }
+    }
+
+    #[test]
+    fn background_sequences() {
+        let cases = [
+            (AnsiBackground::DarkBlue, "\u{1b}[40m"),
+            (AnsiBackground::Orange, "\u{1b}[41m"),
+            (AnsiBackground::MarbleBlue, "\u{1b}[42m"),
+            (AnsiBackground::GreyishTurquoise, "\u{1b}[43m"),
+            (AnsiBackground::Grey, "\u{1b}[44m"),
+            (AnsiBackground::Indigo, "\u{1b}[45m"),
+            (AnsiBackground::LightGrey, "\u{1b}[46m"),
+            (AnsiBackground::White, "\u{1b}[47m"),
+        ];
+        for (background, escape) in cases {
+            let expected = format!("```ansi\n{}x\u{1b}[0m\n```", escape);
+            assert_eq!(block("x", AnsiStyle::plain().on(background)), expected, "{:?}", background);
+        }
+    }
+
+    #[test]
+    fn style_sequences() {
+        assert_eq!(block("x", Ansi::Red.bold()), "```ansi\n\u{1b}[1;31mx\u{1b}[0m\n```");
+        assert_eq!(block("x", Ansi::Red.underline()), "```ansi\n\u{1b}[4;31mx\u{1b}[0m\n```");
+        assert_eq!(
+            block("x", Ansi::Yellow.bold().underline().on(AnsiBackground::Indigo)),
+            "```ansi\n\u{1b}[1;4;33;45mx\u{1b}[0m\n```"
+        );
+        assert_eq!(block("x", AnsiStyle::plain().bold()), "```ansi\n\u{1b}[1mx\u{1b}[0m\n```");
+        assert_eq!(block("x", AnsiStyle::plain()), "```ansi\nx\n```");
+    }
+
+    #[test]
+    fn builds_lines_and_escapes_backticks() {
+        let rendered = AnsiBlock::new()
+            .push("OK ", Ansi::Green)
+            .push_line("FAILED", Ansi::Red.bold())
+            .push("```", AnsiStyle::plain())
+            .to_code_block(usize::MAX);
+        assert_eq!(
+            rendered,
+            "```ansi\n\u{1b}[32mOK \u{1b}[0m\u{1b}[1;31mFAILED\u{1b}[0m\n`\u{200b}`\u{200b}`\u{200b}\n```"
+        );
+    }
+
+    #[test]
+    fn truncates_to_the_budget() {
+        let block = AnsiBlock::new().push("passed", Ansi::Green).push("abcdefghij", Ansi::Red);
+        // fences (12) + green piece (5 + 6 + 4) + red escape and reset (9) leave room for 4 characters
+        let rendered = block.to_code_block(12 + 15 + 9 + 4);
+        assert_eq!(rendered, "```ansi\n\u{1b}[32mpassed\u{1b}[0m\u{1b}[31mabc…\u{1b}[0m\n```");
+        assert_eq!(rendered.chars().count(), 40);
+        assert_eq!(block.to_code_block(11), "");
+    }
+}
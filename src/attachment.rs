@@ -0,0 +1,264 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+
+use crate::{Embed, Webhook, WebhookError};
+
+/// A file uploaded with the message. The bytes are shared, so cloning a webhook doesn't copy them.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct File {
+    pub(crate) filename: String,
+    pub(crate) bytes: Arc<[u8]>,
+}
+
+/// The `attachments` entry that lets the payload refer to an uploaded file.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub(crate) struct AttachmentInfo {
+    id: usize,
+    filename: String,
+}
+
+impl Webhook {
+    /// Attaches a file, sent as part `files[n]` of a multipart request. Embeds can show an
+    /// attached image with `attachment://{filename}`.
+    ///
+    /// Discord allows 10 files per message; more fail when sending, before any request is made.
+    pub fn add_file<S: AsRef<str>>(mut self, filename: S, bytes: Vec<u8>) -> Self {
+        let filename = filename.as_ref().to_string();
+        self.attachments.push(AttachmentInfo {
+            id: self.files.len(),
+            filename: filename.clone(),
+        });
+        self.files.push(File { filename, bytes: bytes.into() });
+        self
+    }
+
+    /// The same as [`Webhook::add_file`].
+    pub fn add_attachment<S: AsRef<str>>(self, filename: S, bytes: Vec<u8>) -> Self {
+        self.add_file(filename, bytes)
+    }
+
+    /// Attaches a file read from disk, named after the last component of the path.
+    pub fn add_file_from_path<P: AsRef<Path>>(self, path: P) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let filename = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "path has no file name"))?;
+        let bytes = std::fs::read(path)?;
+        Ok(self.add_file(filename, bytes))
+    }
+
+    /// Names of the attached files, in order.
+    pub fn filenames(&self) -> impl Iterator<Item = &str> {
+        self.files.iter().map(|file| file.filename.as_str())
+    }
+}
+
+impl Embed {
+    /// Shows a file attached with [`Webhook::add_file`] as the embed's image.
+    pub fn set_image_attachment<S: AsRef<str>>(self, filename: S) -> Self {
+        self.set_image(format!("attachment://{}", filename.as_ref()), None::<&str>, None, None)
+    }
+
+    /// Shows a file attached with [`Webhook::add_file`] as the embed's thumbnail.
+    pub fn set_thumbnail_attachment<S: AsRef<str>>(self, filename: S) -> Self {
+        self.set_thumbnail(format!("attachment://{}", filename.as_ref()), None::<&str>, None, None)
+    }
+}
+
+/// A serialized request body and its content type.
+#[derive(Debug, Clone)]
+pub(crate) struct Body {
+    pub(crate) content_type: String,
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl Body {
+    pub(crate) fn json<T: Serialize>(payload: &T) -> Result<Self, WebhookError> {
+        Ok(Self {
+            content_type: String::from("application/json"),
+            bytes: serde_json::to_vec(payload)?,
+        })
+    }
+
+    /// The webhook's payload as JSON, or as `multipart/form-data` when it has files.
+    pub(crate) fn webhook(webhook: &Webhook) -> Result<Self, WebhookError> {
+        Self::with_files(webhook, webhook)
+    }
+
+    /// Like [`Body::webhook`], with the payload for editing a message, which can clear fields.
+    pub(crate) fn edit(webhook: &Webhook) -> Result<Self, WebhookError> {
+        Self::with_files(webhook, &webhook.edit_payload())
+    }
+
+    fn with_files<T: Serialize>(webhook: &Webhook, payload: &T) -> Result<Self, WebhookError> {
+        if !webhook.skip_validation {
+            webhook.validate()?;
+        }
+        if webhook.files.is_empty() {
+            return Self::json(payload);
+        }
+        if let Some(file) = webhook.files.iter().find(|file| !webhook.allows_filename(&file.filename)) {
+            return Err(WebhookError::DisallowedFile(file.filename.clone()));
+        }
+
+        let payload = serde_json::to_vec(payload)?;
+        let boundary = boundary(&payload, &webhook.files);
+        let mut bytes = Vec::new();
+        let mut part = |disposition: String, content_type: &str, data: &[u8]| {
+            bytes.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+            bytes.extend_from_slice(format!("Content-Disposition: form-data; {}\r\n", disposition).as_bytes());
+            bytes.extend_from_slice(format!("Content-Type: {}\r\n\r\n", content_type).as_bytes());
+            bytes.extend_from_slice(data);
+            bytes.extend_from_slice(b"\r\n");
+        };
+        part(String::from("name=\"payload_json\""), "application/json", &payload);
+        for (i, file) in webhook.files.iter().enumerate() {
+            let disposition = format!("name=\"files[{}]\"; filename=\"{}\"", i, quoted(&file.filename));
+            part(disposition, content_type(&file.filename), &file.bytes);
+        }
+        bytes.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+        Ok(Self {
+            content_type: format!("multipart/form-data; boundary={}", boundary),
+            bytes,
+        })
+    }
+
+    pub(crate) fn is_json(&self) -> bool {
+        self.content_type == "application/json"
+    }
+}
+
+/// A boundary derived from the content, so it can't appear in it and bodies are reproducible.
+fn boundary(payload: &[u8], files: &[File]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(payload);
+    for file in files {
+        hasher.update(&file.bytes);
+    }
+    format!("blurple-hook-{}", hex::encode(hasher.finalize()))
+}
+
+/// Escapes a filename for a quoted header parameter.
+fn quoted(filename: &str) -> String {
+    filename
+        .chars()
+        .filter(|c| *c != '\r' && *c != '\n')
+        .flat_map(|c| match c {
+            '"' | '\\' => vec!['\\', c],
+            c => vec![c],
+        })
+        .collect()
+}
+
+fn content_type(filename: &str) -> &'static str {
+    let extension = filename.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("txt" | "log") => "text/plain",
+        Some("json") => "application/json",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Body;
+    use crate::{assert_payload_matches, Embed, Webhook, WebhookError};
+
+    #[test]
+    fn json_path_is_unchanged_without_files() {
+        let webhook = Webhook::new("https://discord.com/api/webhooks/1/token").set_content("Content Text");
+        let body = Body::webhook(&webhook).unwrap();
+        assert!(body.is_json());
+        assert_eq!(body.bytes, serde_json::to_vec(&webhook).unwrap());
+        assert!(!String::from_utf8(body.bytes).unwrap().contains("attachments"));
+    }
+
+    #[test]
+    fn files_become_multipart_parts() {
+        let webhook = Webhook::new("https://discord.com/api/webhooks/1/token")
+            .set_content("Content Text")
+            .add_file("build \"1\".log", b"ok".to_vec())
+            .add_file("chart.png", vec![0x89, b'P', b'N', b'G']);
+        assert_payload_matches!(webhook, serde_json::json!({
+            "attachments": [{"id": 0, "filename": "build \"1\".log"}, {"id": 1, "filename": "chart.png"}],
+        }));
+
+        let body = Body::webhook(&webhook).unwrap();
+        let boundary = body.content_type.strip_prefix("multipart/form-data; boundary=").unwrap();
+        let text = String::from_utf8_lossy(&body.bytes);
+        let parts: Vec<&str> = text.split(&format!("--{}", boundary)).collect();
+        assert_eq!(parts.len(), 5);
+        assert!(parts[1].starts_with("\r\nContent-Disposition: form-data; name=\"payload_json\"\r\nContent-Type: application/json\r\n\r\n{"));
+        assert_eq!(parts[2], "\r\nContent-Disposition: form-data; name=\"files[0]\"; filename=\"build \\\"1\\\".log\"\r\nContent-Type: text/plain\r\n\r\nok\r\n");
+        assert!(parts[3].contains("name=\"files[1]\"; filename=\"chart.png\"\r\nContent-Type: image/png"));
+        assert_eq!(parts[4], "--\r\n");
+    }
+
+    #[tokio::test]
+    async fn sends_multipart_requests() {
+        use wiremock::matchers::{header_regex, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header_regex("content-type", "^multipart/form-data; boundary=blurple-hook-[0-9a-f]{40}$"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Webhook::new(format!("{}/api/webhooks/1/token", server.uri()))
+            .set_content("Logs attached")
+            .add_attachment("notes.txt", b"hello".to_vec())
+            .send()
+            .await
+            .unwrap();
+        let requests = server.received_requests().await.unwrap();
+        let body = String::from_utf8_lossy(&requests[0].body);
+        let payload_json = body
+            .split("name=\"payload_json\"\r\nContent-Type: application/json\r\n\r\n")
+            .nth(1)
+            .and_then(|rest| rest.split("\r\n--").next())
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(payload_json).unwrap(),
+            serde_json::json!({"content": "Logs attached", "attachments": [{"id": 0, "filename": "notes.txt"}]})
+        );
+        assert!(body.contains("name=\"files[0]\"; filename=\"notes.txt\"\r\nContent-Type: text/plain\r\n\r\nhello\r\n"));
+    }
+
+    #[test]
+    fn embeds_reference_attached_files() {
+        let webhook = Webhook::new("https://discord.com/api/webhooks/1/token")
+            .add_embed(Embed::new().set_image_attachment("chart.svg").set_thumbnail_attachment("logo.svg"))
+            .add_file("chart.svg", b"<svg/>".to_vec())
+            .add_file("logo.svg", b"<svg/>".to_vec());
+        assert_payload_matches!(webhook, serde_json::json!({
+            "embeds": [{"image": {"url": "attachment://chart.svg"}, "thumbnail": {"url": "attachment://logo.svg"}}],
+            "attachments": [{"id": 0, "filename": "chart.svg"}, {"id": 1, "filename": "logo.svg"}],
+        }));
+    }
+
+    #[test]
+    fn rejects_files_before_sending() {
+        let mut webhook = Webhook::new("https://discord.com/api/webhooks/1/token");
+        for i in 0..11 {
+            webhook = webhook.add_file(format!("{}.txt", i), Vec::new());
+        }
+        assert!(matches!(Body::webhook(&webhook), Err(WebhookError::Validation(_))));
+
+        let webhook = Webhook::new("https://discord.com/api/webhooks/1/token")
+            .set_allowed_extensions(vec![String::from("png")])
+            .add_file("secrets.env", Vec::new());
+        assert!(matches!(Body::webhook(&webhook), Err(WebhookError::DisallowedFile(name)) if name == "secrets.env"));
+    }
+}
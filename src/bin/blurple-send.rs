@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use blurple_hook::{Embed, Webhook, WebhookError};
+use clap::Parser;
+use serde_json::{json, Map, Value};
+
+/// Send a Discord webhook from the command line.
+#[derive(Debug, Parser)]
+#[command(name = "blurple-send", version)]
+struct Args {
+    /// The webhook url
+    #[arg(long, conflicts_with = "env_var")]
+    url: Option<String>,
+    /// Read the webhook url from this environment variable
+    #[arg(long, default_value = "WEBHOOK_URL")]
+    env_var: String,
+    /// Message content
+    #[arg(long)]
+    content: Option<String>,
+    /// Embed title
+    #[arg(long)]
+    title: Option<String>,
+    /// Embed description
+    #[arg(long)]
+    description: Option<String>,
+    /// Embed field as name=value, may be repeated
+    #[arg(long = "field", value_parser = parse_field)]
+    fields: Vec<(String, String)>,
+    /// Embed colour as an integer, #RRGGBB, or a palette name such as blurple
+    #[arg(long)]
+    colour: Option<String>,
+    /// Read the content aloud with text-to-speech
+    #[arg(long)]
+    tts: bool,
+    /// Post without notifying anyone
+    #[arg(long)]
+    silent: bool,
+    /// Attach this file, may be repeated
+    #[arg(long = "file")]
+    files: Vec<PathBuf>,
+    /// Send this JSON payload as-is instead of building one from the other flags
+    #[arg(long, conflicts_with_all = ["content", "title", "description", "fields", "colour", "tts", "silent", "files"])]
+    json: Option<PathBuf>,
+}
+
+fn parse_field(field: &str) -> Result<(String, String), String> {
+    field
+        .split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected name=value, got \"{}\"", field))
+}
+
+fn build_embed(args: &Args) -> Result<Option<Embed>, WebhookError> {
+    if args.title.is_none() && args.description.is_none() && args.fields.is_empty() && args.colour.is_none() {
+        return Ok(None);
+    }
+
+    let mut embed = Map::new();
+    if let Some(title) = &args.title {
+        embed.insert("title".to_string(), json!(title));
+    }
+    if let Some(description) = &args.description {
+        embed.insert("description".to_string(), json!(description));
+    }
+    if let Some(colour) = &args.colour {
+        let colour = colour.parse::<u64>().map_or_else(|_| json!(colour), |int| json!(int));
+        embed.insert("color".to_string(), colour);
+    }
+    let fields = args
+        .fields
+        .iter()
+        .map(|(name, value)| json!({"name": name, "value": value, "inline": false}))
+        .collect::<Vec<_>>();
+    embed.insert("fields".to_string(), Value::Array(fields));
+
+    Embed::from_value(Value::Object(embed)).map(Some)
+}
+
+async fn run(args: Args) -> Result<(), String> {
+    let url = match &args.url {
+        Some(url) => url.clone(),
+        None => std::env::var(&args.env_var)
+            .map_err(|_| format!("no --url given and ${} is not set", args.env_var))?,
+    };
+
+    if let Some(path) = &args.json {
+        let payload = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let value: Value = serde_json::from_str(&payload).map_err(|err| format!("{}: {}", path.display(), err))?;
+        return Webhook::send_value(&url, &value).await.map(drop).map_err(describe);
+    }
+
+    let mut webhook = Webhook::new(&url);
+    if let Some(content) = &args.content {
+        webhook = webhook.set_content(content);
+    }
+    if let Some(embed) = build_embed(&args).map_err(describe)? {
+        webhook = webhook.add_embed(embed);
+    }
+    if args.tts {
+        webhook = webhook.set_tts(true);
+    }
+    if args.silent {
+        webhook = webhook.silent();
+    }
+    for path in &args.files {
+        webhook = webhook.add_file_from_path(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    }
+    webhook.validate().map_err(|err| err.to_string())?;
+    webhook.send().await.map(drop).map_err(describe)
+}
+
+/// Formats an error, preferring Discord's own message and field errors over the raw body.
+fn describe(err: WebhookError) -> String {
+    if let (WebhookError::Api { status, .. }, Some(discord)) = (&err, err.discord_error()) {
+        let mut description = format!("Discord returned {}: {} (code {})", status, discord.message, discord.code.code());
+        for field in &discord.errors {
+            description.push_str(&format!("\n  {}", field));
+        }
+        return description;
+    }
+    err.to_string()
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Args::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("blurple-send: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
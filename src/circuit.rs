@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::secret::SecretUrl;
+use crate::{DiscordErrorCode, WebhookError};
+
+/// Stops sending to a webhook url after repeated failures.
+///
+/// After `failure_threshold` consecutive failures the circuit for that url opens, and sends fail
+/// straight away with [`WebhookError::CircuitOpen`] until `cooldown` has passed. The circuit is
+/// then half open: the next result decides whether it closes again or reopens for another
+/// cooldown. Connection errors, server errors and 401, 403 and 404 responses count as failures;
+/// rejected payloads and rate limits don't.
+///
+/// Clones share the same circuits. Attach one with [`HttpSender::circuit_breaker`](crate::HttpSender::circuit_breaker).
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    circuits: Arc<Mutex<HashMap<SecretUrl, Circuit>>>,
+}
+
+#[derive(Debug, Default)]
+struct Circuit {
+    failures: u32,
+    opened_at: Option<Instant>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CircuitState {
+    /// Sends go through.
+    Closed,
+    /// Sends fail without a request being made.
+    Open,
+    /// The cooldown has passed; the next send decides whether the circuit closes.
+    HalfOpen,
+}
+
+impl Default for CircuitBreaker {
+    /// Opens after 5 consecutive failures, for a minute.
+    fn default() -> Self {
+        Self::new(5, Duration::from_secs(60))
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            circuits: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn state(&self, url: &str) -> CircuitState {
+        let circuits = self.circuits.lock().unwrap();
+        match circuits.get(circuit_key(url)).and_then(|circuit| circuit.opened_at) {
+            None => CircuitState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.cooldown => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Closes the circuit for a url, e.g. after fixing the webhook.
+    pub fn reset(&self, url: &str) {
+        self.circuits.lock().unwrap().remove(circuit_key(url));
+    }
+
+    /// Fails if the circuit for the url is open.
+    pub(crate) fn check(&self, url: &str) -> Result<(), WebhookError> {
+        let circuits = self.circuits.lock().unwrap();
+        if let Some(opened_at) = circuits.get(circuit_key(url)).and_then(|circuit| circuit.opened_at) {
+            let elapsed = opened_at.elapsed();
+            if elapsed < self.cooldown {
+                return Err(WebhookError::CircuitOpen { retry_after: self.cooldown - elapsed });
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn record<T>(&self, url: &str, result: &Result<T, WebhookError>) {
+        let mut circuits = self.circuits.lock().unwrap();
+        match result {
+            Ok(_) => {
+                circuits.remove(circuit_key(url));
+            },
+            Err(err) if is_failure(err) => {
+                let circuit = circuits.entry(SecretUrl::from(circuit_key(url))).or_default();
+                circuit.failures += 1;
+                if circuit.failures >= self.failure_threshold {
+                    circuit.opened_at = Some(Instant::now());
+                }
+            },
+            Err(_) => {},
+        }
+    }
+}
+
+/// Message edits and deletes share the circuit of their webhook.
+fn circuit_key(url: &str) -> &str {
+    url.split("/messages/").next().unwrap_or(url)
+}
+
+fn is_failure(err: &WebhookError) -> bool {
+    match err {
+        WebhookError::Http(_) => true,
+        // a deleted message says nothing about the webhook
+        WebhookError::Api { code: Some(DiscordErrorCode::UnknownMessage), .. } => false,
+        WebhookError::Api { status, .. } => matches!(status, 401 | 403 | 404) || *status >= 500,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::{CircuitBreaker, CircuitState};
+    use crate::message::MESSAGE_JSON;
+    use crate::{HttpSender, Webhook, WebhookError};
+
+    #[tokio::test]
+    async fn opens_after_consecutive_failures() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(404).set_body_string(r#"{"message": "Unknown Webhook", "code": 10015}"#))
+            .up_to_n_times(3)
+            .expect(3)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let url = format!("{}/api/webhooks/1/token", server.uri());
+        let breaker = CircuitBreaker::new(3, Duration::from_millis(200));
+        let sender = HttpSender::new().circuit_breaker(breaker.clone());
+        let webhook = Webhook::new(&url).set_content("Content Text");
+
+        for _ in 0..3 {
+            assert!(matches!(webhook.send_with(&sender).await, Err(WebhookError::Api { status: 404, .. })));
+        }
+        assert_eq!(breaker.state(&url), CircuitState::Open);
+        assert!(matches!(webhook.send_with(&sender).await, Err(WebhookError::CircuitOpen { .. })));
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(breaker.state(&url), CircuitState::HalfOpen);
+        webhook.send_with(&sender).await.unwrap();
+        assert_eq!(breaker.state(&url), CircuitState::Closed);
+    }
+
+    #[test]
+    fn payload_errors_do_not_count() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60));
+        let url = "https://discord.com/api/webhooks/1/token";
+        breaker.record::<()>(url, &Err(WebhookError::api(400, String::from(r#"{"code": 50035}"#))));
+        breaker.record::<()>(url, &Err(WebhookError::api(429, String::new())));
+        assert_eq!(breaker.state(url), CircuitState::Closed);
+
+        breaker.record::<()>(&format!("{}/messages/1", url), &Err(WebhookError::api(503, String::new())));
+        assert_eq!(breaker.state(url), CircuitState::Open);
+        breaker.reset(url);
+        assert_eq!(breaker.state(url), CircuitState::Closed);
+    }
+}
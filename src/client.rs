@@ -0,0 +1,250 @@
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::attachment::Body;
+use crate::secret::SecretUrl;
+use crate::{reqwest, HttpSender, Webhook, WebhookError, WebhookMessage, WebhookSender};
+
+/// A webhook url and the sender that delivers to it, kept apart from the payloads sent.
+///
+/// Any [`Webhook`] works as a payload. Its own url is ignored, so one payload, e.g. one built
+/// with [`Webhook::payload`], can go to several clients. Attached files are shared, not copied,
+/// between the requests. The connection pool, retry policy and
+/// rate limit buckets come from the sender, see [`HttpSender`].
+///
+/// ```no_run
+/// use blurple_hook::{Webhook, WebhookClient, WebhookError};
+///
+/// # async fn run() -> Result<(), WebhookError> {
+/// let alerts = WebhookClient::new("https://discord.com/api/webhooks/1/token");
+/// let audit = WebhookClient::new("https://discord.com/api/webhooks/2/token");
+/// let message = Webhook::payload().set_content("Deploy started");
+/// alerts.send(&message).await?;
+/// audit.send(&message).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WebhookClient<S = HttpSender> {
+    webhook_url: SecretUrl,
+    sender: S,
+    headers: Vec<(String, String)>,
+}
+
+impl WebhookClient {
+    pub fn new<U: AsRef<str>>(webhook_url: U) -> Self {
+        Self::with_sender(webhook_url, HttpSender::new())
+    }
+}
+
+impl<S: WebhookSender> WebhookClient<S> {
+    pub fn with_sender<U: AsRef<str>>(webhook_url: U, sender: S) -> Self {
+        Self {
+            webhook_url: webhook_url.as_ref().into(),
+            sender,
+            headers: Vec::new(),
+        }
+    }
+
+    /// Adds a header to every request made through this client. A header of the same name set
+    /// on the payload with [`Webhook::add_header`] takes precedence.
+    pub fn with_header<A: AsRef<str>, B: AsRef<str>>(mut self, name: A, value: B) -> Self {
+        let name = name.as_ref();
+        self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value.as_ref().to_string()));
+        self
+    }
+
+    pub fn sender(&self) -> &S {
+        &self.sender
+    }
+
+    /// The webhook url with its token redacted, safe to log.
+    pub fn redacted_url(&self) -> String {
+        self.webhook_url.redacted()
+    }
+
+    /// Sends the payload as a new message.
+    pub async fn send(&self, message: &Webhook) -> Result<WebhookMessage, WebhookError> {
+        self.target(message).send_with(&self.sender).await
+    }
+
+    /// Replaces a message sent through this webhook with the payload, see [`Webhook::edit`].
+    pub async fn edit(&self, message: &Webhook, message_id: &str) -> Result<WebhookMessage, WebhookError> {
+        self.target(message).edit_with(&self.sender, message_id).await
+    }
+
+    /// Deletes a message sent through this webhook, see [`Webhook::delete_message`].
+    pub async fn delete(&self, message_id: &str) -> Result<(), WebhookError> {
+        self.target(&Webhook::payload()).delete_message_with(&self.sender, message_id).await
+    }
+
+    fn target(&self, message: &Webhook) -> Webhook {
+        let mut webhook = message.clone();
+        webhook.webhook_url = self.webhook_url.clone();
+        for (name, value) in &self.headers {
+            if !webhook.headers.iter().any(|(n, _)| n.eq_ignore_ascii_case(name)) {
+                webhook.headers.push((name.clone(), value.clone()));
+            }
+        }
+        webhook
+    }
+}
+
+impl Webhook {
+    /// A payload without a url, for sending through a [`WebhookClient`] or [`Webhook::broadcast`].
+    /// Sending, editing or deleting with it directly fails with [`WebhookError::InvalidUrl`]
+    /// before any request is made.
+    pub fn payload() -> Self {
+        Self::new("")
+    }
+
+    /// Sends the payload to every url, at most `concurrency` at a time, ignoring the webhook's
+    /// own url. The body is serialized once.
+    ///
+    /// Fails before any request if the payload itself is invalid; otherwise returns each url's
+    /// result, in the order of `urls`.
+    pub async fn broadcast<U: AsRef<str>>(&self, urls: &[U], concurrency: usize) -> Result<Vec<Result<WebhookMessage, WebhookError>>, WebhookError> {
+        self.broadcast_with(&HttpSender::new(), urls, concurrency).await
+    }
+
+    /// Like [`Webhook::broadcast`], through a configured [`HttpSender`].
+    pub async fn broadcast_with<U: AsRef<str>>(&self, sender: &HttpSender, urls: &[U], concurrency: usize) -> Result<Vec<Result<WebhookMessage, WebhookError>>, WebhookError> {
+        let webhook = self.prepared();
+        let body = Arc::new(Body::webhook(&webhook)?);
+        let headers = Arc::new(webhook.header_map()?);
+        let query = Arc::new(webhook.query_params());
+        let sender = sender.for_webhook(&webhook).into_owned();
+        let limit = Arc::new(Semaphore::new(concurrency.max(1)));
+
+        let mut tasks = JoinSet::new();
+        for (i, url) in urls.iter().enumerate() {
+            let (sender, body, headers, query, limit) = (sender.clone(), body.clone(), headers.clone(), query.clone(), limit.clone());
+            let url = url.as_ref().to_string();
+            tasks.spawn(async move {
+                let _permit = limit.acquire_owned().await;
+                let delivery = sender.execute_body(reqwest::Method::POST, &url, &query, &headers, Body::clone(&body)).await;
+                (i, delivery.and_then(|delivery| delivery.message()))
+            });
+        }
+        let mut results: Vec<_> = urls.iter().map(|_| None).collect();
+        while let Some(joined) = tasks.join_next().await {
+            let (i, result) = joined.expect("broadcast task panicked");
+            results[i] = Some(result);
+        }
+        Ok(results.into_iter().flatten().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::WebhookClient;
+    use crate::{Webhook, WebhookError};
+
+    #[tokio::test]
+    async fn sends_one_payload_to_several_urls() {
+        let servers = [MockServer::start().await, MockServer::start().await];
+        for server in &servers {
+            Mock::given(method("POST"))
+                .and(path("/api/webhooks/1/token"))
+                .and(body_partial_json(serde_json::json!({"content": "Deploy started"})))
+                .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+                .expect(1)
+                .mount(server)
+                .await;
+        }
+
+        let message = Webhook::new("https://discord.com/api/webhooks/9/ignored").set_content("Deploy started");
+        for server in &servers {
+            let client = WebhookClient::new(format!("{}/api/webhooks/1/token", server.uri()));
+            client.send(&message).await.unwrap();
+        }
+        assert_eq!(
+            WebhookClient::new("https://discord.com/api/webhooks/1/secret").redacted_url(),
+            "https://discord.com/api/webhooks/1/[redacted]"
+        );
+    }
+
+    #[tokio::test]
+    async fn client_headers_are_merged_into_requests() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("X-Audit-Id", "deploy-7"))
+            .and(header("X-Gateway", "payload"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = WebhookClient::new(format!("{}/api/webhooks/1/token", server.uri()))
+            .with_header("X-Audit-Id", "deploy-7")
+            .with_header("X-Gateway", "client");
+        let message = Webhook::payload().set_content("Deploy started").add_header("x-gateway", "payload");
+        client.send(&message).await.unwrap();
+
+        let client = client.with_header("Host", "example.com");
+        assert!(matches!(client.send(&message).await, Err(crate::WebhookError::InvalidHeader(name)) if name == "Host"));
+    }
+
+    #[tokio::test]
+    async fn broadcasts_to_every_url() {
+        let server = MockServer::start().await;
+        for id in [1, 2, 3] {
+            Mock::given(method("POST"))
+                .and(path(format!("/api/webhooks/{}/token", id)))
+                .and(body_partial_json(serde_json::json!({"content": "Disk full on db-1"})))
+                .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("POST"))
+            .and(path("/api/webhooks/4/token"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({"message": "Unknown Webhook", "code": 10015})))
+            .mount(&server)
+            .await;
+
+        let urls: Vec<String> = (1..=4).map(|id| format!("{}/api/webhooks/{}/token", server.uri(), id)).collect();
+        let results = Webhook::payload().set_content("Disk full on db-1").broadcast(&urls, 2).await.unwrap();
+        assert_eq!(results.len(), 4);
+        assert!(results[..3].iter().all(Result::is_ok));
+        assert!(results[3].as_ref().unwrap_err().is_unknown_webhook());
+
+        assert!(Webhook::payload().set_content("a".repeat(2001)).broadcast(&urls, 2).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn edits_and_deletes_messages() {
+        let server = MockServer::start().await;
+        Mock::given(method("PATCH"))
+            .and(path("/api/webhooks/1/token/messages/2"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/webhooks/1/token/messages/2"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = WebhookClient::new(format!("{}/api/webhooks/1/token", server.uri()));
+        client.edit(&Webhook::payload().set_content("Deploy finished"), "2").await.unwrap();
+        client.delete("2").await.unwrap();
+        assert!(client.delete("not-an-id").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn payloads_need_a_client_to_be_sent() {
+        let message = Webhook::payload().set_content("Deploy started");
+        assert!(matches!(message.send().await, Err(WebhookError::InvalidUrl(reason)) if reason.contains("WebhookClient")));
+        assert!(matches!(message.edit("2").await, Err(WebhookError::InvalidUrl(_))));
+        assert!(matches!(message.delete_message("2").await, Err(WebhookError::InvalidUrl(_))));
+    }
+}
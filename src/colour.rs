@@ -0,0 +1,107 @@
+use serde::{Deserialize, Deserializer};
+use sha1::{Digest, Sha1};
+
+use crate::ColourType;
+
+/// Discord's brand colours, for [`crate::Embed::set_colour`] without spelling out the hex.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Colour {
+    Blurple,
+    Green,
+    Yellow,
+    Fuchsia,
+    Red,
+    White,
+    Black,
+    Greyple,
+}
+
+impl Colour {
+    pub fn value(self) -> usize {
+        match self {
+            Colour::Blurple => 0x5865F2,
+            Colour::Green => 0x57F287,
+            Colour::Yellow => 0xFEE75C,
+            Colour::Fuchsia => 0xEB459E,
+            Colour::Red => 0xED4245,
+            Colour::White => 0xFFFFFF,
+            Colour::Black => 0x000000,
+            Colour::Greyple => 0x99AAB5,
+        }
+    }
+}
+
+impl From<Colour> for ColourType<&'static str> {
+    fn from(colour: Colour) -> Self {
+        ColourType::Integer(colour.value())
+    }
+}
+
+/// Discord's brand palette, looked up case-insensitively by name.
+const PALETTE: [(&str, Colour); 8] = [
+    ("blurple", Colour::Blurple),
+    ("green", Colour::Green),
+    ("yellow", Colour::Yellow),
+    ("fuchsia", Colour::Fuchsia),
+    ("red", Colour::Red),
+    ("white", Colour::White),
+    ("black", Colour::Black),
+    ("greyple", Colour::Greyple),
+];
+
+/// The largest colour Discord accepts, as colours are 24-bit.
+pub(crate) const MAX_COLOUR: usize = 0xFFFFFF;
+/// What [`crate::Embed::set_colour`] uses for hex that doesn't parse.
+pub(crate) const FALLBACK_COLOUR: usize = 0x999999;
+
+/// The colour's value, or `None` for hex that doesn't parse. Not checked against [`MAX_COLOUR`].
+pub(crate) fn parse_colour<S: AsRef<str>>(colour: ColourType<S>) -> Option<usize> {
+    match colour {
+        ColourType::Hex(hex) => {
+            let hex = hex.as_ref();
+            // CSS style shorthand, `#FA0` for `#FFAA00`; without the `#`, `FA0` is still 0xFA0
+            if let Some(digits) = hex.strip_prefix('#').filter(|digits| digits.len() == 3) {
+                return usize::from_str_radix(&digits.chars().flat_map(|c| [c, c]).collect::<String>(), 16).ok();
+            }
+            usize::from_str_radix(hex.trim_start_matches('#').trim_start_matches("0x"), 16).ok()
+        },
+        ColourType::Integer(int) => Some(int),
+        ColourType::Rgb(r, g, b) => Some(usize::from(r) << 16 | usize::from(g) << 8 | usize::from(b)),
+        ColourType::FromSeed(seed) => {
+            let mut hasher = Sha1::new();
+            hasher.update(seed.as_ref().as_bytes());
+            usize::from_str_radix(&hex::encode(hasher.finalize())[0..6], 16).ok()
+        },
+    }
+}
+
+pub(crate) fn named_colour(name: &str) -> Option<usize> {
+    PALETTE
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, colour)| colour.value())
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawColour {
+    Integer(usize),
+    Text(String),
+}
+
+/// Accepts an embed colour as an integer, a `#RRGGBB` string, or a palette name.
+pub(crate) fn deserialize_colour<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<usize>, D::Error> {
+    use serde::de::Error;
+
+    let colour = match Option::<RawColour>::deserialize(deserializer)? {
+        Some(RawColour::Integer(int)) => int,
+        Some(RawColour::Text(text)) => match text.starts_with('#') {
+            true => parse_colour(ColourType::Hex(&text))
+                .ok_or_else(|| D::Error::custom(format!("invalid hex colour \"{}\"", text)))?,
+            false => named_colour(&text)
+                .ok_or_else(|| D::Error::custom(format!("unknown colour name \"{}\"", text)))?,
+        },
+        None => return Ok(None),
+    };
+    Ok(Some(colour))
+}
@@ -0,0 +1,707 @@
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::validation::{
+    check_count, check_length, MAX_ACTION_ROWS, MAX_COMPONENTS_V2, MAX_BUTTON_LABEL, MAX_CUSTOM_ID, MAX_ROW_COMPONENTS, MAX_SELECT_OPTIONS,
+    MAX_SELECT_OPTION_TEXT, MAX_SELECT_PLACEHOLDER,
+};
+use crate::{ValidationError, Webhook};
+
+const ACTION_ROW: u8 = 1;
+const BUTTON: u8 = 2;
+
+/// Something at the top level of a message's components: an [`ActionRow`], or with the
+/// `components-v2` feature, a [`Layout`](crate::layout::Layout).
+#[derive(Debug, Clone, Serialize, Eq, PartialEq)]
+#[serde(untagged)]
+pub enum TopLevelComponent {
+    ActionRow(ActionRow),
+    #[cfg(feature = "components-v2")]
+    Layout(crate::layout::Layout),
+}
+
+impl From<ActionRow> for TopLevelComponent {
+    fn from(row: ActionRow) -> Self {
+        TopLevelComponent::ActionRow(row)
+    }
+}
+
+impl<'de> Deserialize<'de> for TopLevelComponent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let component = match value.get("type").and_then(serde_json::Value::as_u64) {
+            Some(1) => serde_json::from_value(value).map(TopLevelComponent::ActionRow),
+            #[cfg(feature = "components-v2")]
+            Some(_) => serde_json::from_value(value).map(TopLevelComponent::Layout),
+            _ => return Err(D::Error::custom("unknown component type")),
+        };
+        component.map_err(D::Error::custom)
+    }
+}
+
+impl TopLevelComponent {
+    /// How many components this is towards Discord's limit, counting everything nested in it.
+    pub(crate) fn count(&self) -> usize {
+        match self {
+            TopLevelComponent::ActionRow(row) => 1 + row.components.len(),
+            #[cfg(feature = "components-v2")]
+            TopLevelComponent::Layout(layout) => layout.count(),
+        }
+    }
+
+    pub(crate) fn check(&self) -> Result<(), ValidationError> {
+        match self {
+            TopLevelComponent::ActionRow(row) => check_row(row),
+            #[cfg(feature = "components-v2")]
+            TopLevelComponent::Layout(layout) => layout.check(),
+        }
+    }
+
+    /// Whether this holds anything that sends an interaction, i.e. more than link buttons.
+    pub(crate) fn is_interactive(&self) -> bool {
+        match self {
+            TopLevelComponent::ActionRow(row) => row.components.iter().any(|component| match component {
+                Component::Button(button) => button.is_interactive(),
+                Component::SelectMenu(_) => true,
+            }),
+            #[cfg(feature = "components-v2")]
+            TopLevelComponent::Layout(layout) => layout.is_interactive(),
+        }
+    }
+}
+
+/// A row below the message, of up to 5 buttons or a single select menu.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ActionRow {
+    #[serde(rename = "type")]
+    _type: u8,
+    components: Vec<Component>,
+}
+
+/// Something in an [`ActionRow`].
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(untagged)]
+pub enum Component {
+    Button(Button),
+    SelectMenu(SelectMenu),
+}
+
+impl From<Button> for Component {
+    fn from(button: Button) -> Self {
+        Component::Button(button)
+    }
+}
+
+impl From<SelectMenu> for Component {
+    fn from(menu: SelectMenu) -> Self {
+        Component::SelectMenu(menu)
+    }
+}
+
+impl Default for ActionRow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ActionRow {
+    pub fn new() -> Self {
+        Self {
+            _type: ACTION_ROW,
+            components: Vec::new(),
+        }
+    }
+
+    pub fn add_button(mut self, button: Button) -> Self {
+        self.components.push(Component::Button(button));
+        self
+    }
+
+    /// Adds a select menu, which Discord only accepts alone in its row.
+    pub fn add_select_menu(mut self, menu: SelectMenu) -> Self {
+        self.components.push(Component::SelectMenu(menu));
+        self
+    }
+
+    pub fn components(&self) -> &[Component] {
+        &self.components
+    }
+
+    pub fn buttons(&self) -> impl Iterator<Item = &Button> {
+        self.components.iter().filter_map(|component| match component {
+            Component::Button(button) => Some(button),
+            Component::SelectMenu(_) => None,
+        })
+    }
+}
+
+/// How a button looks, and for [`ButtonStyle::Link`], that it opens a url instead of sending an
+/// interaction.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum ButtonStyle {
+    Primary,
+    Secondary,
+    Success,
+    Danger,
+    Link,
+}
+
+impl From<ButtonStyle> for u8 {
+    fn from(style: ButtonStyle) -> Self {
+        match style {
+            ButtonStyle::Primary => 1,
+            ButtonStyle::Secondary => 2,
+            ButtonStyle::Success => 3,
+            ButtonStyle::Danger => 4,
+            ButtonStyle::Link => 5,
+        }
+    }
+}
+
+impl TryFrom<u8> for ButtonStyle {
+    type Error = String;
+
+    fn try_from(style: u8) -> Result<Self, Self::Error> {
+        match style {
+            1 => Ok(ButtonStyle::Primary),
+            2 => Ok(ButtonStyle::Secondary),
+            3 => Ok(ButtonStyle::Success),
+            4 => Ok(ButtonStyle::Danger),
+            5 => Ok(ButtonStyle::Link),
+            style => Err(format!("unknown button style {}", style)),
+        }
+    }
+}
+
+/// A button component.
+///
+/// Link buttons work on any webhook. The other styles send an interaction with their
+/// `custom_id`, so they only work on webhooks owned by an application.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Button {
+    #[serde(rename = "type")]
+    _type: u8,
+    style: ButtonStyle,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    emoji: Option<Emoji>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    custom_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    disabled: bool,
+}
+
+impl Button {
+    pub fn new<S: AsRef<str>>(style: ButtonStyle, label: S) -> Self {
+        Self {
+            _type: BUTTON,
+            style,
+            label: Some(label.as_ref().to_string()),
+            emoji: None,
+            custom_id: None,
+            url: None,
+            disabled: false,
+        }
+    }
+
+    /// A button that opens `url`.
+    pub fn link<A: AsRef<str>, B: AsRef<str>>(label: A, url: B) -> Self {
+        Self::new(ButtonStyle::Link, label).set_url(url)
+    }
+
+    /// The id sent back in the interaction when the button is clicked.
+    pub fn set_custom_id<S: AsRef<str>>(mut self, custom_id: S) -> Self {
+        self.custom_id = Some(custom_id.as_ref().to_string());
+        self
+    }
+
+    pub fn set_url<S: AsRef<str>>(mut self, url: S) -> Self {
+        self.url = Some(url.as_ref().to_string());
+        self
+    }
+
+    pub fn set_emoji(mut self, emoji: Emoji) -> Self {
+        self.emoji = Some(emoji);
+        self
+    }
+
+    pub fn set_disabled(mut self, disabled: bool) -> Self {
+        self.disabled = disabled;
+        self
+    }
+
+    pub fn style(&self) -> ButtonStyle {
+        self.style
+    }
+
+    pub(crate) fn is_interactive(&self) -> bool {
+        self.style != ButtonStyle::Link
+    }
+
+    /// Link buttons need a url and can't have a `custom_id`; the other styles are the reverse.
+    pub(crate) fn check(&self) -> Result<(), ValidationError> {
+        if let Some(label) = &self.label {
+            check_length("button label", label, MAX_BUTTON_LABEL)?;
+        }
+        if let Some(custom_id) = &self.custom_id {
+            check_length("custom_id", custom_id, MAX_CUSTOM_ID)?;
+        }
+        let is_link = self.style == ButtonStyle::Link;
+        let reason = match (is_link, self.custom_id.is_some(), self.url.is_some()) {
+            (true, true, _) => "link buttons can't have a custom_id",
+            (true, false, false) => "link buttons need a url",
+            (false, _, true) => "only link buttons can have a url",
+            (false, false, false) => "buttons need a custom_id",
+            _ => return Ok(()),
+        };
+        Err(ValidationError::Invalid { field: "button", reason })
+    }
+}
+
+/// What a [`SelectMenu`] picks from.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum SelectMenuType {
+    /// The menu's own options.
+    String,
+    User,
+    Role,
+    /// Users and roles.
+    Mentionable,
+    Channel,
+}
+
+impl From<SelectMenuType> for u8 {
+    fn from(kind: SelectMenuType) -> Self {
+        match kind {
+            SelectMenuType::String => 3,
+            SelectMenuType::User => 5,
+            SelectMenuType::Role => 6,
+            SelectMenuType::Mentionable => 7,
+            SelectMenuType::Channel => 8,
+        }
+    }
+}
+
+impl TryFrom<u8> for SelectMenuType {
+    type Error = String;
+
+    fn try_from(kind: u8) -> Result<Self, Self::Error> {
+        match kind {
+            3 => Ok(SelectMenuType::String),
+            5 => Ok(SelectMenuType::User),
+            6 => Ok(SelectMenuType::Role),
+            7 => Ok(SelectMenuType::Mentionable),
+            8 => Ok(SelectMenuType::Channel),
+            kind => Err(format!("unknown select menu type {}", kind)),
+        }
+    }
+}
+
+/// A dropdown that sends an interaction with its `custom_id` and the picked values, so like
+/// non-link buttons it only works on webhooks owned by an application.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SelectMenu {
+    #[serde(rename = "type")]
+    kind: SelectMenuType,
+    custom_id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    options: Vec<SelectOption>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    channel_types: Vec<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    placeholder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_values: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_values: Option<u8>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    disabled: bool,
+}
+
+impl SelectMenu {
+    pub fn new<S: AsRef<str>>(kind: SelectMenuType, custom_id: S) -> Self {
+        Self {
+            kind,
+            custom_id: custom_id.as_ref().to_string(),
+            options: Vec::new(),
+            channel_types: Vec::new(),
+            placeholder: None,
+            min_values: None,
+            max_values: None,
+            disabled: false,
+        }
+    }
+
+    /// A menu of the options added with [`SelectMenu::add_option`].
+    pub fn string<S: AsRef<str>>(custom_id: S) -> Self {
+        Self::new(SelectMenuType::String, custom_id)
+    }
+
+    pub fn user<S: AsRef<str>>(custom_id: S) -> Self {
+        Self::new(SelectMenuType::User, custom_id)
+    }
+
+    pub fn role<S: AsRef<str>>(custom_id: S) -> Self {
+        Self::new(SelectMenuType::Role, custom_id)
+    }
+
+    pub fn mentionable<S: AsRef<str>>(custom_id: S) -> Self {
+        Self::new(SelectMenuType::Mentionable, custom_id)
+    }
+
+    pub fn channel<S: AsRef<str>>(custom_id: S) -> Self {
+        Self::new(SelectMenuType::Channel, custom_id)
+    }
+
+    /// Only for [`SelectMenuType::String`] menus, which need at least one.
+    pub fn add_option(mut self, option: SelectOption) -> Self {
+        self.options.push(option);
+        self
+    }
+
+    /// Limits a [`SelectMenuType::Channel`] menu to these Discord channel types, e.g. `0` for
+    /// text channels.
+    pub fn set_channel_types<I: IntoIterator<Item = u8>>(mut self, channel_types: I) -> Self {
+        self.channel_types = channel_types.into_iter().collect();
+        self
+    }
+
+    /// The text shown before anything is picked.
+    pub fn set_placeholder<S: AsRef<str>>(mut self, placeholder: S) -> Self {
+        self.placeholder = Some(placeholder.as_ref().to_string());
+        self
+    }
+
+    /// How many values must be picked, 1 by default, up to 25.
+    pub fn set_min_values(mut self, min_values: u8) -> Self {
+        self.min_values = Some(min_values);
+        self
+    }
+
+    /// How many values can be picked, 1 by default, up to 25.
+    pub fn set_max_values(mut self, max_values: u8) -> Self {
+        self.max_values = Some(max_values);
+        self
+    }
+
+    pub fn set_disabled(mut self, disabled: bool) -> Self {
+        self.disabled = disabled;
+        self
+    }
+
+    pub fn kind(&self) -> SelectMenuType {
+        self.kind
+    }
+
+    pub(crate) fn check(&self) -> Result<(), ValidationError> {
+        let invalid = |reason| Err(ValidationError::Invalid { field: "select menu", reason });
+        check_length("custom_id", &self.custom_id, MAX_CUSTOM_ID)?;
+        if let Some(placeholder) = &self.placeholder {
+            check_length("select menu placeholder", placeholder, MAX_SELECT_PLACEHOLDER)?;
+        }
+        match (self.kind, self.options.is_empty()) {
+            (SelectMenuType::String, true) => return invalid("string select menus need options"),
+            (SelectMenuType::String, false) => check_count("select menu options", self.options.len(), MAX_SELECT_OPTIONS)?,
+            (_, false) => return invalid("only string select menus can have options"),
+            (_, true) => {},
+        }
+        if !self.channel_types.is_empty() && self.kind != SelectMenuType::Channel {
+            return invalid("only channel select menus can have channel_types");
+        }
+        let (min, max) = (self.min_values.unwrap_or(1), self.max_values.unwrap_or(1));
+        if usize::from(min) > MAX_SELECT_OPTIONS || !(1..=MAX_SELECT_OPTIONS).contains(&usize::from(max)) {
+            return invalid("min_values and max_values must be at most 25, and max_values at least 1");
+        }
+        if min > max {
+            return invalid("min_values can't be more than max_values");
+        }
+        if self.kind == SelectMenuType::String && usize::from(min) > self.options.len() {
+            return invalid("min_values can't be more than the number of options");
+        }
+        for option in &self.options {
+            check_length("select option label", &option.label, MAX_SELECT_OPTION_TEXT)?;
+            check_length("select option value", &option.value, MAX_SELECT_OPTION_TEXT)?;
+            if let Some(description) = &option.description {
+                check_length("select option description", description, MAX_SELECT_OPTION_TEXT)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A choice in a [`SelectMenuType::String`] menu. `value` is what the interaction reports.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SelectOption {
+    label: String,
+    value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    emoji: Option<Emoji>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    default: bool,
+}
+
+impl SelectOption {
+    pub fn new<A: AsRef<str>, B: AsRef<str>>(label: A, value: B) -> Self {
+        Self {
+            label: label.as_ref().to_string(),
+            value: value.as_ref().to_string(),
+            description: None,
+            emoji: None,
+            default: false,
+        }
+    }
+
+    pub fn set_description<S: AsRef<str>>(mut self, description: S) -> Self {
+        self.description = Some(description.as_ref().to_string());
+        self
+    }
+
+    pub fn set_emoji(mut self, emoji: Emoji) -> Self {
+        self.emoji = Some(emoji);
+        self
+    }
+
+    /// Picks the option before the user does.
+    pub fn set_default(mut self, default: bool) -> Self {
+        self.default = default;
+        self
+    }
+}
+
+/// The emoji shown on a button: a unicode emoji, or a server's custom emoji.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Emoji {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    animated: bool,
+}
+
+impl Emoji {
+    pub fn unicode<S: AsRef<str>>(emoji: S) -> Self {
+        Self {
+            id: None,
+            name: Some(emoji.as_ref().to_string()),
+            animated: false,
+        }
+    }
+
+    pub fn custom<S: AsRef<str>>(name: S, id: u64, animated: bool) -> Self {
+        Self {
+            id: Some(id.to_string()),
+            name: Some(name.as_ref().to_string()),
+            animated,
+        }
+    }
+}
+
+/// Checks the buttons and select menus in a row that Discord would reject.
+pub(crate) fn check_row(row: &ActionRow) -> Result<(), ValidationError> {
+    check_count("action row", row.components.len(), MAX_ROW_COMPONENTS)?;
+    let has_menu = row.components.iter().any(|component| matches!(component, Component::SelectMenu(_)));
+    if has_menu && row.components.len() > 1 {
+        return Err(ValidationError::Invalid { field: "action row", reason: "a select menu must be alone in its row" });
+    }
+    for component in &row.components {
+        match component {
+            Component::Button(button) => button.check()?,
+            Component::SelectMenu(menu) => menu.check()?,
+        }
+    }
+    Ok(())
+}
+
+/// Checks a message's components. Classic messages allow 5 action rows; with
+/// `IS_COMPONENTS_V2`, 40 components in total, nested ones included.
+pub(crate) fn check_components(components: &[TopLevelComponent], components_v2: bool) -> Result<(), ValidationError> {
+    if components_v2 {
+        check_count("components", components.iter().map(TopLevelComponent::count).sum(), MAX_COMPONENTS_V2)?;
+    } else {
+        check_count("components", components.len(), MAX_ACTION_ROWS)?;
+        #[cfg(feature = "components-v2")]
+        if components.iter().any(|component| matches!(component, TopLevelComponent::Layout(_))) {
+            return Err(ValidationError::Invalid { field: "components", reason: "layouts need the IS_COMPONENTS_V2 flag" });
+        }
+    }
+    components.iter().try_for_each(TopLevelComponent::check)
+}
+
+impl Webhook {
+    /// Adds a row of buttons, or a select menu, below the message.
+    ///
+    /// Discord ignores components unless the request asks for them, so this also turns on
+    /// [`Webhook::set_with_components`].
+    pub fn add_component(mut self, row: ActionRow) -> Self {
+        self.components.push(TopLevelComponent::ActionRow(row));
+        self.with_components = true;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{check_row, ActionRow, Button, ButtonStyle, Emoji, SelectMenu, SelectOption};
+    use crate::{ValidationError, Webhook};
+
+    #[test]
+    fn rows_serialize_as_discord_components() {
+        let webhook = Webhook::new("https://discord.com/api/webhooks/1/token").add_component(
+            ActionRow::new()
+                .add_button(Button::link("Docs", "https://docs.rs/blurple_hook"))
+                .add_button(Button::new(ButtonStyle::Danger, "Rollback").set_custom_id("rollback").set_disabled(true)),
+        );
+        let payload = webhook.to_value().unwrap();
+        assert_eq!(payload["components"], json!([{
+            "type": 1,
+            "components": [
+                {"type": 2, "style": 5, "label": "Docs", "url": "https://docs.rs/blurple_hook"},
+                {"type": 2, "style": 4, "label": "Rollback", "custom_id": "rollback", "disabled": true},
+            ],
+        }]));
+    }
+
+    #[test]
+    fn round_trips_discord_documented_components() {
+        let documented = json!({
+            "type": 1,
+            "components": [
+                {"type": 2, "label": "Click me!", "style": 1, "custom_id": "click_one"},
+                {"type": 2, "label": "Docs", "style": 5, "url": "https://discord.com/developers/docs", "emoji": {"name": "📖"}},
+                {"type": 2, "label": "Party", "style": 3, "custom_id": "party", "emoji": {"id": "41771983429993937", "name": "blobparty", "animated": true}},
+            ],
+        });
+        let row: ActionRow = serde_json::from_value(documented.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&row).unwrap(), documented);
+
+        let built = ActionRow::new()
+            .add_button(Button::new(ButtonStyle::Primary, "Click me!").set_custom_id("click_one"))
+            .add_button(Button::link("Docs", "https://discord.com/developers/docs").set_emoji(Emoji::unicode("📖")))
+            .add_button(Button::new(ButtonStyle::Success, "Party").set_custom_id("party").set_emoji(Emoji::custom("blobparty", 41771983429993937, true)));
+        assert_eq!(built, row);
+    }
+
+    #[test]
+    fn rejects_malformed_buttons() {
+        let invalid = |button: Button| check_row(&ActionRow::new().add_button(button));
+        let reason = |reason| Err(ValidationError::Invalid { field: "button", reason });
+        assert_eq!(invalid(Button::link("Docs", "https://docs.rs").set_custom_id("docs")), reason("link buttons can't have a custom_id"));
+        assert_eq!(invalid(Button::new(ButtonStyle::Link, "Docs")), reason("link buttons need a url"));
+        assert_eq!(invalid(Button::new(ButtonStyle::Primary, "Go")), reason("buttons need a custom_id"));
+        assert_eq!(invalid(Button::new(ButtonStyle::Primary, "Go").set_url("https://docs.rs")), reason("only link buttons can have a url"));
+
+        let full_row = (0..6).fold(ActionRow::new(), |row, i| row.add_button(Button::new(ButtonStyle::Secondary, "Go").set_custom_id(i.to_string())));
+        assert_eq!(check_row(&full_row), Err(ValidationError::TooMany { field: "action row", count: 6, limit: 5 }));
+
+        assert!(invalid(Button::new(ButtonStyle::Primary, "a".repeat(80)).set_custom_id("c".repeat(100))).is_ok());
+        assert_eq!(
+            invalid(Button::new(ButtonStyle::Primary, "a".repeat(81)).set_custom_id("go")),
+            Err(ValidationError::TooLong { field: "button label", length: 81, limit: 80 })
+        );
+        assert_eq!(
+            invalid(Button::new(ButtonStyle::Primary, "Go").set_custom_id("c".repeat(101))),
+            Err(ValidationError::TooLong { field: "custom_id", length: 101, limit: 100 })
+        );
+    }
+
+    #[test]
+    fn interactive_components_need_with_components() {
+        let webhook = |button: Button| Webhook::new("https://discord.com/webhook").add_component(ActionRow::new().add_button(button));
+        let go = Button::new(ButtonStyle::Primary, "Go").set_custom_id("go");
+        assert!(webhook(go.clone()).validate().is_ok());
+        assert_eq!(
+            webhook(go).set_with_components(false).validate(),
+            Err(ValidationError::Invalid { field: "components", reason: "interactive components are dropped without with_components" })
+        );
+        assert!(webhook(Button::link("Docs", "https://docs.rs")).set_with_components(false).validate().is_ok());
+    }
+
+    #[test]
+    fn select_menus_serialize_and_round_trip() {
+        let menu = SelectMenu::string("env")
+            .set_placeholder("Deploy to")
+            .set_max_values(2)
+            .add_option(SelectOption::new("Staging", "staging").set_default(true))
+            .add_option(SelectOption::new("Production", "prod").set_description("Pages on-call").set_emoji(Emoji::unicode("🚀")));
+        let row = ActionRow::new().add_select_menu(menu);
+        let documented = json!({
+            "type": 1,
+            "components": [{
+                "type": 3,
+                "custom_id": "env",
+                "options": [
+                    {"label": "Staging", "value": "staging", "default": true},
+                    {"label": "Production", "value": "prod", "description": "Pages on-call", "emoji": {"name": "🚀"}},
+                ],
+                "placeholder": "Deploy to",
+                "max_values": 2,
+            }],
+        });
+        assert_eq!(serde_json::to_value(&row).unwrap(), documented);
+        assert_eq!(serde_json::from_value::<ActionRow>(documented).unwrap(), row);
+        assert_eq!(check_row(&row), Ok(()));
+
+        let channels = ActionRow::new().add_select_menu(SelectMenu::channel("log channel").set_channel_types([0, 5]).set_min_values(0));
+        assert_eq!(
+            serde_json::to_value(&channels).unwrap()["components"][0],
+            json!({"type": 8, "custom_id": "log channel", "channel_types": [0, 5], "min_values": 0})
+        );
+        assert_eq!(check_row(&channels), Ok(()));
+    }
+
+    #[test]
+    fn rejects_malformed_select_menus() {
+        let invalid = |row: ActionRow| check_row(&row);
+        let menu = |menu: SelectMenu| ActionRow::new().add_select_menu(menu);
+        let reason = |field, reason| Err(ValidationError::Invalid { field, reason });
+        let option = || SelectOption::new("Staging", "staging");
+
+        assert_eq!(invalid(menu(SelectMenu::string("env"))), reason("select menu", "string select menus need options"));
+        assert_eq!(invalid(menu(SelectMenu::user("who").add_option(option()))), reason("select menu", "only string select menus can have options"));
+        assert_eq!(invalid(menu(SelectMenu::role("roles").set_channel_types([0]))), reason("select menu", "only channel select menus can have channel_types"));
+        assert_eq!(invalid(menu(SelectMenu::user("who").set_min_values(3).set_max_values(2))), reason("select menu", "min_values can't be more than max_values"));
+        assert_eq!(
+            invalid(menu(SelectMenu::string("env").add_option(option()).set_min_values(2).set_max_values(2))),
+            reason("select menu", "min_values can't be more than the number of options")
+        );
+        assert!(invalid(menu(SelectMenu::user("who").set_max_values(26))).is_err());
+        assert_eq!(
+            invalid(menu(SelectMenu::string("env").add_option(SelectOption::new("a".repeat(101), "a")))),
+            Err(ValidationError::TooLong { field: "select option label", length: 101, limit: 100 })
+        );
+        assert_eq!(
+            invalid(menu(SelectMenu::user("who")).add_button(Button::link("Docs", "https://docs.rs"))),
+            reason("action row", "a select menu must be alone in its row")
+        );
+    }
+
+    #[tokio::test]
+    async fn adding_a_row_asks_for_components() {
+        use wiremock::matchers::{method, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(query_param("with_components", "true"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Webhook::new(format!("{}/api/webhooks/1/token", server.uri()))
+            .add_component(ActionRow::new().add_button(Button::link("Docs", "https://docs.rs/blurple_hook")))
+            .send()
+            .await
+            .unwrap();
+    }
+}
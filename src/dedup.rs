@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sha1::{Digest, Sha1};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::secret::SecretUrl;
+use crate::{Footer, HttpSender, Webhook, WebhookError, WebhookMessage, WebhookSender};
+
+/// Sends webhooks, but edits the previous message instead when the same payload is sent again.
+///
+/// Repeats within the window append `(repeated ×N)` to the content, or to the last embed's
+/// footer when there is no content. Each repeat restarts the window. A different payload, or a
+/// repeat after the window has passed, is sent as a new message. Repeats are tracked per
+/// webhook url. Services that don't return the created message can't be edited, so every send
+/// to them is a new message.
+///
+/// Sends to one url wait for each other, but a slow url doesn't hold up sends to the others.
+#[derive(Debug)]
+pub struct DedupingWebhook<S = HttpSender> {
+    sender: S,
+    window: Duration,
+    last: std::sync::Mutex<HashMap<SecretUrl, Arc<Mutex<Option<LastSent>>>>>,
+}
+
+#[derive(Debug)]
+struct LastSent {
+    fingerprint: Vec<u8>,
+    message_id: String,
+    webhook: Webhook,
+    count: u32,
+    seen_at: Instant,
+}
+
+impl DedupingWebhook {
+    pub fn new(window: Duration) -> Self {
+        Self::with_sender(HttpSender::new(), window)
+    }
+}
+
+impl<S: WebhookSender> DedupingWebhook<S> {
+    pub fn with_sender(sender: S, window: Duration) -> Self {
+        Self {
+            sender,
+            window,
+            last: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn sender(&self) -> &S {
+        &self.sender
+    }
+
+    /// Sends the webhook, or edits the last message sent to its url if this is a repeat.
+    pub async fn send(&self, webhook: &Webhook) -> Result<WebhookMessage, WebhookError> {
+        let fingerprint = Sha1::digest(serde_json::to_vec(webhook)?).to_vec();
+        let slot = {
+            let mut last = self.last.lock().unwrap();
+            Arc::clone(last.entry(webhook.webhook_url.clone()).or_default())
+        };
+
+        // held across the request so concurrent repeats to the url can't both send
+        let mut last = slot.lock().await;
+        if let Some(previous) = last.as_mut() {
+            if previous.fingerprint == fingerprint && previous.seen_at.elapsed() < self.window {
+                let count = previous.count + 1;
+                let repeated = with_repeat_counter(&previous.webhook, count);
+                match self.sender.edit(&repeated.prepared(), &previous.message_id).await {
+                    Ok(message) => {
+                        previous.count = count;
+                        previous.seen_at = Instant::now();
+                        return Ok(message);
+                    },
+                    // the message was deleted, so start over with a new one
+                    Err(err) if err.is_message_not_found() => {},
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+
+        let message = self.sender.send(&webhook.prepared()).await?;
+        *last = (!message.id.is_empty()).then(|| LastSent {
+            fingerprint,
+            message_id: message.id.clone(),
+            webhook: webhook.clone(),
+            count: 1,
+            seen_at: Instant::now(),
+        });
+        Ok(message)
+    }
+}
+
+fn with_repeat_counter(webhook: &Webhook, count: u32) -> Webhook {
+    let suffix = format!("(repeated ×{})", count);
+    let mut webhook = webhook.clone();
+    match (&mut webhook.content, webhook.embeds.last_mut()) {
+        (Some(content), _) if !content.is_empty() => *content = format!("{} {}", content, suffix),
+        (_, Some(embed)) => {
+            let footer = match embed.footer.as_deref() {
+                Some(footer) => Footer { text: format!("{} {}", footer.text, suffix), ..footer.clone() },
+                None => Footer { text: suffix, icon_url: None, proxy_icon_url: None },
+            };
+            embed.footer = Some(Arc::new(footer));
+        },
+        (content, None) => *content = Some(suffix),
+    }
+    webhook
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::DedupingWebhook;
+    use crate::{Embed, MockSender, Webhook, WebhookError, WebhookMessage, WebhookSender};
+
+    /// Never answers sends to webhook 2.
+    struct Hanging(MockSender);
+
+    impl WebhookSender for Hanging {
+        async fn send(&self, webhook: &Webhook) -> Result<WebhookMessage, WebhookError> {
+            if webhook.webhook_url.as_str().contains("/webhooks/2/") {
+                std::future::pending::<()>().await;
+            }
+            self.0.send(webhook).await
+        }
+
+        async fn edit(&self, webhook: &Webhook, message_id: &str) -> Result<WebhookMessage, WebhookError> {
+            self.0.edit(webhook, message_id).await
+        }
+
+        async fn delete(&self, webhook: &Webhook, message_id: &str) -> Result<(), WebhookError> {
+            self.0.delete(webhook, message_id).await
+        }
+    }
+
+    fn webhook(content: &str) -> Webhook {
+        Webhook::new("https://discord.com/api/webhooks/1/token").set_content(content)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn repeats_edit_the_first_message() {
+        let dedup = DedupingWebhook::with_sender(MockSender::new(), Duration::from_secs(60));
+        for _ in 0..3 {
+            dedup.send(&webhook("Build failed")).await.unwrap();
+        }
+
+        let sender = dedup.sender();
+        assert_eq!(sender.sent_webhooks(), vec![webhook("Build failed")]);
+        let edits = sender.edits();
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[0], (String::from("1"), webhook("Build failed (repeated ×2)")));
+        assert_eq!(edits[1], (String::from("1"), webhook("Build failed (repeated ×3)")));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn different_payloads_and_expired_windows_reset() {
+        let dedup = DedupingWebhook::with_sender(MockSender::new(), Duration::from_secs(60));
+        dedup.send(&webhook("Build failed")).await.unwrap();
+        dedup.send(&webhook("Build passed")).await.unwrap();
+        dedup.send(&webhook("Build failed")).await.unwrap();
+        tokio::time::advance(Duration::from_secs(61)).await;
+        dedup.send(&webhook("Build failed")).await.unwrap();
+
+        assert_eq!(dedup.sender().sent_webhooks().len(), 4);
+        assert!(dedup.sender().edits().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn embed_only_repeats_count_in_the_footer() {
+        let dedup = DedupingWebhook::with_sender(MockSender::new(), Duration::from_secs(60));
+        let webhook = Webhook::new("https://discord.com/api/webhooks/1/token")
+            .add_embed(Embed::new().set_title("Disk full").set_footer("host-1", None::<&str>, None::<&str>));
+        dedup.send(&webhook).await.unwrap();
+        dedup.send(&webhook).await.unwrap();
+
+        let (_, edited) = dedup.sender().edits().remove(0);
+        crate::assert_payload_matches!(edited, serde_json::json!({"embeds": [{"footer": {"text": "host-1 (repeated ×2)"}}]}));
+    }
+
+    #[tokio::test]
+    async fn responses_without_a_message_are_sent_again() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let dedup = DedupingWebhook::new(Duration::from_secs(60));
+        let webhook = Webhook::new(server.uri()).set_content("Build failed");
+        dedup.send(&webhook).await.unwrap();
+        dedup.send(&webhook).await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_hanging_url_does_not_block_the_others() {
+        let dedup = DedupingWebhook::with_sender(Hanging(MockSender::new()), Duration::from_secs(60));
+        let hanging = Webhook::new("https://discord.com/api/webhooks/2/token").set_content("Build failed");
+        let other = webhook("Build failed");
+        let sent = tokio::time::timeout(Duration::from_secs(1), async {
+            tokio::select! {
+                biased;
+                _ = dedup.send(&hanging) => unreachable!("webhook 2 never answers"),
+                sent = dedup.send(&other) => sent,
+            }
+        });
+        sent.await.expect("blocked by the hanging url").unwrap();
+    }
+}
@@ -0,0 +1,417 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+use crate::reqwest;
+use crate::{ValidationError, WebhookMessage};
+
+/// Everything that can go wrong sending a webhook.
+///
+/// Features add variants, such as `Middleware`, so matches need a wildcard arm.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WebhookError {
+    /// The payload could not be serialized to JSON.
+    Serialization(serde_json::Error),
+    /// The request could not be sent or the response could not be read.
+    Http(reqwest::Error),
+    /// Discord's response body was not the expected JSON.
+    InvalidResponse(serde_json::Error),
+    /// Discord responded with a non-success status. `discord` is the body parsed as Discord's
+    /// error JSON, when it is one.
+    Api { status: u16, body: String, code: Option<DiscordErrorCode>, discord: Option<DiscordApiError> },
+    /// The message being edited was deleted, or was never sent through this webhook.
+    MessageNotFound { message_id: String },
+    /// Discord rate limited the request without saying when to retry. The crate's senders no
+    /// longer return this; they wait a second and keep the body in [`WebhookError::RateLimited`].
+    MissingRetryAfter,
+    /// Discord was still rate limiting the request after the sender's retries ran out. `body` is
+    /// the last 429 response.
+    RateLimited { attempts: u32, waited: Duration, retry_after: Duration, body: String },
+    /// A custom header has an invalid name or value, or overrides a header the crate manages.
+    InvalidHeader(String),
+    /// A url is malformed. Webhook urls are reported redacted.
+    InvalidUrl(String),
+    /// The environment variable [`Webhook::from_env`](crate::Webhook::from_env) reads isn't set.
+    MissingEnvVar(String),
+    /// A message id isn't a Discord snowflake.
+    InvalidMessageId(String),
+    /// The webhook url failed too often recently, so the request wasn't made.
+    CircuitOpen { retry_after: Duration },
+    /// The payload breaks a Discord limit.
+    Validation(ValidationError),
+    /// An attached file's extension isn't in [`Webhook::set_allowed_extensions`](crate::Webhook::set_allowed_extensions).
+    DisallowedFile(String),
+    /// A strict (`try_`) builder method was given a value that breaks a Discord limit.
+    InvalidSetter { setter: &'static str, error: ValidationError },
+    /// A `reqwest-middleware` middleware failed the request.
+    #[cfg(feature = "middleware")]
+    Middleware(anyhow::Error),
+}
+
+/// A paginated send that stopped part way through.
+#[derive(Debug)]
+pub struct PaginatedSendError {
+    /// The messages sent before the failure, in order.
+    pub sent: Vec<WebhookMessage>,
+    pub error: WebhookError,
+}
+
+impl fmt::Display for PaginatedSendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed after sending {} messages, {}", self.sent.len(), self.error)
+    }
+}
+
+impl std::error::Error for PaginatedSendError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl WebhookError {
+    /// Builds an [`WebhookError::Api`] from a failed response, parsing Discord's error code from the body.
+    pub(crate) fn api(status: u16, body: String) -> Self {
+        let code = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|value| value.get("code").and_then(|code| code.as_u64()))
+            .map(DiscordErrorCode::from);
+        let discord = serde_json::from_str(&body).ok();
+        WebhookError::Api { status, body, code, discord }
+    }
+
+    /// The Discord error code, if Discord returned one.
+    pub fn discord_code(&self) -> Option<DiscordErrorCode> {
+        match self {
+            WebhookError::Api { code, .. } => *code,
+            _ => None,
+        }
+    }
+
+    pub fn is_unknown_webhook(&self) -> bool {
+        self.discord_code().is_some_and(|code| code.is_unknown_webhook())
+    }
+
+    /// Discord's error body parsed into its code, message and per-field errors, for an
+    /// [`WebhookError::Api`] whose body is Discord's JSON.
+    pub fn discord_error(&self) -> Option<&DiscordApiError> {
+        match self {
+            WebhookError::Api { discord, .. } => discord.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Whether Discord rate limited the request, as a 429 response or after the `retry`
+    /// feature's retries ran out.
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(self, WebhookError::Api { status: 429, .. } | WebhookError::RateLimited { .. } | WebhookError::MissingRetryAfter)
+    }
+
+    /// How long Discord asked to wait before retrying a rate limited request, from the 429
+    /// body's `retry_after`.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            WebhookError::RateLimited { retry_after, .. } => Some(*retry_after),
+            WebhookError::Api { status: 429, body, .. } => serde_json::from_str::<Value>(body)
+                .ok()
+                .and_then(|value| value.get("retry_after")?.as_f64())
+                .and_then(|secs| Duration::try_from_secs_f64(secs).ok()),
+            _ => None,
+        }
+    }
+
+    /// Whether the error says the message no longer exists, whichever sender reported it.
+    pub fn is_message_not_found(&self) -> bool {
+        matches!(self, WebhookError::MessageNotFound { .. }) || self.discord_code() == Some(DiscordErrorCode::UnknownMessage)
+    }
+}
+
+/// The JSON body of a failed Discord request.
+///
+/// ```
+/// use blurple_hook::DiscordApiError;
+///
+/// let body = r#"{"code": 50035, "message": "Invalid Form Body", "errors": {"embeds": {"0": {"title":
+///     {"_errors": [{"code": "BASE_TYPE_MAX_LENGTH", "message": "Must be 256 or fewer in length."}]}}}}}"#;
+/// let error: DiscordApiError = serde_json::from_str(body).unwrap();
+/// assert!(error.code.is_invalid_form_body());
+/// assert_eq!(error.errors[0].path, "embeds.0.title");
+/// ```
+#[derive(Debug, Clone, Deserialize, Eq, PartialEq)]
+pub struct DiscordApiError {
+    pub code: DiscordErrorCode,
+    pub message: String,
+    /// Each rejected field, flattened from Discord's nested `errors` object.
+    #[serde(default, deserialize_with = "field_errors")]
+    pub errors: Vec<FieldError>,
+}
+
+/// Why Discord rejected one field of the payload, with [`DiscordErrorCode::InvalidFormBody`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FieldError {
+    /// The field's path in the payload, joined with `.`, such as `embeds.0.title`.
+    pub path: String,
+    /// Discord's code for the problem, such as `BASE_TYPE_MAX_LENGTH`.
+    pub code: String,
+    pub message: String,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} ({})", self.path, self.message, self.code)
+    }
+}
+
+fn field_errors<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<FieldError>, D::Error> {
+    let mut errors = Vec::new();
+    collect_field_errors(&Value::deserialize(deserializer)?, "", &mut errors);
+    Ok(errors)
+}
+
+/// Walks the `errors` object, where each rejected field holds an `_errors` list.
+fn collect_field_errors(value: &Value, path: &str, errors: &mut Vec<FieldError>) {
+    let Some(object) = value.as_object() else {
+        return;
+    };
+    for (key, value) in object {
+        if key == "_errors" {
+            let text = |error: &Value, key| error.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+            errors.extend(value.as_array().into_iter().flatten().map(|error| FieldError {
+                path: path.to_string(),
+                code: text(error, "code"),
+                message: text(error, "message"),
+            }));
+        } else if path.is_empty() {
+            collect_field_errors(value, key, errors);
+        } else {
+            collect_field_errors(value, &format!("{}.{}", path, key), errors);
+        }
+    }
+}
+
+/// JSON error codes returned in Discord's error bodies.
+///
+/// | Code  | Variant              | Meaning                                        |
+/// |-------|----------------------|------------------------------------------------|
+/// | 10003 | `UnknownChannel`     | The channel does not exist                     |
+/// | 10008 | `UnknownMessage`     | The message does not exist                     |
+/// | 10015 | `UnknownWebhook`     | The webhook was deleted or never existed       |
+/// | 30007 | `MaxWebhooks`        | The channel has reached its webhook limit (15) |
+/// | 50001 | `MissingAccess`      | The webhook cannot access the channel          |
+/// | 50006 | `EmptyMessage`       | Cannot send an empty message                   |
+/// | 50013 | `MissingPermissions` | Lacking permissions for the action             |
+/// | 50027 | `InvalidWebhookToken`| The webhook token is invalid                   |
+/// | 50035 | `InvalidFormBody`    | The payload failed Discord's validation        |
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, Deserialize, Eq, PartialEq)]
+#[serde(from = "u64")]
+pub enum DiscordErrorCode {
+    UnknownChannel,
+    UnknownMessage,
+    UnknownWebhook,
+    MaxWebhooks,
+    MissingAccess,
+    EmptyMessage,
+    MissingPermissions,
+    InvalidWebhookToken,
+    InvalidFormBody,
+    Unknown(u64),
+}
+
+impl DiscordErrorCode {
+    pub fn code(&self) -> u64 {
+        match self {
+            DiscordErrorCode::UnknownChannel => 10003,
+            DiscordErrorCode::UnknownMessage => 10008,
+            DiscordErrorCode::UnknownWebhook => 10015,
+            DiscordErrorCode::MaxWebhooks => 30007,
+            DiscordErrorCode::MissingAccess => 50001,
+            DiscordErrorCode::EmptyMessage => 50006,
+            DiscordErrorCode::MissingPermissions => 50013,
+            DiscordErrorCode::InvalidWebhookToken => 50027,
+            DiscordErrorCode::InvalidFormBody => 50035,
+            DiscordErrorCode::Unknown(code) => *code,
+        }
+    }
+
+    /// Whether the webhook itself is gone or unusable, so retrying will never succeed.
+    pub fn is_unknown_webhook(&self) -> bool {
+        matches!(self, DiscordErrorCode::UnknownWebhook | DiscordErrorCode::InvalidWebhookToken)
+    }
+
+    pub fn is_invalid_form_body(&self) -> bool {
+        matches!(self, DiscordErrorCode::InvalidFormBody)
+    }
+}
+
+impl From<u64> for DiscordErrorCode {
+    fn from(code: u64) -> Self {
+        match code {
+            10003 => DiscordErrorCode::UnknownChannel,
+            10008 => DiscordErrorCode::UnknownMessage,
+            10015 => DiscordErrorCode::UnknownWebhook,
+            30007 => DiscordErrorCode::MaxWebhooks,
+            50001 => DiscordErrorCode::MissingAccess,
+            50006 => DiscordErrorCode::EmptyMessage,
+            50013 => DiscordErrorCode::MissingPermissions,
+            50027 => DiscordErrorCode::InvalidWebhookToken,
+            50035 => DiscordErrorCode::InvalidFormBody,
+            code => DiscordErrorCode::Unknown(code),
+        }
+    }
+}
+
+impl fmt::Display for WebhookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebhookError::Serialization(err) => write!(f, "Failed to serialize webhook, {}", err),
+            WebhookError::Http(err) => write!(f, "Failed to send request, {}", err),
+            WebhookError::InvalidResponse(err) => write!(f, "Failed to read response, {}", err),
+            WebhookError::Api { body, .. } => write!(f, "Failed to send request, {}", body),
+            WebhookError::MessageNotFound { message_id } => write!(f, "Message {} no longer exists", message_id),
+            WebhookError::MissingRetryAfter => write!(f, "Missing \"Retry After\" header"),
+            WebhookError::RateLimited { attempts, waited, .. } => write!(
+                f,
+                "Still rate limited after {} attempts, waited {:?}",
+                attempts,
+                waited
+            ),
+            WebhookError::InvalidHeader(name) => write!(f, "Invalid or reserved header, {}", name),
+            WebhookError::InvalidUrl(url) => write!(f, "Invalid url, {}", url),
+            WebhookError::MissingEnvVar(name) => write!(f, "Environment variable {} is not set", name),
+            WebhookError::InvalidMessageId(id) => write!(f, "Invalid message id, {:?}", id),
+            WebhookError::CircuitOpen { retry_after } => {
+                write!(f, "Webhook url is failing, retry in {:.1} seconds", retry_after.as_secs_f64())
+            },
+            WebhookError::Validation(err) => write!(f, "Invalid payload, {}", err),
+            WebhookError::DisallowedFile(filename) => write!(f, "File type not allowed, {}", filename),
+            WebhookError::InvalidSetter { setter, error } => write!(f, "{}: {}", setter, error),
+            #[cfg(feature = "middleware")]
+            WebhookError::Middleware(err) => write!(f, "Middleware failed the request, {}", err),
+        }
+    }
+}
+
+impl std::error::Error for WebhookError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WebhookError::Serialization(err) => Some(err),
+            WebhookError::Http(err) => Some(err),
+            WebhookError::InvalidResponse(err) => Some(err),
+            WebhookError::Validation(err) => Some(err),
+            WebhookError::InvalidSetter { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ValidationError> for WebhookError {
+    fn from(err: ValidationError) -> Self {
+        WebhookError::Validation(err)
+    }
+}
+
+impl From<serde_json::Error> for WebhookError {
+    fn from(err: serde_json::Error) -> Self {
+        WebhookError::Serialization(err)
+    }
+}
+
+#[cfg(feature = "middleware")]
+impl From<reqwest_middleware::Error> for WebhookError {
+    fn from(err: reqwest_middleware::Error) -> Self {
+        match err {
+            reqwest_middleware::Error::Reqwest(err) => err.into(),
+            reqwest_middleware::Error::Middleware(err) => WebhookError::Middleware(err),
+        }
+    }
+}
+
+impl From<reqwest::Error> for WebhookError {
+    fn from(err: reqwest::Error) -> Self {
+        // reqwest includes the url, and so the webhook token, in its error messages
+        WebhookError::Http(err.without_url())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{DiscordApiError, DiscordErrorCode, FieldError, WebhookError};
+
+    #[test]
+    fn maps_error_bodies_to_codes() {
+        let cases = [
+            (r#"{"message": "Unknown Webhook", "code": 10015}"#, Some(DiscordErrorCode::UnknownWebhook)),
+            (r#"{"message": "Cannot send an empty message", "code": 50006}"#, Some(DiscordErrorCode::EmptyMessage)),
+            (r#"{"message": "Invalid Form Body", "code": 50035, "errors": {}}"#, Some(DiscordErrorCode::InvalidFormBody)),
+            (r#"{"message": "Maximum number of webhooks reached (15)", "code": 30007}"#, Some(DiscordErrorCode::MaxWebhooks)),
+            (r#"{"message": "Something new", "code": 99999}"#, Some(DiscordErrorCode::Unknown(99999))),
+            ("<html>Bad Gateway</html>", None),
+        ];
+        for (body, expected) in cases {
+            assert_eq!(WebhookError::api(400, body.to_string()).discord_code(), expected, "{}", body);
+        }
+    }
+
+    #[test]
+    fn unknown_webhook_predicate() {
+        assert!(WebhookError::api(404, r#"{"code": 10015}"#.to_string()).is_unknown_webhook());
+        assert!(!WebhookError::api(400, r#"{"code": 50035}"#.to_string()).is_unknown_webhook());
+        assert_eq!(DiscordErrorCode::from(50035).code(), 50035);
+    }
+
+    #[test]
+    fn reads_retry_after_from_rate_limits() {
+        let limited = WebhookError::api(429, String::from(r#"{"message": "You are being rate limited.", "retry_after": 1.5, "global": false}"#));
+        assert!(limited.is_rate_limited());
+        assert_eq!(limited.retry_after(), Some(Duration::from_millis(1500)));
+        assert!(WebhookError::MissingRetryAfter.is_rate_limited());
+        assert_eq!(WebhookError::MissingRetryAfter.retry_after(), None);
+        assert!(!WebhookError::api(400, String::from(r#"{"retry_after": 1.5}"#)).is_rate_limited());
+        assert_eq!(WebhookError::api(400, String::from(r#"{"retry_after": 1.5}"#)).retry_after(), None);
+    }
+
+    #[test]
+    fn parses_field_errors() {
+        let body = r#"{
+            "code": 50035,
+            "errors": {
+                "content": {"_errors": [{"code": "BASE_TYPE_MAX_LENGTH", "message": "Must be 2000 or fewer in length."}]},
+                "embeds": {"0": {"fields": {"1": {"value": {"_errors": [
+                    {"code": "BASE_TYPE_REQUIRED", "message": "This field is required"}
+                ]}}}}}
+            },
+            "message": "Invalid Form Body"
+        }"#;
+        let err = WebhookError::api(400, body.to_string());
+        assert!(matches!(&err, WebhookError::Api { discord: Some(discord), .. } if discord.message == "Invalid Form Body"));
+        let error = err.discord_error().unwrap();
+        assert_eq!(error.code, DiscordErrorCode::InvalidFormBody);
+        assert_eq!(error.message, "Invalid Form Body");
+        assert_eq!(
+            error.errors,
+            vec![
+                FieldError {
+                    path: String::from("content"),
+                    code: String::from("BASE_TYPE_MAX_LENGTH"),
+                    message: String::from("Must be 2000 or fewer in length."),
+                },
+                FieldError {
+                    path: String::from("embeds.0.fields.1.value"),
+                    code: String::from("BASE_TYPE_REQUIRED"),
+                    message: String::from("This field is required"),
+                },
+            ]
+        );
+        assert_eq!(error.errors[1].to_string(), "embeds.0.fields.1.value: This field is required (BASE_TYPE_REQUIRED)");
+
+        let unknown: DiscordApiError = serde_json::from_str(r#"{"message": "Unknown Webhook", "code": 10015}"#).unwrap();
+        assert!(unknown.errors.is_empty());
+        assert!(WebhookError::api(502, String::from("<html>Bad Gateway</html>")).discord_error().is_none());
+        assert!(WebhookError::MissingRetryAfter.discord_error().is_none());
+    }
+}
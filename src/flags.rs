@@ -0,0 +1,105 @@
+use std::ops::{BitOr, BitOrAssign};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Webhook;
+
+/// The message flags a webhook can set, combined with `|`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Eq, PartialEq, Hash)]
+#[serde(transparent)]
+pub struct MessageFlags(u64);
+
+impl MessageFlags {
+    /// Don't show embeds for links in the content.
+    pub const SUPPRESS_EMBEDS: Self = Self(1 << 2);
+    /// Don't notify anyone, though mentions still highlight the message.
+    pub const SUPPRESS_NOTIFICATIONS: Self = Self(1 << 12);
+    /// Lay the message out with components alone, replacing content and embeds. Set by
+    /// `Webhook::add_layout` with the `components-v2` feature.
+    pub const IS_COMPONENTS_V2: Self = Self(1 << 15);
+
+    pub fn empty() -> Self {
+        Self(0)
+    }
+
+    pub fn bits(&self) -> u64 {
+        self.0
+    }
+
+    pub fn contains(&self, flags: Self) -> bool {
+        self.0 & flags.0 == flags.0
+    }
+
+    pub(crate) fn without(self, flags: Self) -> Self {
+        Self(self.0 & !flags.0)
+    }
+}
+
+impl BitOr for MessageFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for MessageFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl Webhook {
+    /// Reads the content aloud with text-to-speech.
+    pub fn set_tts(mut self, tts: bool) -> Self {
+        self.tts = tts;
+        self
+    }
+
+    /// Replaces the message flags.
+    pub fn set_flags(mut self, flags: MessageFlags) -> Self {
+        self.flags = Some(flags);
+        self
+    }
+
+    /// Hides the embeds Discord would show for links in the content.
+    pub fn suppress_embeds(self) -> Self {
+        self.add_flags(MessageFlags::SUPPRESS_EMBEDS)
+    }
+
+    /// Posts without notifying anyone, so the message lands in the channel without a ping.
+    pub fn silent(self) -> Self {
+        self.add_flags(MessageFlags::SUPPRESS_NOTIFICATIONS)
+    }
+
+    pub(crate) fn add_flags(mut self, flags: MessageFlags) -> Self {
+        *self.flags.get_or_insert_with(MessageFlags::empty) |= flags;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::MessageFlags;
+    use crate::{assert_payload_matches, Webhook};
+
+    #[test]
+    fn flags_and_tts_are_sent_when_set() {
+        let webhook = Webhook::new("https://discord.com/api/webhooks/1/token").set_content("3 AM disk alert");
+        let payload = webhook.to_value().unwrap();
+        assert!(payload.get("tts").is_none());
+        assert!(payload.get("flags").is_none());
+
+        let silent = webhook.clone().silent().suppress_embeds();
+        assert_payload_matches!(silent, json!({"flags": 4100}));
+        assert!(silent.to_value().unwrap().get("tts").is_none());
+
+        let flags = MessageFlags::SUPPRESS_EMBEDS | MessageFlags::SUPPRESS_NOTIFICATIONS;
+        assert!(flags.contains(MessageFlags::SUPPRESS_NOTIFICATIONS));
+        assert!(!MessageFlags::SUPPRESS_EMBEDS.contains(flags));
+        assert_eq!(webhook.clone().set_flags(flags), silent);
+        assert_payload_matches!(webhook.set_tts(true), json!({"tts": true}));
+    }
+}
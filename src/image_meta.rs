@@ -0,0 +1,191 @@
+//! Reads pixel dimensions from image headers, without decoding the image.
+
+use crate::Embed;
+
+/// The width and height of a PNG, JPEG, GIF or WebP image, or `None` for anything else.
+pub fn image_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png(bytes)
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        gif(bytes)
+    } else if bytes.starts_with(&[0xFF, 0xD8]) {
+        jpeg(bytes)
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        webp(bytes)
+    } else {
+        None
+    }
+}
+
+fn be16(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?)))
+}
+
+fn le16(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?)))
+}
+
+fn le24(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 3)?;
+    Some(u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16)
+}
+
+fn png(bytes: &[u8]) -> Option<(u32, u32)> {
+    // the IHDR chunk always comes first
+    if bytes.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+    Some((width, height))
+}
+
+fn gif(bytes: &[u8]) -> Option<(u32, u32)> {
+    Some((le16(bytes, 6)?, le16(bytes, 8)?))
+}
+
+fn jpeg(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut at = 2;
+    loop {
+        if *bytes.get(at)? != 0xFF {
+            return None;
+        }
+        let marker = *bytes.get(at + 1)?;
+        match marker {
+            // padding before a marker
+            0xFF => at += 1,
+            // markers without a length
+            0x01 | 0xD0..=0xD7 => at += 2,
+            // start of frame, except the DHT, JPG and DAC markers that share the range
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                return Some((be16(bytes, at + 7)?, be16(bytes, at + 5)?));
+            },
+            // start of scan or end of image before any frame header
+            0xDA | 0xD9 => return None,
+            _ => at += 2 + be16(bytes, at + 2)? as usize,
+        }
+    }
+}
+
+fn webp(bytes: &[u8]) -> Option<(u32, u32)> {
+    match bytes.get(12..16)? {
+        // lossy: a 14 bit width and height after the frame's start code
+        b"VP8 " if bytes.get(23..26)? == [0x9D, 0x01, 0x2A] => {
+            Some((le16(bytes, 26)? & 0x3FFF, le16(bytes, 28)? & 0x3FFF))
+        },
+        // lossless: 14 bit width and height minus one, packed after the signature byte
+        b"VP8L" if *bytes.get(20)? == 0x2F => {
+            let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?);
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        },
+        // extended: 24 bit canvas width and height minus one
+        b"VP8X" => Some((le24(bytes, 24)? + 1, le24(bytes, 27)? + 1)),
+        _ => None,
+    }
+}
+
+impl Embed {
+    /// Fills in the width and height of an image or thumbnail that references `filename` as
+    /// `attachment://{filename}`, read from the file's header. Dimensions already set are kept,
+    /// and files in an unknown format are ignored.
+    pub fn attachment_dimensions<S: AsRef<str>>(mut self, filename: S, bytes: &[u8]) -> Self {
+        let reference = format!("attachment://{}", filename.as_ref());
+        let Some((width, height)) = image_dimensions(bytes) else {
+            return self;
+        };
+        let (width, height) = (width as usize, height as usize);
+        if let Some(image) = self.image.as_mut().filter(|image| image.url == reference) {
+            image.width.get_or_insert(width);
+            image.height.get_or_insert(height);
+        }
+        if let Some(thumbnail) = self.thumbnail.as_mut().filter(|thumbnail| thumbnail.url == reference) {
+            thumbnail.width.get_or_insert(width);
+            thumbnail.height.get_or_insert(height);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::image_dimensions;
+    use crate::{assert_payload_matches, Embed};
+
+    /// Header fixtures for a 640x480 image in each format.
+    fn fixtures() -> Vec<(&'static str, Vec<u8>)> {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&640u32.to_be_bytes());
+        png.extend_from_slice(&480u32.to_be_bytes());
+        png.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+        let mut gif = b"GIF89a".to_vec();
+        gif.extend_from_slice(&640u16.to_le_bytes());
+        gif.extend_from_slice(&480u16.to_le_bytes());
+
+        // SOI, an APP0 segment, then a baseline SOF0 frame header
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+        jpeg.extend_from_slice(b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+        jpeg.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08]);
+        jpeg.extend_from_slice(&480u16.to_be_bytes());
+        jpeg.extend_from_slice(&640u16.to_be_bytes());
+        jpeg.extend_from_slice(&[0x03, 0x01, 0x22, 0x00]);
+
+        let webp = |chunk: &[u8], data: &[u8]| {
+            let mut bytes = b"RIFF\0\0\0\0WEBP".to_vec();
+            bytes.extend_from_slice(chunk);
+            bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(data);
+            bytes
+        };
+        let mut lossy = vec![0x50, 0x02, 0x00, 0x9D, 0x01, 0x2A];
+        lossy.extend_from_slice(&640u16.to_le_bytes());
+        lossy.extend_from_slice(&480u16.to_le_bytes());
+        let lossless_bits: u32 = 639 | (479 << 14);
+        let mut lossless = vec![0x2F];
+        lossless.extend_from_slice(&lossless_bits.to_le_bytes());
+        let extended = [0x10, 0, 0, 0, 0x7F, 0x02, 0x00, 0xDF, 0x01, 0x00];
+
+        vec![
+            ("png", png),
+            ("gif", gif),
+            ("jpeg", jpeg),
+            ("webp lossy", webp(b"VP8 ", &lossy)),
+            ("webp lossless", webp(b"VP8L", &lossless)),
+            ("webp extended", webp(b"VP8X", &extended)),
+        ]
+    }
+
+    #[test]
+    fn reads_dimensions_from_headers() {
+        for (format, bytes) in fixtures() {
+            assert_eq!(image_dimensions(&bytes), Some((640, 480)), "{}", format);
+        }
+        assert_eq!(image_dimensions(b"%PDF-1.7"), None);
+        assert_eq!(image_dimensions(b"\x89PNG\r\n\x1a\n"), None);
+    }
+
+    #[test]
+    fn hints_land_in_the_payload() {
+        for (_, bytes) in fixtures() {
+            let embed = Embed::new()
+                .set_image("attachment://chart.bin", None::<&str>, None, None)
+                .set_thumbnail("https://example.com/logo.png", None::<&str>, None, None)
+                .attachment_dimensions("chart.bin", &bytes);
+            assert_payload_matches!(embed, json!({"image": {"width": 640, "height": 480}}));
+            assert_eq!(serde_json::to_value(&embed).unwrap()["thumbnail"], json!({"url": "https://example.com/logo.png"}));
+        }
+
+        let (_, png) = fixtures().remove(0);
+        let webhook = crate::Webhook::new("https://discord.com/api/webhooks/1/token")
+            .add_embed(Embed::new().set_image("attachment://chart.png", None::<&str>, None, None))
+            .add_file("chart.png", png);
+        assert_payload_matches!(webhook, json!({"embeds": [{"image": {"width": 640, "height": 480}}]}));
+
+        let unknown = Embed::new()
+            .set_image("attachment://notes.txt", None::<&str>, None, None)
+            .attachment_dimensions("notes.txt", b"hello");
+        assert_eq!(serde_json::to_value(&unknown).unwrap()["image"], json!({"url": "attachment://notes.txt"}));
+    }
+}
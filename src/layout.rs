@@ -0,0 +1,555 @@
+//! Components V2 layouts: text, sections, media galleries, files and separators, optionally
+//! grouped in containers, in place of content and embeds.
+//!
+//! Adding one with [`Webhook::add_layout`] sets [`MessageFlags::IS_COMPONENTS_V2`], after which
+//! Discord rejects the message if it also has content or embeds.
+//!
+//! ```
+//! use blurple_hook::layout::{Container, Section, Separator, TextDisplay, Thumbnail};
+//! use blurple_hook::{Colour, Webhook};
+//!
+//! let webhook = Webhook::new("https://discord.com/api/webhooks/1/token").add_layout(
+//!     Container::new()
+//!         .set_accent_colour(Colour::Green)
+//!         .add_component(TextDisplay::new("## Deploy finished"))
+//!         .add_component(Separator::new())
+//!         .add_component(Section::new(Thumbnail::new("https://example.com/logo.png")).add_text("`main` is live on production")),
+//! );
+//! assert!(webhook.validate().is_ok());
+//! ```
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::colour;
+use crate::flags::MessageFlags;
+use crate::validation::{check_count, check_length, MAX_GALLERY_ITEMS, MAX_SECTION_TEXT, MAX_TEXT_DISPLAY};
+use crate::{Button, ColourType, TopLevelComponent, ValidationError, Webhook};
+
+const SECTION: u8 = 9;
+const TEXT_DISPLAY: u8 = 10;
+const THUMBNAIL: u8 = 11;
+const MEDIA_GALLERY: u8 = 12;
+const FILE: u8 = 13;
+const SEPARATOR: u8 = 14;
+const CONTAINER: u8 = 17;
+
+/// A layout component, added to a message with [`Webhook::add_layout`] or to a [`Container`].
+#[derive(Debug, Clone, Serialize, Eq, PartialEq)]
+#[serde(untagged)]
+pub enum Layout {
+    Section(Section),
+    TextDisplay(TextDisplay),
+    MediaGallery(MediaGallery),
+    File(File),
+    Separator(Separator),
+    Container(Container),
+}
+
+macro_rules! layout_from {
+    ($($variant:ident),*) => {$(
+        impl From<$variant> for Layout {
+            fn from(component: $variant) -> Self {
+                Layout::$variant(component)
+            }
+        }
+
+        impl From<$variant> for TopLevelComponent {
+            fn from(component: $variant) -> Self {
+                TopLevelComponent::Layout(Layout::$variant(component))
+            }
+        }
+    )*};
+}
+
+layout_from!(Section, TextDisplay, MediaGallery, File, Separator, Container);
+
+impl From<Layout> for TopLevelComponent {
+    fn from(layout: Layout) -> Self {
+        TopLevelComponent::Layout(layout)
+    }
+}
+
+impl<'de> Deserialize<'de> for Layout {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let kind = value.get("type").and_then(serde_json::Value::as_u64).and_then(|kind| u8::try_from(kind).ok());
+        let layout = match kind {
+            Some(SECTION) => serde_json::from_value(value).map(Layout::Section),
+            Some(TEXT_DISPLAY) => serde_json::from_value(value).map(Layout::TextDisplay),
+            Some(MEDIA_GALLERY) => serde_json::from_value(value).map(Layout::MediaGallery),
+            Some(FILE) => serde_json::from_value(value).map(Layout::File),
+            Some(SEPARATOR) => serde_json::from_value(value).map(Layout::Separator),
+            Some(CONTAINER) => serde_json::from_value(value).map(Layout::Container),
+            _ => return Err(D::Error::custom("unknown layout component type")),
+        };
+        layout.map_err(D::Error::custom)
+    }
+}
+
+impl Layout {
+    /// How many components this is towards Discord's limit of 40, counting nested ones.
+    pub(crate) fn count(&self) -> usize {
+        match self {
+            Layout::Section(section) => 2 + section.components.len(),
+            Layout::Container(container) => 1 + container.components.iter().map(TopLevelComponent::count).sum::<usize>(),
+            Layout::TextDisplay(_) | Layout::MediaGallery(_) | Layout::File(_) | Layout::Separator(_) => 1,
+        }
+    }
+
+    pub(crate) fn check(&self) -> Result<(), ValidationError> {
+        match self {
+            Layout::Section(section) => {
+                if section.components.is_empty() {
+                    return Err(ValidationError::Invalid { field: "section", reason: "sections need text" });
+                }
+                check_count("section", section.components.len(), MAX_SECTION_TEXT)?;
+                section.components.iter().try_for_each(TextDisplay::check)?;
+                match &section.accessory {
+                    Accessory::Button(button) => button.check(),
+                    Accessory::Thumbnail(_) => Ok(()),
+                }
+            },
+            Layout::TextDisplay(text) => text.check(),
+            Layout::MediaGallery(gallery) => {
+                if gallery.items.is_empty() {
+                    return Err(ValidationError::Invalid { field: "media gallery", reason: "media galleries need items" });
+                }
+                check_count("media gallery", gallery.items.len(), MAX_GALLERY_ITEMS)
+            },
+            Layout::Container(container) => {
+                let nested = |component: &TopLevelComponent| matches!(component, TopLevelComponent::Layout(Layout::Container(_)));
+                if container.components.iter().any(nested) {
+                    return Err(ValidationError::Invalid { field: "container", reason: "containers can't be nested" });
+                }
+                container.components.iter().try_for_each(TopLevelComponent::check)
+            },
+            Layout::File(_) | Layout::Separator(_) => Ok(()),
+        }
+    }
+
+    pub(crate) fn is_interactive(&self) -> bool {
+        match self {
+            Layout::Section(section) => matches!(&section.accessory, Accessory::Button(button) if button.is_interactive()),
+            Layout::Container(container) => container.components.iter().any(TopLevelComponent::is_interactive),
+            Layout::TextDisplay(_) | Layout::MediaGallery(_) | Layout::File(_) | Layout::Separator(_) => false,
+        }
+    }
+}
+
+/// A url, or `attachment://{filename}` for a file attached with [`Webhook::add_file`].
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+struct Media {
+    url: String,
+}
+
+impl Media {
+    fn new<S: AsRef<str>>(url: S) -> Self {
+        Self { url: url.as_ref().to_string() }
+    }
+}
+
+/// Markdown text, up to 4000 characters.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct TextDisplay {
+    #[serde(rename = "type")]
+    _type: u8,
+    content: String,
+}
+
+impl TextDisplay {
+    pub fn new<S: AsRef<str>>(content: S) -> Self {
+        Self {
+            _type: TEXT_DISPLAY,
+            content: content.as_ref().to_string(),
+        }
+    }
+
+    fn check(&self) -> Result<(), ValidationError> {
+        check_length("text display", &self.content, MAX_TEXT_DISPLAY)
+    }
+}
+
+/// One to three paragraphs of text beside a thumbnail or button.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Section {
+    #[serde(rename = "type")]
+    _type: u8,
+    components: Vec<TextDisplay>,
+    accessory: Accessory,
+}
+
+/// What a [`Section`] shows beside its text.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(untagged)]
+pub enum Accessory {
+    Thumbnail(Thumbnail),
+    Button(Button),
+}
+
+impl From<Thumbnail> for Accessory {
+    fn from(thumbnail: Thumbnail) -> Self {
+        Accessory::Thumbnail(thumbnail)
+    }
+}
+
+impl From<Button> for Accessory {
+    fn from(button: Button) -> Self {
+        Accessory::Button(button)
+    }
+}
+
+impl Section {
+    pub fn new<A: Into<Accessory>>(accessory: A) -> Self {
+        Self {
+            _type: SECTION,
+            components: Vec::new(),
+            accessory: accessory.into(),
+        }
+    }
+
+    pub fn add_text<S: AsRef<str>>(mut self, content: S) -> Self {
+        self.components.push(TextDisplay::new(content));
+        self
+    }
+}
+
+/// A small image beside a [`Section`]'s text.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Thumbnail {
+    #[serde(rename = "type")]
+    _type: u8,
+    media: Media,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    spoiler: bool,
+}
+
+impl Thumbnail {
+    pub fn new<S: AsRef<str>>(url: S) -> Self {
+        Self {
+            _type: THUMBNAIL,
+            media: Media::new(url),
+            description: None,
+            spoiler: false,
+        }
+    }
+
+    /// Alt text for the image.
+    pub fn set_description<S: AsRef<str>>(mut self, description: S) -> Self {
+        self.description = Some(description.as_ref().to_string());
+        self
+    }
+
+    pub fn set_spoiler(mut self, spoiler: bool) -> Self {
+        self.spoiler = spoiler;
+        self
+    }
+}
+
+/// A grid of up to 10 images or videos.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct MediaGallery {
+    #[serde(rename = "type")]
+    _type: u8,
+    items: Vec<MediaGalleryItem>,
+}
+
+/// An image or video in a [`MediaGallery`].
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct MediaGalleryItem {
+    media: Media,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    spoiler: bool,
+}
+
+impl Default for MediaGallery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MediaGallery {
+    pub fn new() -> Self {
+        Self {
+            _type: MEDIA_GALLERY,
+            items: Vec::new(),
+        }
+    }
+
+    pub fn add_item(mut self, item: MediaGalleryItem) -> Self {
+        self.items.push(item);
+        self
+    }
+}
+
+impl MediaGalleryItem {
+    pub fn new<S: AsRef<str>>(url: S) -> Self {
+        Self {
+            media: Media::new(url),
+            description: None,
+            spoiler: false,
+        }
+    }
+
+    /// Alt text for the image or video.
+    pub fn set_description<S: AsRef<str>>(mut self, description: S) -> Self {
+        self.description = Some(description.as_ref().to_string());
+        self
+    }
+
+    pub fn set_spoiler(mut self, spoiler: bool) -> Self {
+        self.spoiler = spoiler;
+        self
+    }
+}
+
+/// A file attached with [`Webhook::add_file`], shown where the component is.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct File {
+    #[serde(rename = "type")]
+    _type: u8,
+    file: Media,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    spoiler: bool,
+}
+
+impl File {
+    pub fn new<S: AsRef<str>>(filename: S) -> Self {
+        Self {
+            _type: FILE,
+            file: Media::new(format!("attachment://{}", filename.as_ref())),
+            spoiler: false,
+        }
+    }
+
+    pub fn set_spoiler(mut self, spoiler: bool) -> Self {
+        self.spoiler = spoiler;
+        self
+    }
+}
+
+/// Space between components, with a line across it by default.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Separator {
+    #[serde(rename = "type")]
+    _type: u8,
+    #[serde(default = "divider")]
+    divider: bool,
+    #[serde(default)]
+    spacing: Spacing,
+}
+
+fn divider() -> bool {
+    true
+}
+
+/// How much space a [`Separator`] takes.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum Spacing {
+    #[default]
+    Small,
+    Large,
+}
+
+impl From<Spacing> for u8 {
+    fn from(spacing: Spacing) -> Self {
+        match spacing {
+            Spacing::Small => 1,
+            Spacing::Large => 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for Spacing {
+    type Error = String;
+
+    fn try_from(spacing: u8) -> Result<Self, Self::Error> {
+        match spacing {
+            1 => Ok(Spacing::Small),
+            2 => Ok(Spacing::Large),
+            spacing => Err(format!("unknown separator spacing {}", spacing)),
+        }
+    }
+}
+
+impl Default for Separator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Separator {
+    pub fn new() -> Self {
+        Self {
+            _type: SEPARATOR,
+            divider: divider(),
+            spacing: Spacing::Small,
+        }
+    }
+
+    /// Whether a line is drawn across the space.
+    pub fn set_divider(mut self, divider: bool) -> Self {
+        self.divider = divider;
+        self
+    }
+
+    pub fn set_spacing(mut self, spacing: Spacing) -> Self {
+        self.spacing = spacing;
+        self
+    }
+}
+
+/// A box around other components, like an embed, with an optional accent colour down its side.
+/// Holds anything but another container, including [`ActionRow`](crate::ActionRow)s.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Container {
+    #[serde(rename = "type")]
+    _type: u8,
+    components: Vec<TopLevelComponent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    accent_color: Option<usize>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    spoiler: bool,
+}
+
+impl Default for Container {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Container {
+    pub fn new() -> Self {
+        Self {
+            _type: CONTAINER,
+            components: Vec::new(),
+            accent_color: None,
+            spoiler: false,
+        }
+    }
+
+    pub fn add_component<C: Into<TopLevelComponent>>(mut self, component: C) -> Self {
+        self.components.push(component.into());
+        self
+    }
+
+    /// Parsed like [`Embed::set_colour`](crate::Embed::set_colour), falling back to grey for
+    /// hex that doesn't parse.
+    pub fn set_accent_colour<S: AsRef<str>, C: Into<ColourType<S>>>(mut self, colour: C) -> Self {
+        let colour = match colour::parse_colour(colour.into()) {
+            Some(colour) => colour.min(colour::MAX_COLOUR),
+            None => colour::FALLBACK_COLOUR,
+        };
+        self.accent_color = Some(colour);
+        self
+    }
+    pub fn set_accent_color<S: AsRef<str>, C: Into<ColourType<S>>>(self, color: C) -> Self {
+        self.set_accent_colour(color)
+    }
+
+    pub fn set_spoiler(mut self, spoiler: bool) -> Self {
+        self.spoiler = spoiler;
+        self
+    }
+}
+
+impl Webhook {
+    /// Adds a layout component, setting [`MessageFlags::IS_COMPONENTS_V2`] and
+    /// [`Webhook::set_with_components`]. The message can then only be made of components, so
+    /// sending it with content or embeds fails validation.
+    pub fn add_layout<C: Into<Layout>>(mut self, component: C) -> Self {
+        self.components.push(TopLevelComponent::Layout(component.into()));
+        self.with_components = true;
+        self.add_flags(MessageFlags::IS_COMPONENTS_V2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Container, File, Layout, MediaGallery, MediaGalleryItem, Section, Separator, Spacing, TextDisplay, Thumbnail};
+    use crate::{assert_payload_matches, ActionRow, Button, ButtonStyle, ColourType, Embed, MessageFlags, ValidationError, Webhook};
+
+    fn webhook() -> Webhook {
+        Webhook::new("https://discord.com/api/webhooks/1/token")
+    }
+
+    #[test]
+    fn layouts_serialize_and_round_trip() {
+        let container = Container::new()
+            .set_accent_colour(ColourType::Hex("#5865F2"))
+            .add_component(TextDisplay::new("## Nightly build"))
+            .add_component(Section::new(Thumbnail::new("https://example.com/logo.png").set_description("logo")).add_text("All 412 tests passed"))
+            .add_component(Separator::new().set_divider(false).set_spacing(Spacing::Large))
+            .add_component(MediaGallery::new().add_item(MediaGalleryItem::new("attachment://chart.png").set_spoiler(true)))
+            .add_component(File::new("report.txt"))
+            .add_component(ActionRow::new().add_button(Button::link("Logs", "https://ci.example.com/412")));
+        let documented = json!({
+            "type": 17,
+            "components": [
+                {"type": 10, "content": "## Nightly build"},
+                {
+                    "type": 9,
+                    "components": [{"type": 10, "content": "All 412 tests passed"}],
+                    "accessory": {"type": 11, "media": {"url": "https://example.com/logo.png"}, "description": "logo"},
+                },
+                {"type": 14, "divider": false, "spacing": 2},
+                {"type": 12, "items": [{"media": {"url": "attachment://chart.png"}, "spoiler": true}]},
+                {"type": 13, "file": {"url": "attachment://report.txt"}},
+                {"type": 1, "components": [{"type": 2, "style": 5, "label": "Logs", "url": "https://ci.example.com/412"}]},
+            ],
+            "accent_color": 0x5865F2,
+        });
+        assert_eq!(serde_json::to_value(&container).unwrap(), documented);
+        assert_eq!(serde_json::from_value::<Layout>(documented).unwrap(), Layout::Container(container.clone()));
+        assert_eq!(serde_json::from_value::<Separator>(json!({"type": 14})).unwrap(), Separator::new());
+
+        let webhook = webhook().add_file("report.txt", b"ok".to_vec()).add_layout(container);
+        assert_payload_matches!(webhook, json!({"flags": 32768, "components": [{"type": 17}]}));
+        assert!(webhook.validate().is_ok());
+        let round_tripped: Webhook = serde_json::from_value(webhook.to_value().unwrap()).unwrap();
+        assert_eq!(round_tripped.to_value().unwrap(), webhook.to_value().unwrap());
+    }
+
+    #[test]
+    fn rejects_malformed_layouts() {
+        let invalid = |field, reason| Err(ValidationError::Invalid { field, reason });
+        let text = || TextDisplay::new("hi");
+
+        assert_eq!(webhook().add_layout(text()).set_content("hi").validate(), invalid("components", "IS_COMPONENTS_V2 messages can't have content or embeds"));
+        assert_eq!(
+            webhook().add_layout(text()).add_embed(Embed::new().set_title("hi")).validate(),
+            invalid("components", "IS_COMPONENTS_V2 messages can't have content or embeds")
+        );
+        assert_eq!(
+            webhook().add_layout(text()).set_flags(MessageFlags::empty()).validate(),
+            invalid("components", "layouts need the IS_COMPONENTS_V2 flag")
+        );
+        assert_eq!(webhook().add_layout(Section::new(Thumbnail::new("https://example.com/a.png"))).validate(), invalid("section", "sections need text"));
+        assert_eq!(
+            webhook().add_layout(Section::new(Button::new(ButtonStyle::Primary, "Go"))).validate(),
+            invalid("section", "sections need text")
+        );
+        assert_eq!(
+            webhook().add_layout(Section::new(Button::new(ButtonStyle::Primary, "Go")).add_text("hi")).validate(),
+            invalid("button", "buttons need a custom_id")
+        );
+        assert_eq!(webhook().add_layout(MediaGallery::new()).validate(), invalid("media gallery", "media galleries need items"));
+        assert_eq!(webhook().add_layout(Container::new().add_component(Container::new())).validate(), invalid("container", "containers can't be nested"));
+        assert_eq!(
+            webhook().add_layout(TextDisplay::new("a".repeat(4001))).validate(),
+            Err(ValidationError::TooLong { field: "text display", length: 4001, limit: 4000 })
+        );
+
+        let crowded = (0..40).fold(Container::new(), |container, _| container.add_component(text()));
+        assert_eq!(webhook().add_layout(crowded).validate(), Err(ValidationError::TooMany { field: "components", count: 41, limit: 40 }));
+        let rows = (0..6).fold(webhook().add_layout(text()), |webhook, _| webhook.add_component(ActionRow::new()));
+        assert!(rows.validate().is_ok());
+    }
+}
@@ -1,7 +1,13 @@
-use chrono::prelude::{DateTime, Utc};
+use chrono::prelude::{DateTime, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
-use anyhow::format_err;
-use sha1::{Sha1, Digest};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::Duration;
+use attachment::{AttachmentInfo, File};
+use secret::SecretUrl;
+use validation::{check_count, check_embed, check_length, check_length_total, MAX_AUTHOR_NAME, MAX_CONTENT, MAX_DESCRIPTION, MAX_EMBEDS, MAX_EMBED_CHARACTERS, MAX_FIELDS, MAX_FIELD_NAME, MAX_FIELD_VALUE, MAX_FILES, MAX_FOOTER, MAX_THREAD_NAME, MAX_TITLE, MAX_USERNAME};
 
 #[cfg(feature = "reqwest")]
 use reqwest as reqwest;
@@ -9,166 +15,710 @@ use reqwest as reqwest;
 #[cfg(all(feature = "rquest", not(feature = "reqwest")))]
 use rquest as reqwest;
 
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, HOST};
+
+mod ansi;
+mod attachment;
+mod circuit;
+mod client;
+mod colour;
+mod component;
+mod dedup;
+mod error;
+mod flags;
+#[cfg(feature = "image-meta")]
+pub mod image_meta;
+#[cfg(feature = "components-v2")]
+pub mod layout;
+mod macros;
+pub mod markdown;
+mod mentions;
+mod message;
+mod progress;
+#[cfg(feature = "middleware")]
+mod middleware;
+mod rate_limit;
+#[cfg(feature = "retry")]
+mod retry;
+mod secret;
+mod sender;
+pub mod validation;
+mod webhook_url;
+#[cfg(any(test, feature = "test-util"))]
+mod mock;
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
+
+pub use ansi::{Ansi, AnsiBackground, AnsiBlock, AnsiStyle};
+pub use circuit::{CircuitBreaker, CircuitState};
+pub use client::WebhookClient;
+pub use colour::Colour;
+pub use component::{ActionRow, Button, ButtonStyle, Component, Emoji, SelectMenu, SelectMenuType, SelectOption, TopLevelComponent};
+pub use dedup::DedupingWebhook;
+#[doc(hidden)]
+pub use macros::validate_webhook_url as __validate_webhook_url;
+pub use error::{DiscordApiError, DiscordErrorCode, FieldError, PaginatedSendError, WebhookError};
+/// The crate's error, for `blurple_hook::Error` in signatures.
+pub type Error = WebhookError;
+pub use flags::MessageFlags;
+pub use mentions::{AllowedMentions, MentionType};
+pub use message::WebhookMessage;
+pub use validation::ValidationError;
+pub use webhook_url::{WebhookUrl, DEFAULT_WEBHOOK_ENV};
+pub use progress::{progress_bar, ProgressReporter};
+pub use rate_limit::{RateLimitBuckets, RateLimitConfig, RateLimitHeaders, RateLimitedWebhook, RateLimiter};
+#[cfg(feature = "retry")]
+pub use retry::RetryPolicy;
+pub use sender::{ClientConfig, HttpSender, WebhookSender, DEFAULT_USER_AGENT};
+#[cfg(any(test, feature = "test-util"))]
+pub use mock::MockSender;
+
 #[cfg(feature = "queue")]
 pub mod queue {
-    use std::collections::VecDeque;
+    use std::collections::{BTreeMap, HashMap};
     use std::sync::Arc;
     use std::time::Duration;
-    use tokio::sync::Mutex;
+    use tokio::sync::{mpsc, watch, Mutex};
     use tokio::task::JoinHandle;
-    use tokio::time::Instant;
-    use crate::Webhook;
+    use crate::secret::SecretUrl;
+    use crate::{ClientConfig, HttpSender, RateLimitConfig, RateLimiter, Webhook, WebhookError, WebhookMessage};
+
+    /// How long the queue waits before checking for newly queued webhooks.
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+    /// How many times a rate limited webhook is re-sent before its error is reported.
+    const MAX_REQUEUES: u32 = 3;
+    /// How long to wait after a 429 that doesn't say.
+    const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+    /// A queued webhook and how sending it went.
+    pub type QueueResult = (Webhook, Result<WebhookMessage, WebhookError>);
+
+    /// Which webhooks a url sends first. Within a priority, webhooks are sent in the order
+    /// they were queued.
+    #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+    pub enum Priority {
+        High,
+        #[default]
+        Normal,
+        Low,
+    }
+
+    /// Where a webhook waits: by priority, then by when it was queued.
+    type Position = (Priority, i64);
+
+    /// The webhooks waiting to be picked up by a started queue.
+    #[derive(Debug, Default)]
+    pub struct QueuedWebhooks {
+        webhooks: BTreeMap<Position, Webhook>,
+        /// The next position at the back of each priority, counting up.
+        back: i64,
+        /// The next position at the front of each priority, counting down.
+        front: i64,
+    }
+
+    impl QueuedWebhooks {
+        pub fn len(&self) -> usize {
+            self.webhooks.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.webhooks.is_empty()
+        }
+
+        fn push_back(&mut self, webhook: Webhook, priority: Priority) {
+            self.webhooks.insert((priority, self.back), webhook);
+            self.back += 1;
+        }
 
+        fn push_front(&mut self, webhook: Webhook, priority: Priority) {
+            self.front -= 1;
+            self.webhooks.insert((priority, self.front), webhook);
+        }
+
+        /// Takes every webhook, in the order they'll be sent.
+        fn take(&mut self) -> BTreeMap<Position, Webhook> {
+            std::mem::take(&mut self.webhooks)
+        }
+    }
+
+    /// Sends queued webhooks in the background.
+    ///
+    /// Webhooks to the same url are sent by [`Priority`], then in the order they were queued,
+    /// paced to that url's rate limit. [`WebhookQueue::enqueue_multi`] queues its webhooks in
+    /// order, and [`WebhookQueue::enqueue_front`] puts a webhook ahead of every other one of
+    /// normal priority, including earlier ones put in front. Different urls are sent to
+    /// concurrently, so a busy url doesn't hold up the others.
+    ///
+    /// Rate limited webhooks are re-sent a few times before giving up; any other error is
+    /// final. Outcomes are reported through [`WebhookQueue::results`], or failures are kept
+    /// for [`QueueHandle::failed`].
     pub struct WebhookQueue {
-        pub webhooks: Arc<Mutex<VecDeque<Webhook>>>,
+        pub webhooks: Arc<Mutex<QueuedWebhooks>>,
+        sender: HttpSender,
+        results: Option<mpsc::UnboundedSender<QueueResult>>,
+        failed: Arc<Mutex<Vec<(Webhook, WebhookError)>>>,
+        rate_limit: RateLimitConfig,
+    }
+
+    impl Default for WebhookQueue {
+        fn default() -> Self {
+            Self::new()
+        }
     }
 
     impl WebhookQueue {
         pub fn new() -> Self {
+            Self::with_sender(HttpSender::new())
+        }
+
+        /// Creates a queue whose sends use a client built from `config`.
+        pub fn with_client_config(config: &ClientConfig) -> Result<Self, WebhookError> {
+            Ok(Self::with_sender(HttpSender::with_config(config)?))
+        }
+
+        pub fn with_sender(sender: HttpSender) -> Self {
             Self {
-                webhooks: Arc::new(Mutex::new(VecDeque::new())),
+                webhooks: Arc::new(Mutex::new(QueuedWebhooks::default())),
+                sender,
+                results: None,
+                failed: Arc::new(Mutex::new(Vec::new())),
+                rate_limit: RateLimitConfig::default(),
             }
         }
 
-        pub async fn enqueue(queue: Arc<Mutex<VecDeque<Webhook>>>, webhook: Webhook) {
-            let mut q = queue.lock().await;
-            q.push_front(webhook);
+        /// Creates a queue that paces each url to `rate_limit`. Panics if its interval is zero.
+        pub fn with_config(rate_limit: RateLimitConfig) -> Self {
+            Self::new().rate_limit(rate_limit)
+        }
+
+        /// Paces each url to `rate_limit` instead of Discord's documented 5 requests per
+        /// 2 seconds, e.g. to leave room for other senders to the same webhook.
+        ///
+        /// Panics if `rate_limit.interval` is zero, which would allow unlimited sends.
+        pub fn rate_limit(mut self, rate_limit: RateLimitConfig) -> Self {
+            assert!(!rate_limit.interval.is_zero(), "rate limit interval must not be zero");
+            self.rate_limit = rate_limit;
+            self
+        }
+
+        /// Receives every webhook once it's sent, or once it has failed for good. Without it,
+        /// failures are kept until taken with [`QueueHandle::failed`].
+        ///
+        /// Call before [`WebhookQueue::start`]; calling again replaces the receiver.
+        pub fn results(&mut self) -> mpsc::UnboundedReceiver<QueueResult> {
+            let (results, receiver) = mpsc::unbounded_channel();
+            self.results = Some(results);
+            receiver
+        }
+
+        pub async fn enqueue(queue: Arc<Mutex<QueuedWebhooks>>, webhook: Webhook) {
+            Self::enqueue_with_priority(queue, webhook, Priority::Normal).await;
         }
 
-        pub async fn enqueue_multi(queue: Arc<Mutex<VecDeque<Webhook>>>, webhooks: Vec<Webhook>) {
+        pub async fn enqueue_multi(queue: Arc<Mutex<QueuedWebhooks>>, webhooks: Vec<Webhook>) {
             let mut q = queue.lock().await;
             for webhook in webhooks {
-                q.push_front(webhook);
+                q.push_back(webhook, Priority::Normal);
             }
         }
 
-        pub fn start(self) -> JoinHandle<Self> {
-            tokio::task::spawn(async move {
+        /// Queues the webhook ahead of every other webhook of normal priority.
+        pub async fn enqueue_front(queue: Arc<Mutex<QueuedWebhooks>>, webhook: Webhook) {
+            queue.lock().await.push_front(webhook, Priority::Normal);
+        }
+
+        pub async fn enqueue_with_priority(queue: Arc<Mutex<QueuedWebhooks>>, webhook: Webhook, priority: Priority) {
+            queue.lock().await.push_back(webhook, priority);
+        }
+
+        /// Starts sending in the background. Webhooks can still be enqueued through
+        /// [`QueueHandle::webhooks`], until the queue is stopped through the handle.
+        pub fn start(self) -> QueueHandle {
+            let (stop, mut stopping) = watch::channel(Stop::Running);
+            let webhooks = Arc::clone(&self.webhooks);
+            let failed = Arc::clone(&self.failed);
+            let task = tokio::task::spawn(async move {
+                let mut destinations = Destinations::default();
                 loop {
-                    let (one, two) = {
-                        let mut whs = self.webhooks.as_ref().lock().await;
-                        let one = whs.pop_back();
-                        let two = whs.pop_back();
-                        (one, two)
-                    };
+                    let stop = *stopping.borrow_and_update();
+                    let queued = self.webhooks.lock().await.take();
 
-                    if cfg!(test) {
-                        if one.is_none() && two.is_none() {
-                            return self;
-                        }
+                    match stop {
+                        Stop::Shutdown => {
+                            let mut unsent = destinations.finish().await;
+                            unsent.extend(queued.into_values());
+                            return unsent;
+                        },
+                        Stop::Drain | Stop::Running => {
+                            for (position, webhook) in queued {
+                                destinations.push(&self, position, webhook, &stopping);
+                            }
+                        },
+                    }
+                    if stop == Stop::Drain {
+                        return destinations.finish().await;
                     }
 
-                    // only 2 so sequential is fine
-                    let _ = match one {
-                        Some(w) => w.send().await,
-                        None => Ok(()),
-                    };
+                    tokio::select! {
+                        _ = tokio::time::sleep(POLL_INTERVAL) => {},
+                        _ = stopping.changed() => {},
+                    }
+                }
+            });
+            QueueHandle { webhooks, failed, stop, task }
+        }
+    }
 
-                    let _ = match two {
-                        Some(w) => w.send().await,
-                        None => Ok(()),
-                    };
+    #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+    enum Stop {
+        Running,
+        Drain,
+        Shutdown,
+    }
+
+    /// Controls a started [`WebhookQueue`].
+    ///
+    /// Dropping the handle leaves the queue running for as long as the runtime does.
+    pub struct QueueHandle {
+        webhooks: Arc<Mutex<QueuedWebhooks>>,
+        failed: Arc<Mutex<Vec<(Webhook, WebhookError)>>>,
+        stop: watch::Sender<Stop>,
+        task: JoinHandle<Vec<Webhook>>,
+    }
+
+    impl QueueHandle {
+        /// The queue to pass to [`WebhookQueue::enqueue`].
+        pub fn webhooks(&self) -> Arc<Mutex<QueuedWebhooks>> {
+            Arc::clone(&self.webhooks)
+        }
+
+        /// Takes the webhooks that failed for good since the last call, with their errors.
+        /// Always empty if the queue reports through [`WebhookQueue::results`].
+        pub async fn failed(&self) -> Vec<(Webhook, WebhookError)> {
+            std::mem::take(&mut *self.failed.lock().await)
+        }
+
+        /// Sends everything queued so far, then stops, returning the webhooks that failed and
+        /// weren't taken with [`QueueHandle::failed`]. Webhooks enqueued after this is called
+        /// aren't sent.
+        ///
+        /// Call it before the process exits, e.g. on SIGTERM, so the last messages make it out.
+        pub async fn drain(self) -> Vec<(Webhook, WebhookError)> {
+            let _ = self.stop.send(Stop::Drain);
+            let _ = self.task.await;
+            std::mem::take(&mut *self.failed.lock().await)
+        }
+
+        /// Stops once the sends already in progress finish, returning the webhooks that weren't
+        /// sent, in the order each url would have sent them.
+        pub async fn shutdown(self) -> Vec<Webhook> {
+            let _ = self.stop.send(Stop::Shutdown);
+            self.task.await.unwrap_or_default()
+        }
+
+        /// Stops immediately, dropping whatever hasn't been sent. A send already in progress
+        /// may still reach Discord.
+        pub fn abort(self) {
+            let _ = self.stop.send(Stop::Shutdown);
+            self.task.abort();
+        }
+    }
 
-                    // we can send 2 webhooks every 2 seconds,
-                    tokio::time::sleep_until(Instant::now() + Duration::from_millis(2000)).await;
+    /// A worker per url, each sending that url's webhooks in order of their position.
+    #[derive(Default)]
+    struct Destinations {
+        workers: HashMap<SecretUrl, Worker>,
+    }
+
+    struct Worker {
+        webhooks: mpsc::UnboundedSender<(Position, Webhook)>,
+        handle: JoinHandle<Vec<Webhook>>,
+    }
+
+    impl Destinations {
+        fn push(&mut self, queue: &WebhookQueue, position: Position, webhook: Webhook, stopping: &watch::Receiver<Stop>) {
+            let worker = self
+                .workers
+                .entry(webhook.webhook_url.clone())
+                .or_insert_with(|| Worker::spawn(queue, stopping.clone()));
+            let _ = worker.webhooks.send((position, webhook));
+        }
+
+        /// Waits for the workers to finish, returning what they didn't send.
+        async fn finish(self) -> Vec<Webhook> {
+            let mut unsent = Vec::new();
+            for (_, worker) in self.workers {
+                drop(worker.webhooks);
+                unsent.extend(worker.handle.await.unwrap_or_default());
+            }
+            unsent
+        }
+    }
+
+    impl Worker {
+        /// Sends until its webhooks run out, or until the queue shuts down. The limiter is kept
+        /// after the url drains, so a burst of new webhooks still respects recent sends.
+        ///
+        /// A rate limited webhook is re-sent before the next one, keeping the url's order.
+        fn spawn(queue: &WebhookQueue, mut stopping: watch::Receiver<Stop>) -> Self {
+            let (webhooks, mut pending) = mpsc::unbounded_channel::<(Position, Webhook)>();
+            let (sender, results, failed) = (queue.sender.clone(), queue.results.clone(), Arc::clone(&queue.failed));
+            let limiter = RateLimiter::with_config(queue.rate_limit);
+            let handle = tokio::task::spawn(async move {
+                let mut unsent = Vec::new();
+                let mut waiting = BTreeMap::new();
+                'webhooks: loop {
+                    while let Ok((position, webhook)) = pending.try_recv() {
+                        waiting.insert(position, webhook);
+                    }
+                    let Some((_, webhook)) = waiting.pop_first() else {
+                        let received = tokio::select! {
+                            biased;
+                            _ = stopping.wait_for(|stop| *stop == Stop::Shutdown) => None,
+                            received = pending.recv() => received,
+                        };
+                        match received {
+                            Some((position, webhook)) => {
+                                waiting.insert(position, webhook);
+                                continue;
+                            },
+                            None => break,
+                        }
+                    };
+                    let mut requeues = 0;
+                    let result = loop {
+                        tokio::select! {
+                            biased;
+                            _ = stopping.wait_for(|stop| *stop == Stop::Shutdown) => {
+                                unsent.push(webhook);
+                                break 'webhooks;
+                            },
+                            _ = limiter.acquire() => {},
+                        }
+                        let result = limiter.deliver(&sender, &webhook).await;
+                        match result.as_ref().err().and_then(rate_limited_for) {
+                            Some(retry_after) if requeues < MAX_REQUEUES => {
+                                requeues += 1;
+                                limiter.pause_for(retry_after).await;
+                            },
+                            _ => break result,
+                        }
+                    };
+                    match (&results, result) {
+                        (Some(results), result) => {
+                            let _ = results.send((webhook, result));
+                        },
+                        (None, Err(error)) => failed.lock().await.push((webhook, error)),
+                        (None, Ok(_)) => {},
+                    }
                 }
-            })
+                pending.close();
+                while let Ok((position, webhook)) = pending.try_recv() {
+                    waiting.insert(position, webhook);
+                }
+                unsent.extend(waiting.into_values());
+                unsent
+            });
+            Self { webhooks, handle }
         }
     }
-}
 
+    /// How long to wait before re-sending after `error`, if it's a rate limit.
+    fn rate_limited_for(error: &WebhookError) -> Option<Duration> {
+        error.is_rate_limited().then(|| error.retry_after().unwrap_or(DEFAULT_RETRY_AFTER))
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub struct Webhook {
     #[serde(skip)]
-    webhook_url: String,
+    webhook_url: SecretUrl,
+    #[serde(skip_serializing_if = "Option::is_none")]
     content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     avatar_url: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    tts: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flags: Option<MessageFlags>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     embeds: Vec<Embed>,
-    components: Vec<Component>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    components: Vec<TopLevelComponent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    allowed_mentions: Option<AllowedMentions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thread_name: Option<String>,
+    #[serde(skip)]
+    thread_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<AttachmentInfo>,
+    #[serde(skip)]
+    files: Vec<File>,
+    #[serde(skip)]
+    with_components: bool,
+    #[serde(skip)]
+    headers: Vec<(String, String)>,
+    #[serde(skip)]
+    allowed_extensions: Option<Vec<String>>,
+    #[serde(skip)]
+    auto_timestamp: bool,
+    #[serde(skip)]
+    skip_validation: bool,
+    #[serde(skip)]
+    locale: Option<String>,
+    #[serde(skip)]
+    #[cfg(feature = "retry")]
+    max_retries: Option<u32>,
+    #[serde(skip)]
+    timeout: Option<Duration>,
+    #[serde(skip)]
+    #[cfg(feature = "retry")]
+    retry_policy: Option<RetryPolicy>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
-struct Component {}
+/// The body of a message edit. Unlike a send, unset content, embeds and components are sent as
+/// `null` and `[]` so an edit can clear them; attachments are only sent when set, since an empty
+/// list would remove the message's files.
+#[derive(Debug, Serialize)]
+struct EditPayload<'a> {
+    content: Option<&'a str>,
+    embeds: &'a [Embed],
+    components: &'a [TopLevelComponent],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    allowed_mentions: Option<&'a AllowedMentions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flags: Option<MessageFlags>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    attachments: &'a [AttachmentInfo],
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub struct Embed {
+    #[serde(skip_serializing_if = "Option::is_none")]
     title: Option<String>,
-    #[serde(rename = "type")]
+    #[serde(rename = "type", default = "default_embed_type")]
     _type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     timestamp: Option<String>,
+    #[serde(default, deserialize_with = "colour::deserialize_colour", skip_serializing_if = "Option::is_none")]
     color: Option<usize>,
-    footer: Option<Footer>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    footer: Option<Arc<Footer>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     image: Option<Image>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     thumbnail: Option<Thumbnail>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     video: Option<Video>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     provider: Option<Provider>,
-    author: Option<Author>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<Arc<Author>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     fields: Vec<Field>,
+    #[serde(skip)]
+    localized_titles: HashMap<String, String>,
+}
+
+fn default_embed_type() -> String {
+    "rich".to_string()
 }
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
-struct Footer {
+pub struct Footer {
     text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     icon_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     proxy_icon_url: Option<String>,
 }
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
-struct Image {
+pub struct Image {
     url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     proxy_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     height: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     width: Option<usize>,
 }
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
-struct Thumbnail {
+pub struct Thumbnail {
     url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     proxy_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     height: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     width: Option<usize>,
 }
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
-struct Video {
+pub struct Video {
     url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     proxy_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     height: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     width: Option<usize>,
 }
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
-struct Provider {
+pub struct Provider {
+    #[serde(skip_serializing_if = "Option::is_none")]
     name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     url: Option<String>,
 }
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
-struct Author {
+pub struct Author {
     name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     icon_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     proxy_icon_url: Option<String>,
 }
+
+impl Footer {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn icon_url(&self) -> Option<&str> {
+        self.icon_url.as_deref()
+    }
+
+    /// Filled in by Discord, so only set on embeds read back from a message.
+    pub fn proxy_icon_url(&self) -> Option<&str> {
+        self.proxy_icon_url.as_deref()
+    }
+}
+
+/// Getters for the embed media types, which share their shape.
+macro_rules! media_getters {
+    ($($media:ident),*) => {$(
+        impl $media {
+            pub fn url(&self) -> &str {
+                &self.url
+            }
+
+            /// Filled in by Discord, like the dimensions.
+            pub fn proxy_url(&self) -> Option<&str> {
+                self.proxy_url.as_deref()
+            }
+
+            pub fn height(&self) -> Option<usize> {
+                self.height
+            }
+
+            pub fn width(&self) -> Option<usize> {
+                self.width
+            }
+        }
+    )*};
+}
+
+media_getters!(Image, Thumbnail, Video);
+
+impl Provider {
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+}
+
+impl Author {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    pub fn icon_url(&self) -> Option<&str> {
+        self.icon_url.as_deref()
+    }
+
+    /// Filled in by Discord, so only set on embeds read back from a message.
+    pub fn proxy_icon_url(&self) -> Option<&str> {
+        self.proxy_icon_url.as_deref()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub struct Field {
     pub name: String,
     pub value: String,
     pub inline: bool,
 }
+/// Wraps text in a code block of at most `limit` characters, cutting the text short with `…`
+/// so the closing fence always fits.
+fn code_block(language: &str, text: &str, limit: usize) -> String {
+    // a fence inside the text would close the block early
+    let text = text.replace("```", "`\u{200b}``");
+    let room = limit.saturating_sub(language.chars().count() + "```\n\n```".len());
+    let text = match text.chars().count() > room {
+        true => text.chars().take(room.saturating_sub(1)).chain(['…']).collect(),
+        false => text,
+    };
+    format!("```{}\n{}\n```", language, text)
+}
+
 pub enum ColourType<S: AsRef<str>> {
+    /// `#RRGGBB`, with or without the `#`, `0xRRGGBB`, or the `#RGB` shorthand. Three digits
+    /// without the `#` are read as a plain number, so `FA0` is `0x000FA0`.
     Hex(S),
     Integer(usize),
-    FromSeed(S)
+    FromSeed(S),
+    /// Red, green and blue components. [`ColourType::rgb`] builds one without naming `S`.
+    Rgb(u8, u8, u8),
+}
+
+impl ColourType<&'static str> {
+    /// `ColourType::rgb(88, 101, 242)` is blurple.
+    pub fn rgb(red: u8, green: u8, blue: u8) -> Self {
+        ColourType::Rgb(red, green, blue)
+    }
 }
 
 impl Webhook {
     pub fn new<S: AsRef<str>>(webhook_url: S) -> Webhook {
         Webhook {
-            webhook_url: webhook_url.as_ref().to_string(),
+            webhook_url: webhook_url.as_ref().into(),
             content: None,
             username: None,
             avatar_url: None,
+            tts: false,
+            flags: None,
             embeds: Vec::new(),
             components: Vec::new(),
+            allowed_mentions: None,
+            thread_name: None,
+            thread_id: None,
+            attachments: Vec::new(),
+            files: Vec::new(),
+            with_components: false,
+            headers: Vec::new(),
+            allowed_extensions: None,
+            auto_timestamp: false,
+            skip_validation: false,
+            locale: None,
+            #[cfg(feature = "retry")]
+            max_retries: None,
+            timeout: None,
+            #[cfg(feature = "retry")]
+            retry_policy: None,
         }
     }
     pub fn set_content<S: AsRef<str>>(mut self, content: S) -> Self {
@@ -183,202 +733,791 @@ impl Webhook {
         self.avatar_url = Some(url.as_ref().to_string());
         self
     }
+    /// Adds an embed. Discord allows 10 per message; send more with [`Webhook::send_paginated`],
+    /// which spreads them over as many messages as needed, see [`Webhook::split`].
     pub fn add_embed(mut self, embed: Embed) -> Self {
         self.embeds.push(embed);
         self
     }
 
-    pub fn add_embeds(mut self, embeds: &mut Vec<Embed>) -> Self {
-        self.embeds.append(embeds);
+    pub fn add_embeds<I: IntoIterator<Item = Embed>>(mut self, embeds: I) -> Self {
+        self.embeds.extend(embeds);
         self
     }
 
-    #[cfg(not(feature = "retry"))]
-    pub async fn send(&self) -> anyhow::Result<()> {
-        let client = reqwest::Client::new();
-
-        let body = serde_json::to_string(self).unwrap();
+    /// Replaces the embeds, e.g. when building the payload for [`Webhook::edit`].
+    pub fn set_embeds(mut self, embeds: Vec<Embed>) -> Self {
+        self.embeds = embeds;
+        self
+    }
 
-        let resp = client
-            .post(format!("{}?wait=true", &self.webhook_url))
-            .header("Content-Type", "application/json")
-            .body(body)
-            .send()
-            .await?;
+    /// Removes the embed at `index`, shifting later embeds down. Does nothing if there is no
+    /// embed at `index`.
+    pub fn remove_embed(mut self, index: usize) -> Self {
+        if index < self.embeds.len() {
+            self.embeds.remove(index);
+        }
+        self
+    }
 
-        match resp.status() {
-            reqwest::StatusCode::NO_CONTENT | reqwest::StatusCode::OK => {
-                Ok(())
-            },
-            _ => {
-                let body = resp.text().await.unwrap_or(String::from(""));
-                Err(format_err!("Failed to send request, {}", body))
-            }
+    /// Points the webhook at another Discord-compatible host, replacing the scheme and host of
+    /// the webhook url while keeping its `/api/webhooks/{id}/{token}` path.
+    ///
+    /// `base` must be an http(s) origin such as `https://discord.example.com`.
+    pub fn set_base_url<S: AsRef<str>>(mut self, base: S) -> Result<Self, WebhookError> {
+        let base = base.as_ref();
+        if !is_origin(base) {
+            return Err(WebhookError::InvalidUrl(base.to_string()));
         }
+        self.webhook_url = self
+            .webhook_url
+            .with_origin(base)
+            .ok_or_else(|| WebhookError::InvalidUrl(self.webhook_url.redacted()))?;
+        Ok(self)
+    }
+
+    /// Like [`Webhook::set_content`], but fails if the content exceeds Discord's limit.
+    pub fn try_set_content<S: AsRef<str>>(self, content: S) -> Result<Self, WebhookError> {
+        check_length("content", content.as_ref(), MAX_CONTENT).map_err(|e| e.in_setter("try_set_content"))?;
+        Ok(self.set_content(content))
     }
 
+    /// Like [`Webhook::set_username`], but fails if the username exceeds Discord's limit.
+    pub fn try_set_username<S: AsRef<str>>(self, username: S) -> Result<Self, WebhookError> {
+        check_length("username", username.as_ref(), MAX_USERNAME).map_err(|e| e.in_setter("try_set_username"))?;
+        Ok(self.set_username(username))
+    }
 
-    #[cfg(feature = "retry")]
-    #[async_recursion::async_recursion]
-    pub async fn send(&self) -> anyhow::Result<()> {
-        let client = reqwest::Client::new();
-
-        let body = serde_json::to_string(self).unwrap();
-
-        let resp = client
-            .post(format!("{}?wait=true", &self.webhook_url))
-            .header("Content-Type", "application/json")
-            .body(body)
-            .send()
-            .await?;
-
-        match resp.status() {
-            reqwest::StatusCode::NO_CONTENT | reqwest::StatusCode::OK => {
-                Ok(())
-            },
-            reqwest::StatusCode::TOO_MANY_REQUESTS => {
-                use std::time::Duration;
-                use tokio::time::{Instant, sleep_until};
-                let retry_after = match resp.headers().get("x-ratelimit-reset-after") {
-                    Some(header) => {
-                        let str = header.to_str().unwrap_or("5.00");
-                        str.parse::<f64>().unwrap_or(5.00)
-                    },
-                    None => return Err(format_err!("Missing \"Retry After\" header"))
-                };
-                log::warn!("Webhook rate limited, retrying in {} seconds", retry_after);
-                sleep_until(Instant::now() + Duration::from_secs_f64(retry_after)).await;
-                self.send().await
-            },
-            _ => {
-                let body = resp.text().await.unwrap_or(String::from(""));
-                Err(format_err!("Failed to send request, {}", body))
-            }
+    /// Like [`Webhook::add_embed`], but fails if the embed breaks a limit or the message
+    /// would exceed 10 embeds or 6000 embed characters.
+    pub fn try_add_embed(self, embed: Embed) -> Result<Self, WebhookError> {
+        let check = || {
+            check_embed(&embed)?;
+            check_count("embeds", self.embeds.len() + 1, MAX_EMBEDS)?;
+            let total = self.embeds.iter().map(Embed::character_count).sum::<usize>() + embed.character_count();
+            check_length_total("embeds", total)
+        };
+        check().map_err(|e| e.in_setter("try_add_embed"))?;
+        Ok(self.add_embed(embed))
+    }
+
+    /// Clones this webhook as a template, replacing only the content.
+    /// Footers and authors on the embeds are shared rather than deep-copied.
+    pub fn clone_with_content<S: AsRef<str>>(&self, content: S) -> Webhook {
+        Webhook {
+            webhook_url: self.webhook_url.clone(),
+            content: Some(content.as_ref().to_string()),
+            username: self.username.clone(),
+            avatar_url: self.avatar_url.clone(),
+            tts: self.tts,
+            flags: self.flags,
+            embeds: self.embeds.clone(),
+            components: self.components.clone(),
+            allowed_mentions: self.allowed_mentions.clone(),
+            thread_name: self.thread_name.clone(),
+            thread_id: self.thread_id,
+            attachments: self.attachments.clone(),
+            files: self.files.clone(),
+            with_components: self.with_components,
+            headers: self.headers.clone(),
+            allowed_extensions: self.allowed_extensions.clone(),
+            auto_timestamp: self.auto_timestamp,
+            skip_validation: self.skip_validation,
+            locale: self.locale.clone(),
+            #[cfg(feature = "retry")]
+            max_retries: self.max_retries,
+            timeout: self.timeout,
+            #[cfg(feature = "retry")]
+            retry_policy: self.retry_policy.clone(),
         }
     }
-}
 
-impl Embed {
-    pub fn new() -> Embed {
-        Embed {
-            title: None,
-            _type: "rich".to_string(),
-            description: None,
-            url: None,
-            timestamp: None,
-            color: None,
-            footer: None,
-            image: None,
-            thumbnail: None,
-            video: None,
-            provider: None,
-            author: None,
-            fields: Vec::new(),
+    /// Checks the payload against Discord's limits, and for values Discord would silently ignore.
+    ///
+    /// Sending runs this first, so a payload Discord would reject fails without a request, unless
+    /// turned off with [`Webhook::skip_validation`].
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if let Some(content) = &self.content {
+            check_length("content", content, MAX_CONTENT)?;
+        }
+        if let Some(username) = &self.username {
+            check_length("username", username, MAX_USERNAME)?;
+        }
+        if let Some(avatar_url) = &self.avatar_url {
+            if !is_image_url(avatar_url) {
+                return Err(ValidationError::Invalid { field: "avatar url", reason: "must be an http(s) image url" });
+            }
+        }
+        if self.thread_id.is_some() && self.thread_name.is_some() {
+            return Err(ValidationError::Invalid { field: "thread", reason: "thread_id and thread_name can't both be set" });
+        }
+        if let Some(thread_name) = &self.thread_name {
+            check_length("thread name", thread_name, MAX_THREAD_NAME)?;
         }
+        check_count("embeds", self.embeds.len(), MAX_EMBEDS)?;
+        self.embeds.iter().try_for_each(check_embed)?;
+        check_length_total("embeds", self.embeds.iter().map(Embed::character_count).sum())?;
+        check_count("files", self.files.len(), MAX_FILES)?;
+        let components_v2 = self.flags.is_some_and(|flags| flags.contains(MessageFlags::IS_COMPONENTS_V2));
+        if components_v2 && (self.content.is_some() || !self.embeds.is_empty()) {
+            return Err(ValidationError::Invalid { field: "components", reason: "IS_COMPONENTS_V2 messages can't have content or embeds" });
+        }
+        component::check_components(&self.components, components_v2)?;
+        if !self.with_components && self.components.iter().any(TopLevelComponent::is_interactive) {
+            return Err(ValidationError::Invalid { field: "components", reason: "interactive components are dropped without with_components" });
+        }
+        Ok(())
     }
-    pub fn set_title<S: AsRef<str>>(mut self, title: S) -> Self {
-        self.title = Some(title.as_ref().to_string());
+
+    /// Sends to, edits and deletes messages in an existing thread of the webhook's channel.
+    ///
+    /// Discord rejects a message with both a thread id and a thread name, so sending one fails
+    /// with [`ValidationError::Invalid`] before any request is made.
+    pub fn set_thread_id(mut self, thread_id: u64) -> Self {
+        self.thread_id = Some(thread_id);
         self
     }
-    pub fn set_description<S: AsRef<str>>(mut self, description: S) -> Self {
-        self.description = Some(description.as_ref().to_string());
+
+    /// Starts a new forum post with this name. Only for webhooks in forum channels, and not
+    /// together with [`Webhook::set_thread_id`].
+    pub fn set_thread_name<S: AsRef<str>>(mut self, thread_name: S) -> Self {
+        self.thread_name = Some(thread_name.as_ref().to_string());
         self
     }
-    pub fn set_url<S: AsRef<str>>(mut self, url: S) -> Self {
-        self.url = Some(url.as_ref().to_string());
+
+    /// Application-owned webhooks need this set to send interactive (non-link) components.
+    /// [`Webhook::add_component`] turns it on; turning it off again while such components are
+    /// attached fails validation, as Discord would silently drop them.
+    pub fn set_with_components(mut self, with_components: bool) -> Self {
+        self.with_components = with_components;
         self
     }
-    pub fn set_timestamp(mut self, timestamp: Option<&std::time::SystemTime>) -> Self {
-        let timestamp: DateTime<Utc> = match timestamp {
-            Some(ts) => (*ts).into(),
-            None => Utc::now(),
-        };
-        let timestamp = timestamp.format("%+").to_string();
-        self.timestamp = Some(timestamp);
+
+    /// Adds a header to every request made for this webhook, replacing any previous value.
+    ///
+    /// Headers the crate sets itself, such as `Content-Type`, cannot be overridden; invalid or
+    /// reserved headers are reported by [`Webhook::send`] before any request is made.
+    pub fn add_header<A: AsRef<str>, B: AsRef<str>>(mut self, name: A, value: B) -> Self {
+        let name = name.as_ref();
+        self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value.as_ref().to_string()));
         self
     }
-    pub fn set_colour<S: AsRef<str>>(mut self, colour: ColourType<S>) -> Self {
-        let colour: usize = match colour {
-            ColourType::Hex(hex) => usize::from_str_radix(
-                hex.as_ref()
-                    .trim_start_matches('#')
-                    .trim_start_matches("0x"),
-                16,
-            )
-                .unwrap_or(10066329),
-            ColourType::Integer(int) => int,
-            ColourType::FromSeed(seed) => {
-                let mut hasher = Sha1::new();
-                hasher.update(seed.as_ref().as_bytes());
-                let result = hasher.finalize();
-                let encoded = format!("#{}", &hex::encode(result)[0..6]);
-                return self.set_colour(ColourType::Hex(encoded))
-            }
-        };
 
-        self.color = Some(colour);
+    /// Restricts attachments to files with one of these extensions (case-insensitive, with or
+    /// without the leading dot). All extensions are allowed until this is set.
+    pub fn set_allowed_extensions(mut self, extensions: Vec<String>) -> Self {
+        let extensions = extensions
+            .iter()
+            .map(|ext| ext.trim_start_matches('.').to_ascii_lowercase())
+            .collect();
+        self.allowed_extensions = Some(extensions);
         self
     }
-    pub fn set_color<S: AsRef<str>>(self, color: ColourType<S>) -> Self {
-        self.set_colour(color)
-    }
-    pub fn set_footer<A: AsRef<str>, B: AsRef<str>, C: AsRef<str>>(
-        mut self,
-        text: A,
-        icon_url: Option<B>,
-        proxy_icon_url: Option<C>,
-    ) -> Self {
-        let icon_url = icon_url.map(|n| n.as_ref().to_string());
-        let proxy_icon_url = proxy_icon_url.map(|n| n.as_ref().to_string());
 
-        self.footer = Some(Footer {
-            text: text.as_ref().to_string(),
-            icon_url,
-            proxy_icon_url,
-        });
+    /// Whether a file with this name may be attached under the allowed extensions.
+    pub fn allows_filename<S: AsRef<str>>(&self, filename: S) -> bool {
+        let allowed = match &self.allowed_extensions {
+            Some(allowed) => allowed,
+            None => return true,
+        };
+        match filename.as_ref().rsplit_once('.') {
+            Some((_, ext)) => allowed.iter().any(|a| a.eq_ignore_ascii_case(ext)),
+            None => false,
+        }
+    }
 
+    /// When enabled, embeds without a timestamp are stamped with the current time when sent.
+    pub fn auto_timestamp(mut self, auto_timestamp: bool) -> Self {
+        self.auto_timestamp = auto_timestamp;
         self
     }
-    pub fn set_image<A: AsRef<str>, B: AsRef<str>>(
-        mut self,
-        url: A,
-        proxy_url: Option<B>,
-        height: Option<usize>,
-        width: Option<usize>,
-    ) -> Self {
-        let proxy_url = proxy_url.map(|p| p.as_ref().to_string());
 
-        self.image = Some(Image {
-            url: url.as_ref().to_string(),
-            proxy_url,
-            height,
-            width,
-        });
+    /// When enabled, sending doesn't run [`Webhook::validate`] first and leaves the limits to
+    /// Discord, e.g. if Discord has raised one before this crate caught up.
+    pub fn skip_validation(mut self, skip_validation: bool) -> Self {
+        self.skip_validation = skip_validation;
+        self
+    }
 
+    /// Overrides the sender's limit on retries after a rate limit, see [`HttpSender::max_retries`].
+    /// Only there with the `retry` feature, which is on by default.
+    #[cfg(feature = "retry")]
+    pub fn set_max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = Some(retries);
         self
     }
-    pub fn set_thumbnail<A: AsRef<str>, B: AsRef<str>>(
-        mut self,
-        url: A,
-        proxy_url: Option<B>,
-        height: Option<usize>,
-        width: Option<usize>,
-    ) -> Self {
-        let proxy_url = proxy_url.map(|p| p.as_ref().to_string());
 
-        self.thumbnail = Some(Thumbnail {
-            url: url.as_ref().to_string(),
-            proxy_url,
-            height,
-            width,
-        });
+    /// Overrides the sender's request timeout, see [`HttpSender::timeout`].
+    pub fn set_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
 
+    /// Picks the locale used for embeds with localized variants, see [`Embed::set_title_localized`].
+    pub fn set_locale<S: AsRef<str>>(mut self, locale: S) -> Self {
+        self.locale = Some(locale.as_ref().to_string());
         self
     }
-    pub fn set_video<A: AsRef<str>, B: AsRef<str>>(
-        mut self,
-        url: A,
-        proxy_url: Option<B>,
+
+    /// The payload as it should go out, with send-time transformations applied.
+    fn prepared(&self) -> Cow<'_, Webhook> {
+        let stamp = self.auto_timestamp && self.embeds.iter().any(|e| e.timestamp.is_none());
+        let localize = self.locale.is_some() && self.embeds.iter().any(|e| !e.localized_titles.is_empty());
+        let measure = cfg!(feature = "image-meta") && !self.files.is_empty();
+        if !stamp && !localize && !measure {
+            return Cow::Borrowed(self);
+        }
+        let mut webhook = self.clone();
+        if stamp {
+            let now = Utc::now().format("%+").to_string();
+            for embed in webhook.embeds.iter_mut().filter(|e| e.timestamp.is_none()) {
+                embed.timestamp = Some(now.clone());
+            }
+        }
+        if let Some(locale) = &self.locale {
+            for embed in &mut webhook.embeds {
+                if let Some(title) = embed.localized_title(locale) {
+                    embed.title = Some(title.clone());
+                }
+            }
+        }
+        #[cfg(feature = "image-meta")]
+        for file in &self.files {
+            for embed in &mut webhook.embeds {
+                *embed = embed.clone().attachment_dimensions(&file.filename, &file.bytes);
+            }
+        }
+        Cow::Owned(webhook)
+    }
+
+    /// The query parameters sent alongside the payload.
+    fn query_params(&self) -> Vec<(&'static str, String)> {
+        let mut query = vec![("wait", String::from("true"))];
+        query.extend(self.edit_query_params());
+        query
+    }
+
+    /// The query parameters for editing a sent message, which always returns the message.
+    fn edit_query_params(&self) -> Vec<(&'static str, String)> {
+        let mut query = self.thread_query_params();
+        if self.with_components {
+            query.push(("with_components", String::from("true")));
+        }
+        query
+    }
+
+    /// Targets the thread, if one is set.
+    fn thread_query_params(&self) -> Vec<(&'static str, String)> {
+        self.thread_id.map(|id| ("thread_id", id.to_string())).into_iter().collect()
+    }
+
+    /// What an edit of a message sends, see [`EditPayload`].
+    fn edit_payload(&self) -> EditPayload<'_> {
+        EditPayload {
+            content: self.content.as_deref(),
+            embeds: &self.embeds,
+            components: &self.components,
+            allowed_mentions: self.allowed_mentions.as_ref(),
+            flags: self.flags,
+            attachments: &self.attachments,
+        }
+    }
+
+    /// The url of a message sent through this webhook, rejecting ids that aren't numeric so
+    /// they can't change the path.
+    fn message_url(&self, message_id: &str) -> Result<String, WebhookError> {
+        if message_id.is_empty() || !message_id.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(WebhookError::InvalidMessageId(message_id.to_string()));
+        }
+        Ok(format!("{}/messages/{}", self.url()?.trim_end_matches('/'), message_id))
+    }
+
+    /// The url to send to. A [`Webhook::payload`] has none until a [`WebhookClient`] sends it.
+    pub(crate) fn url(&self) -> Result<&str, WebhookError> {
+        match self.webhook_url.as_str() {
+            "" => Err(WebhookError::InvalidUrl(String::from("a payload has no url, send it through a WebhookClient"))),
+            url => Ok(url),
+        }
+    }
+
+    /// The custom headers as a [`HeaderMap`], rejecting invalid and reserved headers.
+    fn header_map(&self) -> Result<HeaderMap, WebhookError> {
+        let mut map = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let header_name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| WebhookError::InvalidHeader(name.clone()))?;
+            if RESERVED_HEADERS.contains(&header_name) {
+                return Err(WebhookError::InvalidHeader(name.clone()));
+            }
+            let header_value = HeaderValue::from_str(value)
+                .map_err(|_| WebhookError::InvalidHeader(name.clone()))?;
+            map.insert(header_name, header_value);
+        }
+        Ok(map)
+    }
+
+    /// Serializes the payload into the intermediate JSON value that is sent to Discord,
+    /// so it can be post-processed before being passed to [`Webhook::send_value`].
+    pub fn to_value(&self) -> Result<serde_json::Value, WebhookError> {
+        Ok(serde_json::to_value(self.prepared())?)
+    }
+
+    /// Sends the webhook, returning the message Discord created.
+    ///
+    /// With the `retry` feature, on by default, a rate limited send waits as long as Discord asks
+    /// and tries again, see [`HttpSender::max_retries`]. Without it, a 429 is returned as
+    /// [`WebhookError::Api`].
+    ///
+    /// Sends through [`HttpSender::new`], whose connections are shared process-wide; programs
+    /// that create and drop several tokio runtimes should use [`Webhook::send_with`] and a
+    /// sender per runtime.
+    pub async fn send(&self) -> Result<WebhookMessage, WebhookError> {
+        self.send_with(&HttpSender::new()).await
+    }
+
+    /// Sends the webhook with a client of your own, e.g. one configured with a proxy or custom
+    /// TLS roots. [`Webhook::send`] uses a client shared by the whole process.
+    pub async fn send_with_client(&self, client: &reqwest::Client) -> Result<WebhookMessage, WebhookError> {
+        self.send_with(&HttpSender::with_client(client.clone())).await
+    }
+
+    /// Sends the webhook through a custom [`WebhookSender`].
+    pub async fn send_with<T: WebhookSender>(&self, sender: &T) -> Result<WebhookMessage, WebhookError> {
+        sender.send(&self.prepared()).await
+    }
+
+    /// Replaces the content, embeds and components of a message sent through this webhook,
+    /// returning the edited message.
+    ///
+    /// Fails with [`WebhookError::MessageNotFound`] if the message has been deleted. As with
+    /// [`Webhook::delete_message`], an id that isn't numeric fails with
+    /// [`WebhookError::InvalidMessageId`] without making a request.
+    ///
+    /// A status board posts once, then keeps refreshing the same message:
+    ///
+    /// ```no_run
+    /// use blurple_hook::{Embed, Webhook, WebhookError};
+    ///
+    /// # async fn run(url: &str) -> Result<(), WebhookError> {
+    /// let status = |healthy: usize| Webhook::new(url).add_embed(Embed::new().set_title("Status").add_field("Healthy", healthy, true));
+    /// let board = status(0).send().await?;
+    /// for healthy in 1..=3 {
+    ///     status(healthy).edit(&board.id).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn edit(&self, message_id: &str) -> Result<WebhookMessage, WebhookError> {
+        self.edit_with(&HttpSender::new(), message_id).await
+    }
+
+    /// Pairs with [`Webhook::delete_message`]; the same as [`Webhook::edit`].
+    pub async fn edit_message(&self, message_id: &str) -> Result<WebhookMessage, WebhookError> {
+        self.edit(message_id).await
+    }
+
+    /// Like [`Webhook::edit`], through a custom [`WebhookSender`].
+    pub async fn edit_with<T: WebhookSender>(&self, sender: &T, message_id: &str) -> Result<WebhookMessage, WebhookError> {
+        sender.edit(&self.prepared(), message_id).await
+    }
+
+    /// Deletes a message sent through this webhook. The payload isn't used, so it can be empty.
+    ///
+    /// The id must be numeric; anything else fails with [`WebhookError::InvalidMessageId`]
+    /// without making a request.
+    pub async fn delete_message(&self, message_id: &str) -> Result<(), WebhookError> {
+        self.delete_message_with(&HttpSender::new(), message_id).await
+    }
+
+    /// Like [`Webhook::delete_message`], through a custom [`WebhookSender`].
+    pub async fn delete_message_with<T: WebhookSender>(&self, sender: &T, message_id: &str) -> Result<(), WebhookError> {
+        sender.delete(self, message_id).await
+    }
+
+    /// Splits the embeds into as many messages as needed to stay within Discord's limit of
+    /// 10 embeds and 6000 embed characters per message, preserving embed order.
+    ///
+    /// Only the first message keeps the thread name and TTS, and only the first with embeds
+    /// keeps the content; everything else is copied to every message. Content over 2000
+    /// characters is broken on line or word boundaries (see [`validation::split_text`]) into
+    /// text-only messages sent first, without the embeds, components, files or
+    /// [`MessageFlags::IS_COMPONENTS_V2`], and long embed descriptions into continuation embeds,
+    /// see [`Embed::split_description`].
+    pub fn split(mut self) -> Vec<Webhook> {
+        let mut contents = self.content.take().map(|content| validation::split_text(&content, MAX_CONTENT)).unwrap_or_default();
+        let content = contents.pop();
+        let mut webhooks = Vec::new();
+        if !contents.is_empty() {
+            let components = std::mem::take(&mut self.components);
+            let attachments = std::mem::take(&mut self.attachments);
+            let files = std::mem::take(&mut self.files);
+            let embeds = std::mem::take(&mut self.embeds);
+            let flags = self.flags;
+            self.flags = flags
+                .map(|flags| flags.without(MessageFlags::IS_COMPONENTS_V2))
+                .filter(|flags| *flags != MessageFlags::empty());
+            for piece in contents {
+                webhooks.push(Webhook { content: Some(piece), ..self.clone() });
+                (self.thread_name, self.tts) = (None, false);
+            }
+            (self.components, self.attachments, self.files, self.embeds, self.flags) = (components, attachments, files, embeds, flags);
+        }
+
+        let embeds = std::mem::take(&mut self.embeds).into_iter().flat_map(Embed::split_description);
+        let mut pages: Vec<Vec<Embed>> = Vec::new();
+        let mut page_length = 0;
+        for embed in embeds {
+            let length = embed.character_count();
+            match pages.last_mut() {
+                Some(page) if page.len() < MAX_EMBEDS && page_length + length <= MAX_EMBED_CHARACTERS => {
+                    page_length += length;
+                    page.push(embed);
+                },
+                _ => {
+                    page_length = length;
+                    pages.push(vec![embed]);
+                }
+            }
+        }
+
+        if pages.is_empty() {
+            self.content = content;
+            webhooks.push(self);
+            return webhooks;
+        }
+        for (i, embeds) in pages.into_iter().enumerate() {
+            let mut webhook = self.clone();
+            if i == 0 {
+                webhook.content = content.clone();
+            }
+            webhook.embeds = embeds;
+            webhooks.push(webhook);
+            (self.thread_name, self.tts) = (None, false);
+        }
+        webhooks
+    }
+
+    /// Sends the webhook as [`Webhook::split`] messages, one after another. With a thread name,
+    /// the first message creates the thread and the rest are sent into it.
+    ///
+    /// Stops at the first failed message; the error keeps the messages already sent. Returns
+    /// every message sent, in order, so they can be edited or deleted later.
+    #[doc(alias = "send_split")]
+    pub async fn send_paginated(&self) -> Result<Vec<WebhookMessage>, PaginatedSendError> {
+        self.send_paginated_with(&HttpSender::new()).await
+    }
+
+    /// Like [`Webhook::send_paginated`], through a custom [`WebhookSender`].
+    #[doc(alias = "send_split_with")]
+    pub async fn send_paginated_with<T: WebhookSender>(&self, sender: &T) -> Result<Vec<WebhookMessage>, PaginatedSendError> {
+        let mut pages = self.clone().split();
+        let mut sent = Vec::with_capacity(pages.len());
+        for i in 0..pages.len() {
+            let message = match pages[i].send_with(sender).await {
+                Ok(message) => message,
+                Err(error) => return Err(PaginatedSendError { sent, error }),
+            };
+            // the first message of a new thread is in it, so its channel is the thread
+            if let (0, Some(_), Ok(thread_id)) = (i, &self.thread_name, message.channel_id.parse()) {
+                pages.iter_mut().skip(1).for_each(|page| page.thread_id = Some(thread_id));
+            }
+            sent.push(message);
+        }
+        Ok(sent)
+    }
+
+    /// Sends an already serialized payload to a webhook url.
+    pub async fn send_value<S: AsRef<str>>(webhook_url: S, value: &serde_json::Value) -> Result<WebhookMessage, WebhookError> {
+        HttpSender::new()
+            .execute(reqwest::Method::POST, webhook_url.as_ref(), &[("wait", String::from("true"))], &HeaderMap::new(), value)
+            .await?
+            .message()
+    }
+
+    /// Applies builder methods in place, for building a payload across loops and branches:
+    ///
+    /// ```
+    /// # use blurple_hook::{Embed, Webhook};
+    /// let mut webhook = Webhook::new("https://discord.com/api/webhooks/1/token");
+    /// for failure in ["db-1", "db-2"] {
+    ///     webhook.update(|w| w.add_embed(Embed::new().set_title(failure)));
+    /// }
+    /// if webhook.embeds().len() > 1 {
+    ///     webhook.update(|w| w.set_content("Several hosts are down"));
+    /// }
+    /// ```
+    pub fn update<F: FnOnce(Webhook) -> Webhook>(&mut self, f: F) -> &mut Self {
+        *self = f(std::mem::replace(self, Webhook::payload()));
+        self
+    }
+
+    pub fn content(&self) -> Option<&str> {
+        self.content.as_deref()
+    }
+
+    pub fn username(&self) -> Option<&str> {
+        self.username.as_deref()
+    }
+
+    pub fn avatar_url(&self) -> Option<&str> {
+        self.avatar_url.as_deref()
+    }
+
+    pub fn tts(&self) -> bool {
+        self.tts
+    }
+
+    pub fn flags(&self) -> MessageFlags {
+        self.flags.unwrap_or_default()
+    }
+
+    pub fn embeds(&self) -> &[Embed] {
+        &self.embeds
+    }
+
+    pub fn components(&self) -> &[TopLevelComponent] {
+        &self.components
+    }
+
+    pub fn allowed_mentions(&self) -> Option<&AllowedMentions> {
+        self.allowed_mentions.as_ref()
+    }
+
+    pub fn thread_name(&self) -> Option<&str> {
+        self.thread_name.as_deref()
+    }
+
+    pub fn thread_id(&self) -> Option<u64> {
+        self.thread_id
+    }
+
+    /// The custom headers added with [`Webhook::add_header`].
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// The webhook url with its token redacted, safe to log.
+    pub fn redacted_url(&self) -> String {
+        self.webhook_url.redacted()
+    }
+}
+
+/// Headers the crate manages itself, which [`Webhook::add_header`] may not override.
+const RESERVED_HEADERS: [HeaderName; 4] = [CONTENT_TYPE, CONTENT_LENGTH, CONTENT_ENCODING, HOST];
+
+/// Whether `url` is a bare http(s) origin: a scheme and host, optionally with a port.
+fn is_origin(url: &str) -> bool {
+    let host = match url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")) {
+        Some(host) => host.strip_suffix('/').unwrap_or(host),
+        None => return false,
+    };
+    !host.is_empty()
+        && host.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':' | '[' | ']'))
+}
+
+const IMAGE_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "gif", "webp"];
+
+/// Discord only renders http(s) URLs, and if the path has an extension it must be an image.
+fn is_image_url(url: &str) -> bool {
+    let rest = match url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let path = rest.split(['?', '#']).next().unwrap_or("");
+    let (host, path) = path.split_once('/').unwrap_or((path, ""));
+    if host.is_empty() {
+        return false;
+    }
+    let last_segment = path.rsplit('/').next().unwrap_or("");
+    match last_segment.rsplit_once('.') {
+        Some((_, ext)) => IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()),
+        None => true,
+    }
+}
+
+/// Builds a Discord CDN avatar URL for a user.
+///
+/// Animated avatar hashes (prefixed with `a_`) are served as gifs, and `size` is clamped
+/// to the nearest power of two Discord accepts (16 to 4096, rounding down).
+pub fn avatar_url_for(user_id: u64, avatar_hash: &str, size: u16) -> String {
+    let extension = if avatar_hash.starts_with("a_") { "gif" } else { "png" };
+    let size = match size {
+        0..=16 => 16,
+        4096.. => 4096,
+        _ => 1 << (15 - size.leading_zeros()),
+    };
+    format!("https://cdn.discordapp.com/avatars/{}/{}.{}?size={}", user_id, avatar_hash, extension, size)
+}
+
+impl Default for Embed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Embed {
+    /// Parses an embed from JSON, e.g. a template file.
+    ///
+    /// `color` may be an integer, a `#RRGGBB` string, or a palette name such as `"blurple"`.
+    pub fn from_json<S: AsRef<str>>(json: S) -> Result<Embed, WebhookError> {
+        Ok(serde_json::from_str(json.as_ref())?)
+    }
+
+    /// Parses an embed from a JSON value, accepting the same colour forms as [`Embed::from_json`].
+    pub fn from_value(value: serde_json::Value) -> Result<Embed, WebhookError> {
+        Ok(serde_json::from_value(value)?)
+    }
+
+    pub fn new() -> Embed {
+        Embed {
+            title: None,
+            _type: "rich".to_string(),
+            description: None,
+            url: None,
+            timestamp: None,
+            color: None,
+            footer: None,
+            image: None,
+            thumbnail: None,
+            video: None,
+            provider: None,
+            author: None,
+            fields: Vec::new(),
+            localized_titles: HashMap::new(),
+        }
+    }
+    pub fn set_title<S: AsRef<str>>(mut self, title: S) -> Self {
+        self.title = Some(title.as_ref().to_string());
+        self
+    }
+    /// Sets per-locale variants of the title, keyed by Discord locale such as `fr` or `pt-BR`.
+    ///
+    /// The variant matching [`Webhook::set_locale`] replaces the title when sent, falling back
+    /// to the variant for the base language and then to the plain title.
+    pub fn set_title_localized(mut self, titles: HashMap<String, String>) -> Self {
+        self.localized_titles = titles;
+        self
+    }
+
+    fn localized_title(&self, locale: &str) -> Option<&String> {
+        let language = locale.split(['-', '_']).next().unwrap_or(locale);
+        self.localized_titles.get(locale).or_else(|| self.localized_titles.get(language))
+    }
+
+    pub fn set_description<S: AsRef<str>>(mut self, description: S) -> Self {
+        self.description = Some(description.as_ref().to_string());
+        self
+    }
+    pub fn set_url<S: AsRef<str>>(mut self, url: S) -> Self {
+        self.url = Some(url.as_ref().to_string());
+        self
+    }
+    pub fn set_timestamp(self, timestamp: Option<&std::time::SystemTime>) -> Self {
+        let timestamp: DateTime<Utc> = match timestamp {
+            Some(ts) => (*ts).into(),
+            None => Utc::now(),
+        };
+        self.set_timestamp_chrono(timestamp)
+    }
+    /// Like [`Embed::set_timestamp`], from a chrono timestamp in any time zone. It's sent in UTC.
+    pub fn set_timestamp_chrono<Tz: TimeZone>(mut self, timestamp: DateTime<Tz>) -> Self {
+        self.timestamp = Some(timestamp.with_timezone(&Utc).format("%+").to_string());
+        self
+    }
+    /// Takes a [`ColourType`], or a [`Colour`] from Discord's palette.
+    ///
+    /// Hex that doesn't parse falls back to grey (`0x999999`), and integers above `0xFFFFFF`
+    /// are clamped to it. Use [`Embed::try_set_colour`] to catch either.
+    pub fn set_colour<S: AsRef<str>, C: Into<ColourType<S>>>(mut self, colour: C) -> Self {
+        let colour = match colour::parse_colour(colour.into()) {
+            Some(colour) => colour.min(colour::MAX_COLOUR),
+            None => colour::FALLBACK_COLOUR,
+        };
+        self.color = Some(colour);
+        self
+    }
+    pub fn set_color<S: AsRef<str>, C: Into<ColourType<S>>>(self, color: C) -> Self {
+        self.set_colour(color)
+    }
+    /// Like [`Embed::set_colour`], but fails on hex that doesn't parse or a colour above
+    /// `0xFFFFFF`, as Discord only accepts 24-bit colours.
+    pub fn try_set_colour<S: AsRef<str>, C: Into<ColourType<S>>>(mut self, colour: C) -> Result<Self, ValidationError> {
+        let colour = colour::parse_colour(colour.into()).ok_or(ValidationError::Invalid { field: "colour", reason: "not a hex colour" })?;
+        if colour > colour::MAX_COLOUR {
+            return Err(ValidationError::Invalid { field: "colour", reason: "must be at most 0xFFFFFF" });
+        }
+        self.color = Some(colour);
+        Ok(self)
+    }
+    pub fn try_set_color<S: AsRef<str>, C: Into<ColourType<S>>>(self, color: C) -> Result<Self, ValidationError> {
+        self.try_set_colour(color)
+    }
+    /// The colour that will be sent, however it was set. The same as [`Embed::colour`].
+    pub fn effective_colour(&self) -> Option<u32> {
+        self.colour()
+    }
+    pub fn effective_color(&self) -> Option<u32> {
+        self.colour()
+    }
+    /// Sets the footer. The text can be anything [`Display`], like a version number.
+    pub fn set_footer<A: Display, B: AsRef<str>, C: AsRef<str>>(
+        mut self,
+        text: A,
+        icon_url: Option<B>,
+        proxy_icon_url: Option<C>,
+    ) -> Self {
+        let icon_url = icon_url.map(|n| n.as_ref().to_string());
+        let proxy_icon_url = proxy_icon_url.map(|n| n.as_ref().to_string());
+
+        self.footer = Some(Arc::new(Footer {
+            text: text.to_string(),
+            icon_url,
+            proxy_icon_url,
+        }));
+
+        self
+    }
+    pub fn set_image<A: AsRef<str>, B: AsRef<str>>(
+        mut self,
+        url: A,
+        proxy_url: Option<B>,
+        height: Option<usize>,
+        width: Option<usize>,
+    ) -> Self {
+        let proxy_url = proxy_url.map(|p| p.as_ref().to_string());
+
+        self.image = Some(Image {
+            url: url.as_ref().to_string(),
+            proxy_url,
+            height,
+            width,
+        });
+
+        self
+    }
+    pub fn set_thumbnail<A: AsRef<str>, B: AsRef<str>>(
+        mut self,
+        url: A,
+        proxy_url: Option<B>,
+        height: Option<usize>,
+        width: Option<usize>,
+    ) -> Self {
+        let proxy_url = proxy_url.map(|p| p.as_ref().to_string());
+
+        self.thumbnail = Some(Thumbnail {
+            url: url.as_ref().to_string(),
+            proxy_url,
+            height,
+            width,
+        });
+
+        self
+    }
+    pub fn set_video<A: AsRef<str>, B: AsRef<str>>(
+        mut self,
+        url: A,
+        proxy_url: Option<B>,
         height: Option<usize>,
         width: Option<usize>,
     ) -> Self {
@@ -409,203 +1548,1276 @@ impl Embed {
         let url = url.map(|n| n.as_ref().to_string());
         let icon_url = icon_url.map(|n| n.as_ref().to_string());
         let proxy_icon_url = proxy_icon_url.map(|n| n.as_ref().to_string());
-        self.author = Some(Author {
+        self.author = Some(Arc::new(Author {
             name: name.as_ref().to_string(),
             url,
             icon_url,
             proxy_icon_url,
-        });
+        }));
+
+        self
+    }
+    /// Adds a field. The value can be anything [`Display`], so numbers and other types don't
+    /// need formatting into a string first.
+    ///
+    /// ```
+    /// use std::fmt;
+    /// use blurple_hook::Embed;
+    ///
+    /// struct Uptime(u64);
+    ///
+    /// impl fmt::Display for Uptime {
+    ///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    ///         write!(f, "{}h {}m", self.0 / 60, self.0 % 60)
+    ///     }
+    /// }
+    ///
+    /// let embed = Embed::new()
+    ///     .add_field("Requests", 1204u64, true)
+    ///     .add_field("Error rate", format_args!("{:.2}%", 0.4567), true)
+    ///     .add_field("Uptime", Uptime(135), true);
+    ///
+    /// let fields = &serde_json::to_value(&embed).unwrap()["fields"];
+    /// assert_eq!(fields[0]["value"], "1204");
+    /// assert_eq!(fields[1]["value"], "0.46%");
+    /// assert_eq!(fields[2]["value"], "2h 15m");
+    /// ```
+    pub fn add_field<A: AsRef<str>, B: Display>(mut self, name: A, value: B, inline: bool) -> Self {
+        let field = Field {
+            name: name.as_ref().to_string(),
+            value: value.to_string(),
+            inline,
+        };
+
+        self.fields.push(field);
+
+        self
+    }
+
+    pub fn add_fields(mut self, fields: &mut Vec<Field>) -> Self {
+        self.fields.append(fields);
+        self
+    }
+
+    /// Like [`Embed::set_title`], but fails if the title exceeds Discord's limit.
+    pub fn try_set_title<S: AsRef<str>>(self, title: S) -> Result<Self, WebhookError> {
+        check_length("title", title.as_ref(), MAX_TITLE).map_err(|e| e.in_setter("try_set_title"))?;
+        Ok(self.set_title(title))
+    }
+
+    /// Like [`Embed::set_description`], but fails if the description exceeds Discord's limit.
+    pub fn try_set_description<S: AsRef<str>>(self, description: S) -> Result<Self, WebhookError> {
+        check_length("description", description.as_ref(), MAX_DESCRIPTION).map_err(|e| e.in_setter("try_set_description"))?;
+        Ok(self.set_description(description))
+    }
+
+    /// Like [`Embed::set_footer`], but fails if the footer text exceeds Discord's limit.
+    pub fn try_set_footer<A: Display, B: AsRef<str>, C: AsRef<str>>(
+        self,
+        text: A,
+        icon_url: Option<B>,
+        proxy_icon_url: Option<C>,
+    ) -> Result<Self, WebhookError> {
+        let text = text.to_string();
+        check_length("footer text", &text, MAX_FOOTER).map_err(|e| e.in_setter("try_set_footer"))?;
+        Ok(self.set_footer(text, icon_url, proxy_icon_url))
+    }
+
+    /// Like [`Embed::set_author`], but fails if the author name exceeds Discord's limit.
+    pub fn try_set_author<A: AsRef<str>, B: AsRef<str>, C: AsRef<str>, D: AsRef<str>>(
+        self,
+        name: A,
+        url: Option<B>,
+        icon_url: Option<C>,
+        proxy_icon_url: Option<D>,
+    ) -> Result<Self, WebhookError> {
+        check_length("author name", name.as_ref(), MAX_AUTHOR_NAME).map_err(|e| e.in_setter("try_set_author"))?;
+        Ok(self.set_author(name, url, icon_url, proxy_icon_url))
+    }
+
+    /// Like [`Embed::add_field`], but fails if the field breaks a limit or the embed would
+    /// exceed 25 fields.
+    pub fn try_add_field<A: AsRef<str>, B: Display>(self, name: A, value: B, inline: bool) -> Result<Self, WebhookError> {
+        let value = value.to_string();
+        let check = || {
+            check_count("fields", self.fields.len() + 1, MAX_FIELDS)?;
+            check_length("field name", name.as_ref(), MAX_FIELD_NAME)?;
+            check_length("field value", &value, MAX_FIELD_VALUE)
+        };
+        check().map_err(|e| e.in_setter("try_add_field"))?;
+        Ok(self.add_field(name, value, inline))
+    }
+
+    /// Adds a field showing `value` as pretty-printed JSON in a code block, truncated to fit
+    /// Discord's field value limit. Only fails if `value` can't be serialized.
+    pub fn add_field_json<A: AsRef<str>, T: Serialize>(self, name: A, value: &T, inline: bool) -> Result<Self, WebhookError> {
+        let json = serde_json::to_string_pretty(value)?;
+        Ok(self.add_field(name, code_block("json", &json, MAX_FIELD_VALUE), inline))
+    }
+
+    /// Like [`Embed::add_field_json`], but shows the value's pretty `{:#?}` output.
+    pub fn add_field_debug<A: AsRef<str>, T: std::fmt::Debug>(self, name: A, value: &T, inline: bool) -> Self {
+        self.add_field(name, code_block("rust", &format!("{:#?}", value), MAX_FIELD_VALUE), inline)
+    }
+
+    /// The characters that count towards Discord's 6000 character limit: title, description,
+    /// field names and values, footer text and author name.
+    pub fn character_count(&self) -> usize {
+        let count = |text: &Option<String>| text.as_ref().map_or(0, |t| t.chars().count());
+        count(&self.title)
+            + count(&self.description)
+            + self.fields.iter().map(|f| f.name.chars().count() + f.value.chars().count()).sum::<usize>()
+            + self.footer.as_ref().map_or(0, |f| f.text.chars().count())
+            + self.author.as_ref().map_or(0, |a| a.name.chars().count())
+    }
+
+    /// Adds a bolded, full-width header field followed by one inline field per row.
+    pub fn add_section<S: AsRef<str>>(mut self, header: S, rows: Vec<(String, String)>) -> Self {
+        self.fields.push(Field {
+            name: format!("**{}**", header.as_ref()),
+            // Discord rejects empty field values, so the header carries a zero-width space
+            value: String::from("\u{200b}"),
+            inline: false,
+        });
+        self.fields.extend(rows.into_iter().map(|(name, value)| Field { name, value, inline: true }));
+        self
+    }
+
+    /// Compares only the fields an author sets, ignoring those Discord fills in on received
+    /// embeds (`type`, proxy urls and media dimensions). Timestamps are compared as instants.
+    pub fn content_eq(&self, other: &Embed) -> bool {
+        let timestamps_eq = match (&self.timestamp, &other.timestamp) {
+            (Some(a), Some(b)) => match (DateTime::parse_from_rfc3339(a), DateTime::parse_from_rfc3339(b)) {
+                (Ok(a), Ok(b)) => a == b,
+                _ => a == b,
+            },
+            (a, b) => a == b,
+        };
+        let footers_eq = match (&self.footer, &other.footer) {
+            (Some(a), Some(b)) => a.text == b.text && a.icon_url == b.icon_url,
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        let authors_eq = match (&self.author, &other.author) {
+            (Some(a), Some(b)) => a.name == b.name && a.url == b.url && a.icon_url == b.icon_url,
+            (a, b) => a.is_none() && b.is_none(),
+        };
+
+        self.title == other.title
+            && self.description == other.description
+            && self.url == other.url
+            && timestamps_eq
+            && self.color == other.color
+            && footers_eq
+            && self.image.as_ref().map(|i| &i.url) == other.image.as_ref().map(|i| &i.url)
+            && self.thumbnail.as_ref().map(|t| &t.url) == other.thumbnail.as_ref().map(|t| &t.url)
+            && self.video.as_ref().map(|v| &v.url) == other.video.as_ref().map(|v| &v.url)
+            && self.provider == other.provider
+            && authors_eq
+            && self.fields == other.fields
+    }
+
+    /// Clones this embed as a template, replacing only the fields.
+    /// The footer and author are shared with the template rather than deep-copied.
+    pub fn clone_with_fields(&self, fields: Vec<Field>) -> Embed {
+        Embed {
+            title: self.title.clone(),
+            _type: self._type.clone(),
+            description: self.description.clone(),
+            url: self.url.clone(),
+            timestamp: self.timestamp.clone(),
+            color: self.color,
+            footer: self.footer.clone(),
+            image: self.image.clone(),
+            thumbnail: self.thumbnail.clone(),
+            video: self.video.clone(),
+            provider: self.provider.clone(),
+            author: self.author.clone(),
+            fields,
+            localized_titles: self.localized_titles.clone(),
+        }
+    }
+
+    /// Breaks a description over 4096 characters into continuation embeds (see
+    /// [`validation::split_text`]), adding `part 2/3` style numbering to each footer. Embeds
+    /// within the limit are returned unchanged.
+    ///
+    /// The first part keeps the title, url, author and thumbnail, and the last part the fields,
+    /// image and timestamp. Every part keeps the colour and the footer icon.
+    pub fn split_description(mut self) -> Vec<Embed> {
+        let pieces = match &self.description {
+            Some(description) if description.chars().count() > MAX_DESCRIPTION => validation::split_text(description, MAX_DESCRIPTION),
+            _ => return vec![self],
+        };
+        let footer = self.footer.take();
+        let last = pieces.len() - 1;
+        let mut parts = Vec::with_capacity(pieces.len());
+        for (i, piece) in pieces.into_iter().enumerate() {
+            let mut part = Embed { _type: self._type.clone(), color: self.color, ..Embed::new() };
+            if i == 0 {
+                part.title = self.title.take();
+                part.url = self.url.take();
+                part.author = self.author.take();
+                part.thumbnail = self.thumbnail.take();
+                part.localized_titles = std::mem::take(&mut self.localized_titles);
+            }
+            if i == last {
+                part.fields = std::mem::take(&mut self.fields);
+                part.image = self.image.take();
+                part.video = self.video.take();
+                part.provider = self.provider.take();
+                part.timestamp = self.timestamp.take();
+            }
+            let numbering = format!("part {}/{}", i + 1, last + 1);
+            part.footer = Some(Arc::new(Footer {
+                text: match footer.as_deref() {
+                    Some(footer) if !footer.text.is_empty() => format!("{} · {}", footer.text, numbering),
+                    _ => numbering,
+                },
+                icon_url: footer.as_ref().and_then(|footer| footer.icon_url.clone()),
+                proxy_icon_url: None,
+            }));
+            part.description = Some(piece);
+            parts.push(part);
+        }
+        parts
+    }
+
+    /// Applies builder methods in place, like [`Webhook::update`].
+    pub fn update<F: FnOnce(Embed) -> Embed>(&mut self, f: F) -> &mut Self {
+        *self = f(std::mem::take(self));
+        self
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// The colour as sent, a 24-bit RGB integer.
+    pub fn colour(&self) -> Option<u32> {
+        self.color.and_then(|colour| u32::try_from(colour).ok())
+    }
+
+    pub fn color(&self) -> Option<u32> {
+        self.colour()
+    }
+
+    /// The timestamp as sent, an ISO 8601 string.
+    pub fn timestamp(&self) -> Option<&str> {
+        self.timestamp.as_deref()
+    }
+
+    pub fn footer(&self) -> Option<&Footer> {
+        self.footer.as_deref()
+    }
+
+    pub fn image(&self) -> Option<&Image> {
+        self.image.as_ref()
+    }
+
+    pub fn thumbnail(&self) -> Option<&Thumbnail> {
+        self.thumbnail.as_ref()
+    }
+
+    pub fn video(&self) -> Option<&Video> {
+        self.video.as_ref()
+    }
+
+    pub fn provider(&self) -> Option<&Provider> {
+        self.provider.as_ref()
+    }
+
+    pub fn author(&self) -> Option<&Author> {
+        self.author.as_deref()
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::{assert_payload_matches, avatar_url_for, MockSender, WebhookError, Author, Colour, ColourType, Embed, Field, Footer, MessageFlags, Thumbnail, ValidationError, Webhook};
+    use crate::testing::assert_embed_field;
+
+    #[test]
+    fn create_embed() {
+        let embed = Embed::new()
+            .set_colour(ColourType::Hex("#FFFFFF"))
+            .set_author("Author Name", Some("https://example.com/"), None::<String>, None::<String>)
+            .set_thumbnail("https://example.com/", None::<String>, None, None)
+            .set_title("Example")
+            .set_url("https://example.com/")
+            .set_footer("Footer Text", None::<String>, None::<String>)
+            .set_description("Description Text")
+            .add_field("Example 1", "Value 1", true)
+            .add_fields(&mut vec![
+                Field {
+                    name: "Example 2".to_string(),
+                    value: "Value 2".to_string(),
+                    inline: false,
+                },
+                Field {
+                    name: "Example 3".to_string(),
+                    value: "Value 3".to_string(),
+                    inline: false,
+                }
+            ]);
+
+        let expected = Embed {
+            title: Some("Example".to_string()),
+            _type: "rich".to_string(),
+            description: Some("Description Text".to_string()),
+            url: Some("https://example.com/".to_string()),
+            timestamp: None,
+            color: Some(16777215),
+            footer: Some(Arc::new(Footer {
+                text: "Footer Text".to_string(),
+                icon_url: None,
+                proxy_icon_url: None,
+            })),
+            image: None,
+            thumbnail: Some(Thumbnail {
+                url: "https://example.com/".to_string(),
+                proxy_url: None,
+                height: None,
+                width: None,
+            }),
+            video: None,
+            provider: None,
+            author: Some(Arc::new(Author {
+                name: "Author Name".to_string(),
+                url: Some("https://example.com/".to_string()),
+                icon_url: None,
+                proxy_icon_url: None,
+            })),
+            fields: vec![
+                Field {
+                    name: "Example 1".to_string(),
+                    value: "Value 1".to_string(),
+                    inline: true,
+                },
+                Field {
+                    name: "Example 2".to_string(),
+                    value: "Value 2".to_string(),
+                    inline: false,
+                },
+                Field {
+                    name: "Example 3".to_string(),
+                    value: "Value 3".to_string(),
+                    inline: false,
+                }
+            ],
+            localized_titles: Default::default(),
+        };
+        assert_eq!(embed, expected);
+    }
+
+    #[test]
+    fn embed_adds_to_webhook() {
+        let webhook = Webhook::new("https://discord.com/webhook")
+            .set_content("Content Text")
+            .set_username("Test Username");
+
+        let embed = Embed::new()
+            .set_colour(ColourType::Hex("#FFFFFF"))
+            .set_author("Author Name", Some("https://example.com/"), None::<String>, None::<String>)
+            .set_thumbnail("https://example.com/", None::<String>, None, None)
+            .set_title("Example")
+            .set_url("https://example.com/")
+            .set_footer("Footer Text", None::<String>, None::<String>)
+            .set_description("Description Text")
+            .add_field("Example 1", "Value 1", true)
+            .add_fields(&mut vec![
+                Field {
+                    name: "Example 2".to_string(),
+                    value: "Value 2".to_string(),
+                    inline: false,
+                },
+                Field {
+                    name: "Example 3".to_string(),
+                    value: "Value 3".to_string(),
+                    inline: false,
+                }
+            ]);
+
+        let webhook = webhook.add_embed(embed);
+
+        let expected = Webhook {
+            webhook_url: "https://discord.com/webhook".into(),
+            content: Some("Content Text".to_string()),
+            username: Some("Test Username".to_string()),
+            avatar_url: None,
+            tts: false,
+            flags: None,
+            embeds: vec![
+                Embed {
+                    title: Some("Example".to_string()),
+                    _type: "rich".to_string(),
+                    description: Some("Description Text".to_string()),
+                    url: Some("https://example.com/".to_string()),
+                    timestamp: None,
+                    color: Some(16777215),
+                    footer: Some(Arc::new(Footer {
+                        text: "Footer Text".to_string(),
+                        icon_url: None,
+                        proxy_icon_url: None,
+                    })),
+                    image: None,
+                    thumbnail: Some(Thumbnail {
+                        url: "https://example.com/".to_string(),
+                        proxy_url: None,
+                        height: None,
+                        width: None,
+                    }),
+                    video: None,
+                    provider: None,
+                    author: Some(Arc::new(Author {
+                        name: "Author Name".to_string(),
+                        url: Some("https://example.com/".to_string()),
+                        icon_url: None,
+                        proxy_icon_url: None,
+                    })),
+                    fields: vec![
+                        Field {
+                            name: "Example 1".to_string(),
+                            value: "Value 1".to_string(),
+                            inline: true,
+                        },
+                        Field {
+                            name: "Example 2".to_string(),
+                            value: "Value 2".to_string(),
+                            inline: false,
+                        },
+                        Field {
+                            name: "Example 3".to_string(),
+                            value: "Value 3".to_string(),
+                            inline: false,
+                        }
+                    ],
+                    localized_titles: Default::default(),
+                }
+            ],
+            components: vec![],
+            allowed_mentions: None,
+            thread_name: None,
+            thread_id: None,
+            attachments: vec![],
+            files: vec![],
+            with_components: false,
+            headers: vec![],
+            allowed_extensions: None,
+            auto_timestamp: false,
+            skip_validation: false,
+            locale: None,
+            #[cfg(feature = "retry")]
+            max_retries: None,
+            timeout: None,
+            #[cfg(feature = "retry")]
+            retry_policy: None,
+        };
+
+        assert_eq!(webhook, expected);
+    }
+
+    #[test]
+    fn webhook_to_value() {
+        let webhook = Webhook::new("https://discord.com/webhook")
+            .set_content("Content Text")
+            .add_embed(Embed::new().set_title("Example"));
+
+        assert_payload_matches!(webhook, serde_json::json!({"content": "Content Text", "embeds": [{"title": "Example"}]}));
+        assert!(webhook.to_value().unwrap().get("webhook_url").is_none());
+    }
+
+    #[test]
+    fn unset_fields_are_omitted() {
+        let webhook = Webhook::new("https://discord.com/webhook").set_content("Content Text");
+        assert_eq!(serde_json::to_string(&webhook).unwrap(), r#"{"content":"Content Text"}"#);
+
+        let embed = Embed::new().set_title("Example").set_footer("Footer", None::<&str>, None::<&str>);
+        assert_eq!(serde_json::to_string(&embed).unwrap(), r#"{"title":"Example","type":"rich","footer":{"text":"Footer"}}"#);
+
+        let parsed: Webhook = serde_json::from_str(r#"{"content":"Content Text"}"#).unwrap();
+        assert_eq!(parsed.content.as_deref(), Some("Content Text"));
+    }
+
+    #[tokio::test]
+    async fn mock_sender_records_webhooks() {
+        let sender = MockSender::new();
+        let webhook = Webhook::new("https://discord.com/webhook").set_content("First");
+
+        assert!(webhook.send_with(&sender).await.is_ok());
+        assert_eq!(sender.sent_webhooks(), vec![webhook.clone()]);
+        assert_payload_matches!(sender.sent_webhooks()[0], serde_json::json!({"content": "First"}));
+
+        sender.set_failure(Some((400, "Bad Request")));
+        let result = webhook.send_with(&sender).await;
+        assert!(matches!(result, Err(WebhookError::Api { status: 400, .. })));
+        assert_eq!(sender.sent_webhooks().len(), 2);
+    }
+
+    #[test]
+    fn webhook_debug_redacts_token() {
+        let webhook = Webhook::new("https://discord.com/api/webhooks/123456/secret-token?thread_id=1");
+        let debug = format!("{:?}", webhook);
+        assert!(!debug.contains("secret-token"));
+        assert!(debug.contains("https://discord.com/api/webhooks/123456/[redacted]?thread_id=1"));
+        assert_eq!(webhook.webhook_url.to_string(), "https://discord.com/api/webhooks/123456/[redacted]?thread_id=1");
+        assert_eq!(format!("{}", crate::SecretUrl::from("not a webhook")), "[redacted]");
+    }
+
+    #[tokio::test]
+    async fn per_url_state_debug_redacts_token() {
+        let url = "http://127.0.0.1:9/api/webhooks/123456/secret-token";
+        let webhook = Webhook::new(url).set_content("Content Text");
+        let sender = crate::HttpSender::new()
+            .circuit_breaker(crate::CircuitBreaker::new(1, std::time::Duration::from_secs(60)))
+            .rate_limit_buckets(crate::RateLimitBuckets::new());
+        let err = webhook.send_with(&sender).await.unwrap_err();
+        assert!(!err.to_string().contains("secret-token"));
+        assert!(!format!("{:?}", err).contains("secret-token"));
+
+        let deduping = crate::DedupingWebhook::with_sender(crate::MockSender::new(), std::time::Duration::from_secs(60));
+        deduping.send(&webhook).await.unwrap();
+        for debug in [format!("{:?}", sender), format!("{:?}", deduping)] {
+            assert!(!debug.contains("secret-token"), "{}", debug);
+        }
+        assert!(format!("{:?}", sender).contains("123456/[redacted]"));
+    }
+
+    #[test]
+    fn embed_from_json_colours() {
+        let parse = |colour: &str| {
+            Embed::from_json(format!(r#"{{"title": "Template", "color": {}}}"#, colour))
+        };
+        assert_eq!(parse("16777215").unwrap().color, Some(16777215));
+        assert_eq!(parse(r##""#5865F2""##).unwrap().color, Some(0x5865F2));
+        assert_eq!(parse(r#""Blurple""#).unwrap().color, Some(0x5865F2));
+        assert_eq!(parse("null").unwrap().color, None);
+        assert!(parse(r#""not-a-colour""#).unwrap_err().to_string().contains("unknown colour name"));
+
+        let embed = Embed::from_value(serde_json::json!({"title": "Template"})).unwrap();
+        assert_eq!(embed, Embed::new().set_title("Template"));
+    }
+
+    #[tokio::test]
+    async fn with_components_query() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/webhooks/1/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri()))
+            .set_content("Content Text")
+            .set_thread_id(1000000000000000001)
+            .set_with_components(true);
+        let message = webhook.send().await.unwrap();
+        assert_eq!(message.id, "1100000000000000001");
+        assert_eq!(message.channel_id, "1000000000000000001");
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests[0].url.query(), Some("wait=true&thread_id=1000000000000000001&with_components=true"));
+    }
+
+    #[tokio::test]
+    async fn thread_id_is_a_query_param() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/webhooks/1/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PATCH"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE")).respond_with(ResponseTemplate::new(204)).expect(1).mount(&server).await;
+
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri()))
+            .set_content("Content Text")
+            .set_thread_id(1200000000000000001);
+        let message = webhook.send().await.unwrap();
+        assert!(webhook.to_value().unwrap().get("thread_id").is_none());
+        webhook.edit(&message.id).await.unwrap();
+        webhook.delete_message(&message.id).await.unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        let queries: Vec<Option<&str>> = requests.iter().map(|request| request.url.query()).collect();
+        assert_eq!(queries, [Some("wait=true&thread_id=1200000000000000001"), Some("thread_id=1200000000000000001"), Some("thread_id=1200000000000000001")]);
+    }
+
+    #[test]
+    fn thread_name_or_thread_id() {
+        let webhook = Webhook::new("https://discord.com/api/webhooks/1/token").set_thread_name("Release 1.2");
+        assert_payload_matches!(webhook, serde_json::json!({"thread_name": "Release 1.2"}));
+        assert_eq!(webhook.validate(), Ok(()));
+        assert_eq!(
+            webhook.set_thread_id(1).validate(),
+            Err(ValidationError::Invalid { field: "thread", reason: "thread_id and thread_name can't both be set" })
+        );
+    }
+
+    #[tokio::test]
+    async fn custom_headers_are_sent() {
+        use wiremock::matchers::{header, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("X-Org-Token", "signed"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri()))
+            .set_content("Content Text")
+            .add_header("X-Org-Token", "unsigned")
+            .add_header("x-org-token", "signed");
+        webhook.send().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reserved_and_invalid_headers_are_rejected() {
+        let webhook = Webhook::new("http://127.0.0.1:9/api/webhooks/1/token").set_content("Content Text");
+
+        let result = webhook.clone().add_header("Content-Type", "text/plain").send_with(&crate::HttpSender::new()).await;
+        assert!(matches!(result, Err(WebhookError::InvalidHeader(name)) if name == "Content-Type"));
+
+        let result = webhook.add_header("Bad Header", "value").send_with(&crate::HttpSender::new()).await;
+        assert!(matches!(result, Err(WebhookError::InvalidHeader(_))));
+    }
+
+    #[cfg(feature = "retry")]
+    #[tokio::test]
+    async fn custom_headers_are_sent_on_retry() {
+        use wiremock::matchers::{header, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("X-Org-Token", "signed"))
+            .respond_with(ResponseTemplate::new(429).insert_header("x-ratelimit-reset-after", "0.01"))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(header("X-Org-Token", "signed"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri()))
+            .set_content("Content Text")
+            .add_header("X-Org-Token", "signed");
+        webhook.send().await.unwrap();
+    }
+
+    #[cfg(feature = "retry")]
+    #[tokio::test]
+    async fn webhook_retry_limit_overrides_the_sender() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("x-ratelimit-reset-after", "0.25"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri()))
+            .set_content("Content Text")
+            .set_max_retries(0);
+        let err = webhook.send_with(&crate::HttpSender::new().max_retries(5)).await.unwrap_err();
+        assert!(matches!(
+            err,
+            WebhookError::RateLimited { attempts: 1, waited, retry_after, .. } if waited.is_zero() && retry_after.as_millis() == 250
+        ), "{:?}", err);
+    }
+
+    #[test]
+    fn allowed_extensions() {
+        let webhook = Webhook::new("https://discord.com/webhook");
+        assert!(webhook.allows_filename("secrets.env"));
+
+        let webhook = webhook.set_allowed_extensions(vec![".PNG".to_string(), "txt".to_string()]);
+        assert!(webhook.allows_filename("chart.png"));
+        assert!(webhook.allows_filename("log.TXT"));
+        assert!(!webhook.allows_filename("secrets.env"));
+        assert!(!webhook.allows_filename("Makefile"));
+    }
+
+    #[test]
+    fn embed_content_eq_ignores_discord_fields() {
+        let sent = Embed::new()
+            .set_title("Example")
+            .set_colour(ColourType::Hex("#FFFFFF"))
+            .set_image("https://example.com/image.png", None::<String>, None, None)
+            .set_footer("Footer Text", Some("https://example.com/icon.png"), None::<String>)
+            .add_field("Example 1", "Value 1", true);
+        let sent = Embed { timestamp: Some("2024-06-01T12:00:00.000000+00:00".to_string()), ..sent };
+
+        let received = Embed::from_value(serde_json::json!({
+            "type": "rich",
+            "title": "Example",
+            "color": 16777215,
+            "timestamp": "2024-06-01T12:00:00+00:00",
+            "image": {"url": "https://example.com/image.png", "proxy_url": "https://media.discordapp.net/image.png", "width": 640, "height": 480},
+            "footer": {"text": "Footer Text", "icon_url": "https://example.com/icon.png", "proxy_icon_url": "https://images-ext-1.discordapp.net/icon.png"},
+            "fields": [{"name": "Example 1", "value": "Value 1", "inline": true}]
+        })).unwrap();
+
+        assert_ne!(sent, received);
+        assert!(sent.content_eq(&received));
+        assert!(!sent.content_eq(&received.set_title("Other")));
+    }
+
+    #[test]
+    fn embed_add_section() {
+        let embed = Embed::new().add_section("Build", vec![
+            ("Status".to_string(), "Passed".to_string()),
+            ("Duration".to_string(), "4m 12s".to_string()),
+        ]);
+
+        assert_eq!(embed.fields, vec![
+            Field { name: "**Build**".to_string(), value: "\u{200b}".to_string(), inline: false },
+            Field { name: "Status".to_string(), value: "Passed".to_string(), inline: true },
+            Field { name: "Duration".to_string(), value: "4m 12s".to_string(), inline: true },
+        ]);
+    }
+
+    #[test]
+    fn update_builds_in_place() {
+        let mut webhook = Webhook::new("https://discord.com/api/webhooks/1/token");
+        let mut embed = Embed::new().set_title("Disk usage");
+        for (host, usage) in [("db-1", "91%"), ("db-2", "40%")] {
+            embed.update(|e| e.add_field(host, usage, true));
+        }
+        if embed.fields().len() > 1 {
+            webhook.update(|w| w.set_content("Nightly report")).update(|w| w.add_embed(embed));
+        }
+
+        let expected = Webhook::new("https://discord.com/api/webhooks/1/token").set_content("Nightly report").add_embed(
+            Embed::new().set_title("Disk usage").add_field("db-1", "91%", true).add_field("db-2", "40%", true),
+        );
+        assert_eq!(webhook, expected);
+    }
+
+    #[test]
+    fn getters_read_back_the_payload() {
+        let embed = Embed::new()
+            .set_title("Deploy")
+            .set_description("v1.2.3 is live")
+            .set_colour(Colour::Green)
+            .set_footer("ci", Some("https://example.com/ci.png"), None::<&str>)
+            .set_image("https://example.com/graph.png", None::<&str>, Some(600), Some(800))
+            .set_author("release-bot", None::<&str>, None::<&str>, None::<&str>)
+            .add_field("Env", "prod", true);
+        let webhook = Webhook::new("https://discord.com/api/webhooks/1/secret-token")
+            .set_content("Shipped")
+            .set_username("Blurple Hook")
+            .set_thread_name("Releases")
+            .silent()
+            .add_header("X-Audit-Id", "7")
+            .add_embed(embed);
+
+        assert_eq!((webhook.content(), webhook.username(), webhook.avatar_url()), (Some("Shipped"), Some("Blurple Hook"), None));
+        assert_eq!((webhook.thread_name(), webhook.thread_id(), webhook.tts()), (Some("Releases"), None, false));
+        assert!(webhook.flags().contains(crate::MessageFlags::SUPPRESS_NOTIFICATIONS));
+        assert_eq!(webhook.headers(), [(String::from("X-Audit-Id"), String::from("7"))]);
+        assert_eq!(webhook.redacted_url(), "https://discord.com/api/webhooks/1/[redacted]");
+
+        let embed = &webhook.embeds()[0];
+        assert_eq!((embed.title(), embed.description()), (Some("Deploy"), Some("v1.2.3 is live")));
+        assert_eq!((embed.colour(), embed.color(), embed.effective_colour()), (Some(0x57F287), Some(0x57F287), Some(0x57F287)));
+        assert_eq!(embed.footer().map(|footer| (footer.text(), footer.icon_url())), Some(("ci", Some("https://example.com/ci.png"))));
+        let image = embed.image().unwrap();
+        assert_eq!((image.url(), image.height(), image.width()), ("https://example.com/graph.png", Some(600), Some(800)));
+        assert_eq!(embed.author().map(Author::name), Some("release-bot"));
+        assert_eq!((embed.fields().len(), embed.thumbnail(), embed.provider()), (1, None, None));
+    }
+
+    #[test]
+    fn embeds_can_be_added_replaced_and_removed() {
+        let embed = |title: &str| Embed::new().set_title(title);
+        let webhook = Webhook::new("https://discord.com/webhook")
+            .add_embed(embed("Build"))
+            .add_embeds(["Test", "Deploy"].map(embed))
+            .add_embeds(vec![embed("Verify")]);
+        assert_eq!(webhook.embeds, ["Build", "Test", "Deploy", "Verify"].map(embed));
+
+        let webhook = webhook.remove_embed(1).remove_embed(10);
+        assert_eq!(webhook.embeds, ["Build", "Deploy", "Verify"].map(embed));
+        assert_eq!(webhook.set_embeds(vec![embed("Rollback")]).embeds, [embed("Rollback")]);
+    }
+
+    #[test]
+    fn split_partitions_embeds() {
+        let webhook = Webhook::new("https://discord.com/webhook").set_content("Report");
+        let embeds = (0..25).map(|i| Embed::new().set_title(format!("Embed {}", i))).collect::<Vec<_>>();
+        let pages = webhook.clone().add_embeds(embeds.clone()).split();
+
+        assert_eq!(pages.iter().map(|p| p.embeds.len()).collect::<Vec<_>>(), vec![10, 10, 5]);
+        assert_eq!(pages[0].content.as_deref(), Some("Report"));
+        assert!(pages[1..].iter().all(|p| p.content.is_none()));
+        assert_eq!(pages.into_iter().flat_map(|p| p.embeds).collect::<Vec<_>>(), embeds);
+
+        // three 2500 character embeds can't share a message two at a time past 6000
+        let long = Embed::new().set_description("a".repeat(2500));
+        let pages = webhook.clone().add_embeds([long.clone(), long.clone(), long]).split();
+        assert_eq!(pages.iter().map(|p| p.embeds.len()).collect::<Vec<_>>(), vec![2, 1]);
+
+        assert_eq!(webhook.clone().split(), vec![webhook]);
+
+        // text-only messages can't be laid out with components
+        let pages = Webhook::new("https://discord.com/webhook")
+            .set_content("a ".repeat(1500))
+            .set_flags(MessageFlags::IS_COMPONENTS_V2)
+            .split();
+        assert_eq!(pages.iter().map(|p| p.flags).collect::<Vec<_>>(), [None, Some(MessageFlags::IS_COMPONENTS_V2)]);
+    }
+
+    #[test]
+    fn split_description_adds_continuation_embeds() {
+        let paragraphs = (0..3).map(|i| format!("{}{}", i, "a".repeat(2999))).collect::<Vec<_>>();
+        let embed = Embed::new()
+            .set_title("Incident report")
+            .set_description(paragraphs.join("\n"))
+            .set_colour(Colour::Red)
+            .set_footer("on-call", Some("https://example.com/icon.png"), None::<&str>)
+            .add_field("Severity", "high", true);
+
+        let parts = embed.clone().split_description();
+        assert_eq!(parts.len(), 3);
+        assert!(parts.iter().all(|part| crate::check_embed(part).is_ok() && part.color == embed.color));
+        assert_eq!(parts.iter().map(|part| part.description.clone().unwrap()).collect::<Vec<_>>(), paragraphs);
+        let footers = parts.iter().map(|part| part.footer.as_ref().unwrap().text.as_str()).collect::<Vec<_>>();
+        assert_eq!(footers, ["on-call · part 1/3", "on-call · part 2/3", "on-call · part 3/3"]);
+        assert_eq!((parts[0].title.as_deref(), parts[1].title.as_deref()), (Some("Incident report"), None));
+        assert_eq!((parts[1].fields.len(), parts[2].fields.len()), (0, 1));
+
+        let short = Embed::new().set_description("All clear");
+        assert_eq!(short.clone().split_description(), [short]);
+        let pages = Webhook::new("https://discord.com/webhook").add_embed(embed).split();
+        assert_eq!(pages.iter().map(|page| page.embeds.len()).collect::<Vec<_>>(), [1, 1, 1]);
+    }
+
+    #[tokio::test]
+    async fn send_paginated_breaks_long_content() {
+        let sender = MockSender::new();
+        let lines = (0..300).map(|i| format!("[INFO] request {} served", i)).collect::<Vec<_>>();
+        let webhook = Webhook::new("https://discord.com/webhook")
+            .set_content(lines.join("\n"))
+            .add_embed(Embed::new().set_title("Summary"))
+            .set_thread_name("Access log")
+            .set_tts(true)
+            .set_flags(MessageFlags::SUPPRESS_NOTIFICATIONS);
+
+        assert_eq!(webhook.send_paginated_with(&sender).await.unwrap().len(), 4);
+        let sent = sender.sent_webhooks();
+        assert!(sent.iter().all(|page| page.validate().is_ok()));
+        assert!(sent[..3].iter().all(|page| page.embeds.is_empty()));
+        // only the first message creates the thread and is read aloud; the rest go into it
+        assert_eq!((sent[0].thread_name.as_deref(), sent[0].tts, sent[0].thread_id), (Some("Access log"), true, None));
+        assert!(sent[1..].iter().all(|page| page.thread_name.is_none() && !page.tts && page.thread_id == Some(1000000000000000001)));
+        assert!(sent.iter().all(|page| page.flags == Some(MessageFlags::SUPPRESS_NOTIFICATIONS)));
+        assert_eq!(sent[3].embeds, [Embed::new().set_title("Summary")]);
+        let content = sent.iter().filter_map(|page| page.content.as_deref()).collect::<Vec<_>>().join("\n");
+        assert_eq!(content, lines.join("\n"));
+    }
+
+    #[tokio::test]
+    async fn send_paginated_sends_each_page() {
+        let sender = MockSender::new();
+        let embeds = (0..12).map(|i| Embed::new().set_title(format!("Embed {}", i))).collect::<Vec<_>>();
+        let webhook = Webhook::new("https://discord.com/webhook").add_embeds(embeds.clone());
+
+        let messages = webhook.send_paginated_with(&sender).await.unwrap();
+        assert_eq!(messages.iter().map(|message| message.id.as_str()).collect::<Vec<_>>(), ["1", "2"]);
+        let sent = sender.sent_webhooks();
+        assert_eq!(sent[0].embeds, embeds[..10]);
+        assert_eq!(sent[1].embeds, embeds[10..]);
+
+        let failing = MockSender::failing(400, "Bad Request");
+        let err = webhook.send_paginated_with(&failing).await.unwrap_err();
+        assert!(err.sent.is_empty());
+        assert_eq!(failing.sent_webhooks().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn send_paginated_waits_out_rate_limits() {
+        use wiremock::matchers::{body_partial_json, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({"embeds": [{"title": "Embed 10"}]})))
+            .respond_with(ResponseTemplate::new(429).insert_header("x-ratelimit-reset-after", "0.01"))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .expect(if cfg!(feature = "retry") { 2 } else { 1 })
+            .mount(&server)
+            .await;
+
+        let embeds = (0..15).map(|i| Embed::new().set_title(format!("Embed {}", i)));
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri())).add_embeds(embeds);
+        #[cfg(feature = "retry")]
+        assert_eq!(webhook.send_paginated().await.unwrap().len(), 2);
+
+        // without retries the 429 ends the send, reporting the page that got through
+        #[cfg(not(feature = "retry"))]
+        {
+            let err = webhook.send_paginated().await.unwrap_err();
+            assert_eq!(err.sent.len(), 1);
+            assert!(err.error.is_rate_limited());
+        }
+    }
+
+    #[test]
+    fn timestamps_from_chrono() {
+        use chrono::{FixedOffset, TimeZone, Utc};
+
+        let utc = Utc.with_ymd_and_hms(2024, 7, 1, 12, 30, 0).unwrap() + chrono::Duration::nanoseconds(123_456_789);
+        let embed = Embed::new().set_timestamp_chrono(utc);
+        assert_eq!(embed.timestamp.as_deref(), Some("2024-07-01T12:30:00.123456789+00:00"));
+
+        let offset = utc.with_timezone(&FixedOffset::east_opt(2 * 3600).unwrap());
+        assert_eq!(Embed::new().set_timestamp_chrono(offset), embed);
+        let system = std::time::SystemTime::from(utc);
+        assert_eq!(Embed::new().set_timestamp(Some(&system)), embed);
+    }
+
+    #[tokio::test]
+    async fn auto_timestamp_fills_missing_timestamps() {
+        let sender = MockSender::new();
+        let stamped = Embed::new().set_title("Stamped").set_timestamp(Some(&std::time::UNIX_EPOCH));
+        let webhook = Webhook::new("https://discord.com/webhook")
+            .add_embed(Embed::new().set_title("Unstamped"))
+            .add_embed(stamped.clone())
+            .auto_timestamp(true);
+
+        webhook.send_with(&sender).await.unwrap();
+        let sent = &sender.sent_webhooks()[0];
+        assert!(sent.embeds[0].timestamp.is_some());
+        assert_eq!(sent.embeds[1], stamped);
+        assert!(webhook.embeds[0].timestamp.is_none());
+
+        let webhook = webhook.auto_timestamp(false);
+        webhook.send_with(&sender).await.unwrap();
+        assert!(sender.sent_webhooks()[1].embeds[0].timestamp.is_none());
+    }
+
+    #[test]
+    fn strict_setters_short_circuit() {
+        let result = (|| {
+            let embed = Embed::new()
+                .try_set_title("Report")?
+                .try_add_field("Status", "OK", true)?
+                .try_add_field("Log", "x".repeat(1025), false)?
+                .try_set_description("never reached")?;
+            Webhook::new("https://discord.com/webhook").try_add_embed(embed)
+        })();
+
+        match result {
+            Err(WebhookError::InvalidSetter { setter, error }) => {
+                assert_eq!(setter, "try_add_field");
+                assert_eq!(error, crate::ValidationError::TooLong { field: "field value", length: 1025, limit: 1024 });
+            },
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let webhook = Webhook::new("https://discord.com/webhook")
+            .try_set_content("x".repeat(2000))
+            .unwrap();
+        let full = (0..10).fold(webhook, |w, _| w.try_add_embed(Embed::new().set_title("Embed")).unwrap());
+        let err = full.try_add_embed(Embed::new()).unwrap_err();
+        assert_eq!(err.to_string(), "try_add_embed: embeds has 11 items, the limit is 10");
+    }
 
-        self
+    #[test]
+    fn set_base_url_keeps_webhook_path() {
+        let webhook = Webhook::new("https://discord.com/api/webhooks/123/token?thread_id=1")
+            .set_base_url("http://localhost:8080/")
+            .unwrap();
+        assert_eq!(webhook.webhook_url.as_str(), "http://localhost:8080/api/webhooks/123/token?thread_id=1");
+
+        let webhook = Webhook::new("https://discord.com/api/webhooks/123/token");
+        assert!(matches!(webhook.clone().set_base_url("ftp://example.com"), Err(WebhookError::InvalidUrl(_))));
+        assert!(matches!(webhook.clone().set_base_url("https://example.com/api"), Err(WebhookError::InvalidUrl(_))));
+        assert!(matches!(webhook.set_base_url("https://"), Err(WebhookError::InvalidUrl(_))));
+        assert!(Webhook::new("not a url").set_base_url("https://example.com").is_err());
     }
-    pub fn add_field<A: AsRef<str>, B: AsRef<str>>(mut self, name: A, value: B, inline: bool) -> Self {
-        let field = Field {
-            name: name.as_ref().to_string(),
-            value: value.as_ref().to_string(),
-            inline,
-        };
 
-        self.fields.push(field);
+    #[test]
+    fn embed_effective_colour() {
+        assert_eq!(Embed::new().effective_colour(), None);
+        assert_eq!(Embed::new().set_colour(ColourType::Hex("#5865F2")).effective_colour(), Some(0x5865F2));
+        assert_eq!(Embed::new().set_colour(Colour::Blurple).effective_colour(), Some(0x5865F2));
+        assert_eq!(Embed::new().set_color(ColourType::from(Colour::Fuchsia)).effective_color(), Some(0xEB459E));
+        assert_eq!(Embed::new().set_color(ColourType::<&str>::Integer(255)).effective_color(), Some(255));
+        assert_eq!(Embed::new().set_colour(ColourType::rgb(88, 101, 242)).effective_colour(), Some(0x5865F2));
+        assert_eq!(Embed::new().set_colour(ColourType::<&str>::Rgb(255, 255, 255)).effective_colour(), Some(0xFFFFFF));
+        let seeded = Embed::new().set_colour(ColourType::FromSeed("seed"));
+        assert_eq!(seeded.effective_colour(), seeded.color.map(|c| c as u32));
+        assert_eq!(Embed::from_json(r#"{"color": "red"}"#).unwrap().effective_colour(), Some(0xED4245));
+    }
 
-        self
+    #[test]
+    fn invalid_colours() {
+        assert_eq!(Embed::new().set_colour(ColourType::Hex("#GGGGGG")).effective_colour(), Some(0x999999));
+        assert_eq!(Embed::new().set_colour(ColourType::<&str>::Integer(0x1000000)).effective_colour(), Some(0xFFFFFF));
+
+        let invalid = |reason| Err(ValidationError::Invalid { field: "colour", reason });
+        assert_eq!(Embed::new().try_set_colour(ColourType::Hex("#GGGGGG")), invalid("not a hex colour"));
+        assert_eq!(Embed::new().try_set_colour(ColourType::Hex("")), invalid("not a hex colour"));
+        assert_eq!(Embed::new().try_set_colour(ColourType::Hex("#1000000")), invalid("must be at most 0xFFFFFF"));
+        assert_eq!(Embed::new().try_set_color(ColourType::<&str>::Integer(usize::MAX)), invalid("must be at most 0xFFFFFF"));
+        assert_eq!(Embed::new().try_set_colour(ColourType::Hex("0xFFFFFF")).unwrap().effective_colour(), Some(0xFFFFFF));
+        assert_eq!(Embed::new().try_set_colour(ColourType::Hex("#FA0")).unwrap().effective_colour(), Some(0xFFAA00));
+        assert_eq!(Embed::new().try_set_colour(ColourType::Hex("0xFA0")).unwrap().effective_colour(), Some(0xFA0));
+        assert_eq!(Embed::new().try_set_colour(ColourType::Hex("FFF")).unwrap().effective_colour(), Some(0xFFF));
+        assert_eq!(Embed::new().try_set_colour(ColourType::Hex("#FFZZFF")), invalid("not a hex colour"));
+        assert_eq!(Embed::from_json(r##"{"color": "#fff"}"##).unwrap().effective_colour(), Some(0xFFFFFF));
+        assert!(Embed::new().try_set_colour(ColourType::FromSeed("seed")).is_ok());
     }
 
-    pub fn add_fields(mut self, fields: &mut Vec<Field>) -> Self {
-        self.fields.append(fields);
-        self
+    #[cfg(feature = "queue")]
+    #[tokio::test]
+    async fn queue_keeps_per_url_order() {
+        use crate::queue::WebhookQueue;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&server)
+            .await;
+
+        let queue = WebhookQueue::new();
+        let busy = format!("{}/api/webhooks/1/busy", server.uri());
+        let quiet = format!("{}/api/webhooks/2/quiet", server.uri());
+        let mut webhooks: Vec<Webhook> = (1..=7).map(|i| Webhook::new(&busy).set_content(i.to_string())).collect();
+        webhooks.push(Webhook::new(&quiet).set_content("quiet"));
+        WebhookQueue::enqueue_multi(Arc::clone(&queue.webhooks), webhooks).await;
+        queue.start().drain().await;
+
+        let requests = server.received_requests().await.unwrap();
+        let sent: Vec<(String, String)> = requests
+            .iter()
+            .map(|r| {
+                let body: serde_json::Value = serde_json::from_slice(&r.body).unwrap();
+                (r.url.path().to_string(), body["content"].as_str().unwrap().to_string())
+            })
+            .collect();
+        let busy_order: Vec<&str> = sent.iter().filter(|(p, _)| p.ends_with("busy")).map(|(_, c)| c.as_str()).collect();
+        assert_eq!(busy_order, ["1", "2", "3", "4", "5", "6", "7"]);
+        // the quiet url takes its turn instead of waiting behind the busy one
+        assert_eq!(sent[1].1, "quiet");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::env;
-    use crate::{Author, ColourType, Embed, Field, Footer, Thumbnail, Webhook};
+    #[test]
+    fn localized_titles_follow_the_locale() {
+        let titles = [("fr", "Alerte"), ("pt-BR", "Alerta")]
+            .into_iter()
+            .map(|(locale, title)| (locale.to_string(), title.to_string()))
+            .collect();
+        let webhook = Webhook::new("https://discord.com/webhook")
+            .add_embed(Embed::new().set_title("Alert").set_title_localized(titles));
+
+        let title = |title: &str| serde_json::json!({"embeds": [{"title": title}]});
+        assert_payload_matches!(webhook, title("Alert"));
+        assert_payload_matches!(webhook.clone().set_locale("pt-BR"), title("Alerta"));
+        assert_payload_matches!(webhook.clone().set_locale("fr-CA"), title("Alerte"));
+        assert_payload_matches!(webhook.set_locale("de"), title("Alert"));
+    }
 
     #[test]
-    fn create_embed() {
+    fn json_fields_keep_fences_balanced() {
+        #[derive(Debug, serde::Serialize)]
+        struct Job {
+            name: &'static str,
+            steps: Vec<(u32, &'static str)>,
+        }
+        let job = Job { name: "deploy", steps: (0..100).map(|i| (i, "```ok```")).collect() };
+
+        let embed = Embed::new().add_field_json("Job", &job, false).unwrap().add_field_debug("Job", &job, false);
+        for (field, language) in embed.fields.iter().zip(["json", "rust"]) {
+            assert_eq!(field.value.chars().count(), crate::validation::MAX_FIELD_VALUE);
+            assert!(field.value.starts_with(&format!("```{}\n", language)));
+            assert!(field.value.ends_with("…\n```"));
+            assert_eq!(field.value.matches("```").count(), 2, "{}", field.value);
+        }
+
+        let small = Embed::new().add_field_json("Step", &(1, "build"), true).unwrap();
+        assert_eq!(small.fields[0].value, "```json\n[\n  1,\n  \"build\"\n]\n```");
+    }
+
+    #[test]
+    fn display_values() {
         let embed = Embed::new()
-            .set_colour(ColourType::Hex("#FFFFFF"))
-            .set_author("Author Name", Some("https://example.com/"), None::<String>, None::<String>)
-            .set_thumbnail("https://example.com/", None::<String>, None, None)
-            .set_title("Example")
-            .set_url("https://example.com/")
-            .set_footer("Footer Text", None::<String>, None::<String>)
-            .set_description("Description Text")
-            .add_field("Example 1", "Value 1", true)
-            .add_fields(&mut vec![
-                Field {
-                    name: "Example 2".to_string(),
-                    value: "Value 2".to_string(),
-                    inline: false,
-                },
-                Field {
-                    name: "Example 3".to_string(),
-                    value: "Value 3".to_string(),
-                    inline: false,
-                }
-            ]);
+            .add_field("Took", format_args!("{:.1}s", std::time::Duration::from_millis(1530).as_secs_f64()), true)
+            .add_field("Healthy", true, true)
+            .try_add_field("Count", 42u64, true)
+            .unwrap()
+            .set_footer(format_args!("v{}.{}", 1, 2), None::<&str>, None::<&str>);
+        assert_embed_field(&embed, "Took", "1.5s");
+        assert_embed_field(&embed, "Healthy", "true");
+        assert_embed_field(&embed, "Count", "42");
+        assert_payload_matches!(embed, serde_json::json!({"footer": {"text": "v1.2"}}));
+    }
 
-        let expected = Embed {
-            title: Some("Example".to_string()),
-            _type: "rich".to_string(),
-            description: Some("Description Text".to_string()),
-            url: Some("https://example.com/".to_string()),
-            timestamp: None,
-            color: Some(16777215),
-            footer: Some(Footer {
-                text: "Footer Text".to_string(),
-                icon_url: None,
-                proxy_icon_url: None,
-            }),
-            image: None,
-            thumbnail: Some(Thumbnail {
-                url: "https://example.com/".to_string(),
-                proxy_url: None,
-                height: None,
-                width: None,
-            }),
-            video: None,
-            provider: None,
-            author: Some(Author {
-                name: "Author Name".to_string(),
-                url: Some("https://example.com/".to_string()),
-                icon_url: None,
-                proxy_icon_url: None,
-            }),
-            fields: vec![
-                Field {
-                    name: "Example 1".to_string(),
-                    value: "Value 1".to_string(),
-                    inline: true,
-                },
-                Field {
-                    name: "Example 2".to_string(),
-                    value: "Value 2".to_string(),
-                    inline: false,
-                },
-                Field {
-                    name: "Example 3".to_string(),
-                    value: "Value 3".to_string(),
-                    inline: false,
-                }
-            ],
-        };
-        assert_eq!(embed, expected);
+    #[test]
+    fn message_url_from_webhook_url() {
+        let webhook = Webhook::new("https://discord.com/api/webhooks/123456789012345678/abc-DEF_ghi");
+        assert_eq!(
+            webhook.message_url("1100000000000000001").unwrap(),
+            "https://discord.com/api/webhooks/123456789012345678/abc-DEF_ghi/messages/1100000000000000001"
+        );
+        let webhook = Webhook::new("https://discord.com/api/webhooks/123456789012345678/abc-DEF_ghi/");
+        assert!(webhook.message_url("1").unwrap().ends_with("/abc-DEF_ghi/messages/1"));
+        assert!(matches!(webhook.message_url(""), Err(WebhookError::InvalidMessageId(_))));
+    }
+
+    #[tokio::test]
+    async fn edit_patches_the_message() {
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("PATCH"))
+            .and(path("/api/webhooks/1/token/messages/1100000000000000001"))
+            .and(body_partial_json(serde_json::json!({"content": "Content Text"})))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PATCH"))
+            .and(path("/api/webhooks/1/token/messages/2"))
+            .respond_with(ResponseTemplate::new(404).set_body_string(r#"{"message": "Unknown Message", "code": 10008}"#))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri())).set_content("Content Text");
+        let message = webhook.edit("1100000000000000001").await.unwrap();
+        assert_eq!(message.id, "1100000000000000001");
+
+        let err = webhook.edit_message("2").await.unwrap_err();
+        assert!(matches!(&err, WebhookError::MessageNotFound { message_id } if message_id == "2"));
+        assert!(err.is_message_not_found());
+
+        // would otherwise PATCH the webhook itself
+        let err = webhook.edit("../x").await.unwrap_err();
+        assert!(matches!(err, WebhookError::InvalidMessageId(id) if id == "../x"));
     }
 
     #[test]
-    fn embed_adds_to_webhook() {
-        let webhook = Webhook::new("https://discord.com/webhook")
-            .set_content("Content Text")
-            .set_username("Test Username");
+    fn edits_clear_unset_fields() {
+        let webhook = Webhook::new("https://discord.com/api/webhooks/1/token")
+            .set_content("Deployed")
+            .add_embed(Embed::new().set_title("Deploying"))
+            .set_embeds(vec![]);
+        assert_eq!(
+            serde_json::to_value(webhook.edit_payload()).unwrap(),
+            serde_json::json!({"content": "Deployed", "embeds": [], "components": []})
+        );
+        // sends still leave them out
+        assert_eq!(serde_json::to_value(&webhook).unwrap(), serde_json::json!({"content": "Deployed"}));
 
-        let embed = Embed::new()
-            .set_colour(ColourType::Hex("#FFFFFF"))
-            .set_author("Author Name", Some("https://example.com/"), None::<String>, None::<String>)
-            .set_thumbnail("https://example.com/", None::<String>, None, None)
-            .set_title("Example")
-            .set_url("https://example.com/")
-            .set_footer("Footer Text", None::<String>, None::<String>)
-            .set_description("Description Text")
-            .add_field("Example 1", "Value 1", true)
-            .add_fields(&mut vec![
-                Field {
-                    name: "Example 2".to_string(),
-                    value: "Value 2".to_string(),
-                    inline: false,
-                },
-                Field {
-                    name: "Example 3".to_string(),
-                    value: "Value 3".to_string(),
-                    inline: false,
-                }
-            ]);
+        let webhook = Webhook::new("https://discord.com/api/webhooks/1/token").add_embed(Embed::new().set_title("Deployed"));
+        let payload = serde_json::to_value(webhook.edit_payload()).unwrap();
+        assert_eq!(payload["content"], serde_json::Value::Null);
+        assert_eq!(payload["embeds"][0]["title"], "Deployed");
+    }
 
-        let webhook = webhook.add_embed(embed);
+    #[tokio::test]
+    async fn delete_message_sends_no_body() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
 
-        let expected = Webhook {
-            webhook_url: "https://discord.com/webhook".to_string(),
-            content: Some("Content Text".to_string()),
-            username: Some("Test Username".to_string()),
-            avatar_url: None,
-            embeds: vec![
-                Embed {
-                    title: Some("Example".to_string()),
-                    _type: "rich".to_string(),
-                    description: Some("Description Text".to_string()),
-                    url: Some("https://example.com/".to_string()),
-                    timestamp: None,
-                    color: Some(16777215),
-                    footer: Some(Footer {
-                        text: "Footer Text".to_string(),
-                        icon_url: None,
-                        proxy_icon_url: None,
-                    }),
-                    image: None,
-                    thumbnail: Some(Thumbnail {
-                        url: "https://example.com/".to_string(),
-                        proxy_url: None,
-                        height: None,
-                        width: None,
-                    }),
-                    video: None,
-                    provider: None,
-                    author: Some(Author {
-                        name: "Author Name".to_string(),
-                        url: Some("https://example.com/".to_string()),
-                        icon_url: None,
-                        proxy_icon_url: None,
-                    }),
-                    fields: vec![
-                        Field {
-                            name: "Example 1".to_string(),
-                            value: "Value 1".to_string(),
-                            inline: true,
-                        },
-                        Field {
-                            name: "Example 2".to_string(),
-                            value: "Value 2".to_string(),
-                            inline: false,
-                        },
-                        Field {
-                            name: "Example 3".to_string(),
-                            value: "Value 3".to_string(),
-                            inline: false,
-                        }
-                    ],
-                }
-            ],
-            components: vec![],
-        };
+        let server = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/webhooks/1/token/messages/1100000000000000001"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/webhooks/1/token/messages/2"))
+            .respond_with(ResponseTemplate::new(403).set_body_string(r#"{"message": "Missing Access", "code": 50001}"#))
+            .expect(1)
+            .mount(&server)
+            .await;
 
-        assert_eq!(webhook, expected);
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri()));
+        webhook.delete_message("1100000000000000001").await.unwrap();
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests[0].body.is_empty());
+        assert!(!requests[0].headers.contains_key("content-type"));
+
+        let err = webhook.delete_message("2").await.unwrap_err();
+        assert_eq!(err.discord_code(), Some(crate::DiscordErrorCode::MissingAccess));
+
+        let err = webhook.delete_message("../2").await.unwrap_err();
+        assert!(matches!(err, WebhookError::InvalidMessageId(id) if id == "../2"));
+    }
+
+    #[test]
+    fn avatar_url_validation() {
+        let webhook = Webhook::new("https://discord.com/webhook");
+        assert!(webhook.clone().set_avatar_url("https://example.com/avatar.png").validate().is_ok());
+        assert!(webhook.clone().set_avatar_url("https://example.com/avatar?size=64").validate().is_ok());
+        assert!(webhook.clone().set_avatar_url("ftp://example.com/avatar.png").validate().is_err());
+        assert!(webhook.set_avatar_url("https://example.com/avatar.html").validate().is_err());
+    }
+
+    #[test]
+    fn avatar_url_for_cdn() {
+        assert_eq!(
+            avatar_url_for(80351110224678912, "a_1269e74af4df7417b13759eae50c83dc", 128),
+            "https://cdn.discordapp.com/avatars/80351110224678912/a_1269e74af4df7417b13759eae50c83dc.gif?size=128"
+        );
+        assert_eq!(
+            avatar_url_for(80351110224678912, "1269e74af4df7417b13759eae50c83dc", 100),
+            "https://cdn.discordapp.com/avatars/80351110224678912/1269e74af4df7417b13759eae50c83dc.png?size=64"
+        );
+        assert!(avatar_url_for(1, "abc", 0).ends_with("?size=16"));
+        assert!(avatar_url_for(1, "abc", 9000).ends_with("?size=4096"));
     }
 
     #[tokio::test]
     async fn submit_webhook() {
 
-        let webhook_url = env::var("WEBHOOK").unwrap();
-        let webhook = Webhook::new(&webhook_url);
+        let webhook = Webhook::from_env("WEBHOOK").unwrap();
 
         let embed = Embed::new()
             .set_title("Blurple Test")
@@ -636,6 +2848,234 @@ mod tests {
 
         assert!(result.is_ok());
     }
+    #[cfg(feature = "queue")]
+    #[tokio::test(start_paused = true)]
+    async fn queue_sends_to_different_urls_concurrently() {
+        use crate::queue::WebhookQueue;
+        use crate::RateLimitConfig;
+        use std::sync::Arc;
+        use std::time::Duration;
+        use tokio::time::Instant;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .expect(6)
+            .mount(&server)
+            .await;
+
+        // the pool's idle timer would be the next timer paused time jumps to while a request is out
+        let client = crate::reqwest::Client::builder().pool_idle_timeout(None).build().unwrap();
+        let queue = WebhookQueue::with_sender(crate::HttpSender::with_client(client))
+            .rate_limit(RateLimitConfig { per_interval: 1, interval: Duration::from_secs(1) });
+        let webhooks = (0..6)
+            .map(|i| Webhook::new(format!("{}/api/webhooks/{}/token", server.uri(), i % 2)).set_content(i.to_string()))
+            .collect();
+        WebhookQueue::enqueue_multi(Arc::clone(&queue.webhooks), webhooks).await;
+
+        let started = Instant::now();
+        queue.start().drain().await;
+        // each url waits out its own rate limit twice, at the same time as the other url
+        assert_eq!(started.elapsed(), Duration::from_secs(2));
+
+        let requests = server.received_requests().await.unwrap();
+        let order: Vec<(String, String)> = requests
+            .iter()
+            .map(|request| {
+                let payload: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                (request.url.path().to_string(), payload["content"].as_str().unwrap().to_string())
+            })
+            .collect();
+        for url in ["/api/webhooks/0/token", "/api/webhooks/1/token"] {
+            let contents: Vec<&str> = order.iter().filter(|(path, _)| path == url).map(|(_, content)| content.as_str()).collect();
+            let mut sorted = contents.clone();
+            sorted.sort();
+            assert_eq!(contents, sorted, "{} sent out of order", url);
+        }
+    }
+
+    #[cfg(feature = "queue")]
+    #[tokio::test]
+    async fn queue_shutdown_returns_unsent_webhooks() {
+        use crate::queue::WebhookQueue;
+        use std::time::Duration;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(crate::message::MESSAGE_JSON, "application/json")
+                    .set_delay(Duration::from_millis(300)),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let handle = WebhookQueue::new().start();
+        let url = format!("{}/api/webhooks/1/token", server.uri());
+        let webhooks = (1..=3).map(|i| Webhook::new(&url).set_content(i.to_string())).collect();
+        WebhookQueue::enqueue_multi(handle.webhooks(), webhooks).await;
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        let unsent: Vec<String> = handle.shutdown().await.into_iter().filter_map(|webhook| webhook.content).collect();
+        assert_eq!(unsent, ["2", "3"]);
+    }
+
+    #[cfg(feature = "queue")]
+    #[tokio::test]
+    async fn queue_reports_results() {
+        use crate::queue::WebhookQueue;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let ok = || ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json");
+        Mock::given(method("POST")).and(path("/api/webhooks/1/ok")).respond_with(ok()).expect(1).mount(&server).await;
+        Mock::given(method("POST"))
+            .and(path("/api/webhooks/2/limited"))
+            .respond_with(ResponseTemplate::new(429).set_body_json(serde_json::json!({"message": "You are being rate limited.", "retry_after": 0.1, "global": false})))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).and(path("/api/webhooks/2/limited")).respond_with(ok()).expect(1).mount(&server).await;
+        Mock::given(method("POST"))
+            .and(path("/api/webhooks/3/invalid"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(serde_json::json!({"message": "Invalid Form Body", "code": 50035})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // leave the 429 to the queue rather than the sender's own retries
+        let sender = crate::HttpSender::new();
+        #[cfg(feature = "retry")]
+        let sender = sender.max_retries(0);
+        let mut queue = WebhookQueue::with_sender(sender);
+        let mut results = queue.results();
+        let webhooks = ["1/ok", "2/limited", "3/invalid"]
+            .iter()
+            .map(|path| Webhook::new(format!("{}/api/webhooks/{}", server.uri(), path)).set_content(*path))
+            .collect();
+        WebhookQueue::enqueue_multi(Arc::clone(&queue.webhooks), webhooks).await;
+        queue.start().drain().await;
+
+        let mut outcomes = Vec::new();
+        while let Ok((webhook, result)) = results.try_recv() {
+            outcomes.push((webhook.content.unwrap(), result.map_err(|err| err.discord_code())));
+        }
+        outcomes.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(outcomes.len(), 3);
+        assert_eq!(outcomes[0].1.as_ref().map(|message| message.id.as_str()), Ok("1100000000000000001"));
+        assert!(outcomes[1].1.is_ok());
+        assert_eq!(outcomes[2].1.as_ref().err(), Some(&Some(crate::DiscordErrorCode::InvalidFormBody)));
+    }
+
+    #[cfg(feature = "queue")]
+    #[tokio::test]
+    async fn queue_orders_by_priority() {
+        use crate::queue::{Priority, WebhookQueue};
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .mount(&server)
+            .await;
+
+        let queue = WebhookQueue::new();
+        let url = format!("{}/api/webhooks/1/token", server.uri());
+        let webhook = |content: &str| Webhook::new(&url).set_content(content);
+        let webhooks = || Arc::clone(&queue.webhooks);
+        WebhookQueue::enqueue(webhooks(), webhook("a")).await;
+        WebhookQueue::enqueue_with_priority(webhooks(), webhook("low"), Priority::Low).await;
+        WebhookQueue::enqueue_multi(webhooks(), vec![webhook("b"), webhook("c")]).await;
+        WebhookQueue::enqueue_front(webhooks(), webhook("first")).await;
+        WebhookQueue::enqueue_front(webhooks(), webhook("very first")).await;
+        WebhookQueue::enqueue_with_priority(webhooks(), webhook("high"), Priority::High).await;
+        WebhookQueue::enqueue(webhooks(), webhook("d")).await;
+        assert_eq!(queue.webhooks.lock().await.len(), 8);
+        queue.start().drain().await;
+
+        let sent: Vec<String> = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|request| serde_json::from_slice::<serde_json::Value>(&request.body).unwrap()["content"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(sent, ["high", "very first", "first", "a", "b", "c", "d", "low"]);
+    }
+
+    #[cfg(feature = "queue")]
+    #[tokio::test]
+    async fn queue_uses_the_configured_rate_limit() {
+        use crate::queue::WebhookQueue;
+        use crate::RateLimitConfig;
+        use std::time::{Duration, Instant};
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .expect(8)
+            .mount(&server)
+            .await;
+
+        let url = format!("{}/api/webhooks/1/token", server.uri());
+        let webhooks = || (0..4).map(|i| Webhook::new(&url).set_content(i.to_string())).collect();
+
+        // the default 5 per 2 seconds would make the last of 8 wait a few seconds
+        let queue = WebhookQueue::with_config(RateLimitConfig { per_interval: 8, interval: Duration::from_secs(2) });
+        WebhookQueue::enqueue_multi(Arc::clone(&queue.webhooks), webhooks()).await;
+        WebhookQueue::enqueue_multi(Arc::clone(&queue.webhooks), webhooks()).await;
+        let started = Instant::now();
+        queue.start().drain().await;
+        assert!(started.elapsed() < Duration::from_millis(500), "{:?}", started.elapsed());
+
+        let unlimited = RateLimitConfig { per_interval: 8, interval: Duration::ZERO };
+        assert!(std::panic::catch_unwind(|| WebhookQueue::with_config(unlimited)).is_err());
+    }
+
+    #[cfg(feature = "queue")]
+    #[tokio::test]
+    async fn queue_keeps_failures_without_results() {
+        use crate::queue::WebhookQueue;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/webhooks/1/ok"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/webhooks/2/gone"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({"message": "Unknown Webhook", "code": 10015})))
+            .mount(&server)
+            .await;
+
+        let handle = WebhookQueue::new().start();
+        let webhook = |path: &str| Webhook::new(format!("{}/api/webhooks/{}", server.uri(), path)).set_content(path);
+        WebhookQueue::enqueue_multi(handle.webhooks(), vec![webhook("1/ok"), webhook("2/gone")]).await;
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+
+        let failed = handle.failed().await;
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0.content.as_deref(), Some("2/gone"));
+        assert!(failed[0].1.is_unknown_webhook());
+        assert!(handle.failed().await.is_empty());
+
+        WebhookQueue::enqueue(handle.webhooks(), webhook("2/gone")).await;
+        assert_eq!(handle.drain().await.len(), 1);
+    }
+
     #[cfg(feature = "queue")]
     #[tokio::test]
     async fn test_queue() {
@@ -644,13 +3084,13 @@ mod tests {
 
         let queue = WebhookQueue::new();
 
-        let webhook_url = env::var("WEBHOOK").unwrap();
+        let webhook = Webhook::from_env("WEBHOOK").unwrap();
 
         let webhooks = Arc::clone(&queue.webhooks);
-        for i in 0..5 {
+        for _ in 0..5 {
             let webhooks = Arc::clone(&webhooks);
             let embed = Embed::new().set_title("Example");
-            let webhook = Webhook::new(&webhook_url).add_embed(embed);
+            let webhook = webhook.clone().add_embed(embed);
 
             WebhookQueue::enqueue(webhooks, webhook).await;
         }
@@ -661,7 +3101,7 @@ mod tests {
             assert_eq!(webhooks.len(), 5, "Len is not 5, {}", webhooks.len());
         }
 
-        let _ = queue.start().await;
+        queue.start().drain().await;
 
         let webhooks = Arc::clone(&webhooks);
         let webhooks = webhooks.lock().await;
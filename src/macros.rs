@@ -0,0 +1,145 @@
+/// Checks a webhook url literal at compile time, expanding to the literal.
+///
+/// The url must be `https://` on a Discord host, with an `/api/webhooks/{id}/{token}` path
+/// (optionally versioned, as in `/api/v10/webhooks/`), a numeric id and a non-empty token.
+///
+/// ```
+/// use blurple_hook::{webhook_url, Webhook};
+///
+/// let webhook = Webhook::new(webhook_url!("https://discord.com/api/webhooks/123/abc-DEF_456"));
+/// ```
+///
+/// A malformed literal fails to compile:
+///
+/// ```compile_fail
+/// blurple_hook::webhook_url!("https://dicsord.com/api/webhooks/123/abc");
+/// ```
+#[macro_export]
+macro_rules! webhook_url {
+    ($url:literal) => {{
+        const URL: &str = $url;
+        const _: () = $crate::__validate_webhook_url(URL);
+        URL
+    }};
+}
+
+const HOSTS: [&str; 4] = ["discord.com", "discordapp.com", "canary.discord.com", "ptb.discord.com"];
+
+/// Panics unless `url` is a Discord webhook url. `const` so [`webhook_url!`] can run it at
+/// compile time.
+#[doc(hidden)]
+pub const fn validate_webhook_url(url: &str) {
+    if let Err(reason) = parse_webhook_url(url) {
+        panic!("{}", reason);
+    }
+}
+
+/// Where the id and the token of a Discord webhook url start, or why it isn't one.
+pub(crate) const fn parse_webhook_url(url: &str) -> Result<(usize, usize), &'static str> {
+    let url = url.as_bytes();
+    let Some(mut at) = strip_prefix(url, 0, b"https://") else {
+        return Err("webhook url must start with https://");
+    };
+
+    let mut host = 0;
+    let mut matched = None;
+    while host < HOSTS.len() {
+        if let Some(end) = strip_prefix(url, at, HOSTS[host].as_bytes()) {
+            if end < url.len() && url[end] == b'/' {
+                matched = Some(end);
+            }
+        }
+        host += 1;
+    }
+    at = match matched {
+        Some(end) => end,
+        None => return Err("webhook url must be on discord.com"),
+    };
+
+    at = match strip_prefix(url, at, b"/api/") {
+        Some(end) => end,
+        None => return Err("webhook url path must start with /api/webhooks/"),
+    };
+    // an optional api version, such as `v10/`
+    if let Some(mut end) = strip_prefix(url, at, b"v") {
+        let digits = end;
+        while end < url.len() && url[end].is_ascii_digit() {
+            end += 1;
+        }
+        if end > digits && end < url.len() && url[end] == b'/' {
+            at = end + 1;
+        }
+    }
+    at = match strip_prefix(url, at, b"webhooks/") {
+        Some(end) => end,
+        None => return Err("webhook url path must start with /api/webhooks/"),
+    };
+
+    let id = at;
+    while at < url.len() && url[at].is_ascii_digit() {
+        at += 1;
+    }
+    if at == url.len() && at > id {
+        return Err("webhook url is missing the token after the id");
+    }
+    if at == id || url[at] != b'/' {
+        return Err("webhook url must have a numeric id after /webhooks/");
+    }
+    at += 1;
+
+    let token = at;
+    while at < url.len() && (url[at].is_ascii_alphanumeric() || url[at] == b'-' || url[at] == b'_') {
+        at += 1;
+    }
+    if at == token {
+        return Err("webhook url is missing the token after the id");
+    }
+    if at != url.len() {
+        return Err("webhook url has unexpected characters after the token");
+    }
+    Ok((id, token))
+}
+
+/// The index after `prefix` if `bytes` has it at `at`.
+const fn strip_prefix(bytes: &[u8], at: usize, prefix: &[u8]) -> Option<usize> {
+    if bytes.len() < at + prefix.len() {
+        return None;
+    }
+    let mut i = 0;
+    while i < prefix.len() {
+        if bytes[at + i] != prefix[i] {
+            return None;
+        }
+        i += 1;
+    }
+    Some(at + prefix.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate_webhook_url;
+
+    #[test]
+    fn accepts_webhook_urls() {
+        validate_webhook_url("https://discord.com/api/webhooks/123/abc-DEF_456");
+        validate_webhook_url("https://canary.discord.com/api/v10/webhooks/123/abc");
+        assert_eq!(crate::webhook_url!("https://discordapp.com/api/webhooks/1/token"), "https://discordapp.com/api/webhooks/1/token");
+    }
+
+    #[test]
+    fn rejects_malformed_urls() {
+        let cases = [
+            ("http://discord.com/api/webhooks/123/abc", "must start with https://"),
+            ("https://discord.com.evil.example/api/webhooks/123/abc", "must be on discord.com"),
+            ("https://discord.com/api/webhooks/abc/def", "numeric id"),
+            ("https://discord.com/api/webhooks/123/", "missing the token"),
+            ("https://discord.com/api/webhooks/123", "missing the token"),
+            ("https://discord.com/api/webhooks/123/abc?wait=true", "after the token"),
+        ];
+        for (url, message) in cases {
+            let panic = std::panic::catch_unwind(|| validate_webhook_url(url)).unwrap_err();
+            let panic = panic.downcast_ref::<String>().map(String::as_str).unwrap_or_default();
+            assert!(panic.contains(message), "{}: {}", url, panic);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+/// The part of an embed a piece of text is shown in, which decides how much markdown renders.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum EmbedContext {
+    Title,
+    Description,
+    FieldName,
+    FieldValue,
+    Footer,
+    AuthorName,
+}
+
+/// A markdown construct that Discord shows differently than the author likely expects.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MarkdownConstruct {
+    /// `# `, `## ` and `### ` headers.
+    Header,
+    /// `-# ` subtext.
+    Subtext,
+    /// `- `, `* ` and `1. ` list items.
+    List,
+    /// `> ` and `>>> ` block quotes.
+    BlockQuote,
+    /// Triple backtick code blocks.
+    CodeBlock,
+    /// `[text](url)` masked links.
+    MaskedLink,
+    /// Bold, italics, underline, strikethrough, spoilers and inline code.
+    InlineFormatting,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct MarkdownWarning {
+    /// The 1-based line the construct starts on.
+    pub line: usize,
+    pub construct: MarkdownConstruct,
+}
+
+/// Flags markdown in `text` that won't render as written in the given embed context.
+///
+/// Descriptions and field values render Discord's full markdown. Titles and field names only
+/// render inline formatting, and footers and author names render no markdown at all.
+pub fn check_embed_compatibility(text: &str, context: EmbedContext) -> Vec<MarkdownWarning> {
+    let (block_markdown, inline_markdown) = match context {
+        EmbedContext::Description | EmbedContext::FieldValue => return Vec::new(),
+        EmbedContext::Title | EmbedContext::FieldName => (false, true),
+        EmbedContext::Footer | EmbedContext::AuthorName => (false, false),
+    };
+
+    let mut warnings = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let mut warn = |construct| warnings.push(MarkdownWarning { line: i + 1, construct });
+        let trimmed = line.trim_start();
+
+        if !block_markdown {
+            if let Some(construct) = block_construct(trimmed) {
+                warn(construct);
+            }
+            if trimmed.contains("```") {
+                warn(MarkdownConstruct::CodeBlock);
+            }
+            if has_masked_link(line) {
+                warn(MarkdownConstruct::MaskedLink);
+            }
+        }
+        if !inline_markdown && has_inline_formatting(line) {
+            warn(MarkdownConstruct::InlineFormatting);
+        }
+    }
+    warnings
+}
+
+fn block_construct(line: &str) -> Option<MarkdownConstruct> {
+    if line.starts_with("-# ") {
+        return Some(MarkdownConstruct::Subtext);
+    }
+    if ["# ", "## ", "### "].iter().any(|p| line.starts_with(p)) {
+        return Some(MarkdownConstruct::Header);
+    }
+    if line.starts_with("> ") || line.starts_with(">>> ") {
+        return Some(MarkdownConstruct::BlockQuote);
+    }
+    if line.starts_with("- ") || line.starts_with("* ") {
+        return Some(MarkdownConstruct::List);
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && line[digits..].starts_with(". ") {
+        return Some(MarkdownConstruct::List);
+    }
+    None
+}
+
+fn has_masked_link(line: &str) -> bool {
+    line.find('[')
+        .and_then(|start| line[start..].find("](").map(|end| start + end))
+        .is_some_and(|end| line[end..].contains(')'))
+}
+
+fn has_inline_formatting(line: &str) -> bool {
+    ["**", "__", "~~", "||", "`"].iter().any(|marker| {
+        line.find(marker)
+            .is_some_and(|start| line[start + marker.len()..].contains(marker))
+    }) || ['*', '_'].iter().any(|&marker| {
+        line.find(marker)
+            .is_some_and(|start| line[start + 1..].contains(marker))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_embed_compatibility, EmbedContext, MarkdownConstruct, MarkdownWarning};
+
+    fn constructs(text: &str, context: EmbedContext) -> Vec<MarkdownConstruct> {
+        check_embed_compatibility(text, context).into_iter().map(|w| w.construct).collect()
+    }
+
+    #[test]
+    fn descriptions_render_everything() {
+        let text = "# Header\n- item\n> quote\n[link](https://example.com) **bold**";
+        assert!(check_embed_compatibility(text, EmbedContext::Description).is_empty());
+        assert!(check_embed_compatibility(text, EmbedContext::FieldValue).is_empty());
+    }
+
+    #[test]
+    fn titles_flag_block_markdown() {
+        assert_eq!(constructs("## Deploy **done**", EmbedContext::Title), vec![MarkdownConstruct::Header]);
+        assert_eq!(constructs("1. First", EmbedContext::FieldName), vec![MarkdownConstruct::List]);
+        assert_eq!(constructs("-# small", EmbedContext::Title), vec![MarkdownConstruct::Subtext]);
+        assert_eq!(
+            check_embed_compatibility("Status\nsee [logs](https://example.com)", EmbedContext::Title),
+            vec![MarkdownWarning { line: 2, construct: MarkdownConstruct::MaskedLink }]
+        );
+    }
+
+    #[test]
+    fn footers_flag_inline_formatting() {
+        assert_eq!(constructs("Sent by **bot**", EmbedContext::Footer), vec![MarkdownConstruct::InlineFormatting]);
+        assert_eq!(constructs("`v1.2.3`", EmbedContext::AuthorName), vec![MarkdownConstruct::InlineFormatting]);
+        assert!(constructs("Plain footer text", EmbedContext::Footer).is_empty());
+        assert!(constructs("Sent by **bot**", EmbedContext::Title).is_empty());
+    }
+}
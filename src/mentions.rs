@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+
+use crate::Webhook;
+
+/// Which mentions in the content actually ping anyone.
+///
+/// Without it, Discord pings every user, role and `@everyone` mentioned, which is rarely what
+/// you want for text you didn't write.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct AllowedMentions {
+    parse: Vec<MentionType>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    users: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    roles: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    replied_user: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MentionType {
+    Users,
+    Roles,
+    /// `@everyone` and `@here`.
+    Everyone,
+}
+
+impl AllowedMentions {
+    /// Pings nobody.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Pings every mentioned user and role, and `@everyone`, as if unset.
+    pub fn all() -> Self {
+        Self::none().parse(MentionType::Users).parse(MentionType::Roles).parse(MentionType::Everyone)
+    }
+
+    /// Pings every mention of the given types, e.g. `AllowedMentions::parsing(&[MentionType::Users])`
+    /// to ping users but not roles or `@everyone`.
+    pub fn parsing(mentions: &[MentionType]) -> Self {
+        mentions.iter().fold(Self::none(), |allowed, mention| allowed.parse(*mention))
+    }
+
+    /// Pings every mention of the given type.
+    pub fn parse(mut self, mention: MentionType) -> Self {
+        if !self.parse.contains(&mention) {
+            self.parse.push(mention);
+        }
+        self
+    }
+
+    /// Pings only these users. Discord rejects a user list alongside [`MentionType::Users`], so
+    /// this replaces it.
+    pub fn allow_users<I: IntoIterator<Item = u64>>(mut self, user_ids: I) -> Self {
+        self.parse.retain(|mention| *mention != MentionType::Users);
+        self.users.extend(user_ids.into_iter().map(|id| id.to_string()));
+        self
+    }
+
+    /// Pings only these roles, replacing [`MentionType::Roles`] like [`AllowedMentions::allow_users`].
+    pub fn allow_roles<I: IntoIterator<Item = u64>>(mut self, role_ids: I) -> Self {
+        self.parse.retain(|mention| *mention != MentionType::Roles);
+        self.roles.extend(role_ids.into_iter().map(|id| id.to_string()));
+        self
+    }
+
+    /// Whether the author of the message being replied to is pinged.
+    pub fn replied_user(mut self, replied_user: bool) -> Self {
+        self.replied_user = replied_user;
+        self
+    }
+}
+
+impl Webhook {
+    pub fn set_allowed_mentions(mut self, allowed_mentions: AllowedMentions) -> Self {
+        self.allowed_mentions = Some(allowed_mentions);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{AllowedMentions, MentionType};
+    use crate::{assert_payload_matches, Webhook};
+
+    #[test]
+    fn serializes_allowed_mentions() {
+        assert_eq!(serde_json::to_value(AllowedMentions::none()).unwrap(), json!({"parse": []}));
+        assert_eq!(
+            serde_json::to_value(AllowedMentions::parsing(&[MentionType::Users, MentionType::Users])).unwrap(),
+            json!({"parse": ["users"]})
+        );
+        assert_eq!(
+            serde_json::to_value(AllowedMentions::all().allow_users([80351110224678912])).unwrap(),
+            json!({"parse": ["roles", "everyone"], "users": ["80351110224678912"]})
+        );
+        assert_eq!(
+            serde_json::to_value(AllowedMentions::none().replied_user(true)).unwrap(),
+            json!({"parse": [], "replied_user": true})
+        );
+
+        let webhook = Webhook::new("https://discord.com/api/webhooks/1/token").set_content("@everyone hi");
+        assert!(webhook.to_value().unwrap().get("allowed_mentions").is_none());
+        let webhook = webhook.set_allowed_mentions(AllowedMentions::none().parse(MentionType::Roles).allow_roles([1, 2]));
+        assert_payload_matches!(webhook, json!({"allowed_mentions": {"parse": [], "roles": ["1", "2"]}}));
+    }
+}
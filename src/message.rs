@@ -0,0 +1,58 @@
+use serde::Deserialize;
+
+use crate::Embed;
+
+/// A message Discord created or edited, read from the response body.
+///
+/// Only the fields the crate needs are kept; Discord's other keys are ignored. Services that
+/// answer with an empty body, such as a `204 No Content`, give the default message, whose `id` is
+/// empty.
+#[derive(Debug, Clone, Default, Deserialize, Eq, PartialEq)]
+pub struct WebhookMessage {
+    pub id: String,
+    pub channel_id: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub embeds: Vec<Embed>,
+    /// ISO 8601 creation time.
+    pub timestamp: String,
+    /// ISO 8601 time of the last edit, if the message has been edited.
+    #[serde(default)]
+    pub edited_timestamp: Option<String>,
+}
+
+/// A Discord message body for mocked responses.
+#[cfg(test)]
+pub(crate) const MESSAGE_JSON: &str = r#"{
+    "id": "1100000000000000001",
+    "type": 0,
+    "channel_id": "1000000000000000001",
+    "content": "Content Text",
+    "embeds": [],
+    "timestamp": "2024-06-01T12:00:00.000000+00:00",
+    "edited_timestamp": null,
+    "webhook_id": "1"
+}"#;
+
+#[cfg(test)]
+mod tests {
+    use super::{WebhookMessage, MESSAGE_JSON};
+
+    #[test]
+    fn ignores_unknown_fields() {
+        let message: WebhookMessage = serde_json::from_str(MESSAGE_JSON).unwrap();
+        assert_eq!(message.id, "1100000000000000001");
+        assert_eq!(message.channel_id, "1000000000000000001");
+        assert_eq!(message.content, "Content Text");
+        assert_eq!(message.timestamp, "2024-06-01T12:00:00.000000+00:00");
+        assert_eq!(message.edited_timestamp, None);
+    }
+
+    #[test]
+    fn reads_edited_timestamp() {
+        let json = MESSAGE_JSON.replace(r#""edited_timestamp": null"#, r#""edited_timestamp": "2024-06-01T12:05:00.000000+00:00""#);
+        let message: WebhookMessage = serde_json::from_str(&json).unwrap();
+        assert_eq!(message.edited_timestamp.as_deref(), Some("2024-06-01T12:05:00.000000+00:00"));
+    }
+}
@@ -0,0 +1,108 @@
+use reqwest::header::CONTENT_TYPE;
+use reqwest_middleware::ClientWithMiddleware;
+
+use crate::attachment::Body;
+use crate::sender::read_response;
+use crate::{Webhook, WebhookError, WebhookMessage};
+
+impl Webhook {
+    /// Sends the webhook through a `reqwest-middleware` client, so the request passes through
+    /// its middleware stack (retries, tracing, caching...), returning the message Discord created.
+    ///
+    /// The payload is validated and serialized as [`Webhook::send`] does, and a 429 is
+    /// [`WebhookError::RateLimited`]. Retrying it is left to the middleware, as are gzip,
+    /// [`RateLimitBuckets`](crate::RateLimitBuckets) and the
+    /// [`CircuitBreaker`](crate::CircuitBreaker), which belong to an [`HttpSender`](crate::HttpSender).
+    pub async fn send_with_middleware(&self, client: &ClientWithMiddleware) -> Result<WebhookMessage, WebhookError> {
+        let webhook = self.prepared();
+        let headers = webhook.header_map()?;
+        let body = Body::webhook(&webhook)?;
+
+        let resp = client
+            .post(webhook.url()?)
+            .query(&webhook.query_params())
+            .headers(headers)
+            .header(CONTENT_TYPE, body.content_type)
+            .body(body.bytes)
+            .send()
+            .await?;
+
+        read_response(resp).await?.message()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use reqwest_middleware::{ClientBuilder, Middleware, Next};
+    use wiremock::matchers::{header, method, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::{Webhook, WebhookError};
+
+    struct Tag(Arc<AtomicUsize>);
+
+    #[async_trait::async_trait]
+    impl Middleware for Tag {
+        async fn handle(
+            &self,
+            mut req: reqwest::Request,
+            extensions: &mut http::Extensions,
+            next: Next<'_>,
+        ) -> reqwest_middleware::Result<reqwest::Response> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            req.headers_mut().insert("x-middleware", "tagged".parse().unwrap());
+            next.run(req, extensions).await
+        }
+    }
+
+    #[tokio::test]
+    async fn sends_through_middleware() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(query_param("wait", "true"))
+            .and(header("x-middleware", "tagged"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let client = ClientBuilder::new(reqwest::Client::new())
+            .with(Tag(Arc::clone(&calls)))
+            .build();
+
+        let message = Webhook::new(format!("{}/api/webhooks/1/token", server.uri()))
+            .set_content("Content Text")
+            .send_with_middleware(&client)
+            .await
+            .unwrap();
+        assert_eq!(message.id, "1100000000000000001");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn rate_limits_are_typed() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).set_body_json(serde_json::json!({"message": "You are being rate limited.", "retry_after": 1.5})))
+            .mount(&server)
+            .await;
+
+        let client = ClientBuilder::new(reqwest::Client::new()).build();
+        let err = Webhook::new(format!("{}/api/webhooks/1/token", server.uri()))
+            .set_content("Content Text")
+            .send_with_middleware(&client)
+            .await
+            .unwrap_err();
+        match err {
+            WebhookError::RateLimited { attempts, retry_after, body, .. } => {
+                assert_eq!((attempts, retry_after), (1, std::time::Duration::from_millis(1500)));
+                assert!(body.contains("rate limited"));
+            },
+            err => panic!("{:?}", err),
+        }
+    }
+}
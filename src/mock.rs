@@ -0,0 +1,85 @@
+use std::sync::Mutex;
+
+use crate::{Webhook, WebhookError, WebhookMessage, WebhookSender};
+
+/// A [`WebhookSender`] that records webhooks instead of sending them, for use in tests.
+///
+/// Sent messages are given ids counting up from 1, and land in the webhook's thread or else
+/// channel `1000000000000000001`.
+#[derive(Debug, Default)]
+pub struct MockSender {
+    sent: Mutex<Vec<Webhook>>,
+    edits: Mutex<Vec<(String, Webhook)>>,
+    deletes: Mutex<Vec<String>>,
+    failure: Mutex<Option<(u16, String)>>,
+}
+
+impl MockSender {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a sender that fails every send with the given status and body.
+    pub fn failing<S: AsRef<str>>(status: u16, body: S) -> Self {
+        let sender = Self::new();
+        sender.set_failure(Some((status, body)));
+        sender
+    }
+
+    /// Sets the failure returned by subsequent sends, or `None` to succeed.
+    pub fn set_failure<S: AsRef<str>>(&self, failure: Option<(u16, S)>) {
+        let failure = failure.map(|(status, body)| (status, body.as_ref().to_string()));
+        *self.failure.lock().unwrap() = failure;
+    }
+
+    /// Every webhook this sender has been asked to send, in order.
+    pub fn sent_webhooks(&self) -> Vec<Webhook> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    /// Every edit this sender has been asked to make, as message id and new payload, in order.
+    pub fn edits(&self) -> Vec<(String, Webhook)> {
+        self.edits.lock().unwrap().clone()
+    }
+
+    /// The ids of every message this sender has been asked to delete, in order.
+    pub fn deletes(&self) -> Vec<String> {
+        self.deletes.lock().unwrap().clone()
+    }
+
+    fn respond(&self, id: String, webhook: &Webhook, edited: bool) -> Result<WebhookMessage, WebhookError> {
+        if let Some((status, body)) = self.failure.lock().unwrap().as_ref() {
+            return Err(WebhookError::api(*status, body.clone()));
+        }
+        let now = chrono::Utc::now().to_rfc3339();
+        Ok(WebhookMessage {
+            id,
+            channel_id: webhook.thread_id.map_or_else(|| String::from("1000000000000000001"), |id| id.to_string()),
+            content: webhook.content.clone().unwrap_or_default(),
+            embeds: webhook.embeds.clone(),
+            timestamp: now.clone(),
+            edited_timestamp: edited.then_some(now),
+        })
+    }
+}
+
+impl WebhookSender for MockSender {
+    async fn send(&self, webhook: &Webhook) -> Result<WebhookMessage, WebhookError> {
+        let id = {
+            let mut sent = self.sent.lock().unwrap();
+            sent.push(webhook.clone());
+            sent.len().to_string()
+        };
+        self.respond(id, webhook, false)
+    }
+
+    async fn edit(&self, webhook: &Webhook, message_id: &str) -> Result<WebhookMessage, WebhookError> {
+        self.edits.lock().unwrap().push((message_id.to_string(), webhook.clone()));
+        self.respond(message_id.to_string(), webhook, true)
+    }
+
+    async fn delete(&self, webhook: &Webhook, message_id: &str) -> Result<(), WebhookError> {
+        self.deletes.lock().unwrap().push(message_id.to_string());
+        self.respond(message_id.to_string(), webhook, false).map(drop)
+    }
+}
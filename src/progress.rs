@@ -0,0 +1,279 @@
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, MissedTickBehavior};
+
+use crate::{HttpSender, Webhook, WebhookError, WebhookMessage, WebhookSender};
+
+/// Renders a text progress bar `width` blocks wide, e.g. `▓▓▓▓░░░░░░ 40%`.
+pub fn progress_bar(done: u64, total: u64, width: usize) -> String {
+    let ratio = match total {
+        0 => 1.0,
+        total => done.min(total) as f64 / total as f64,
+    };
+    let filled = (ratio * width as f64).round() as usize;
+    format!("{}{} {}%", "▓".repeat(filled), "░".repeat(width - filled), (ratio * 100.0).floor())
+}
+
+const BAR_WIDTH: usize = 20;
+
+/// Keeps a single message up to date with the progress of a long job.
+///
+/// [`update`](Self::update) only records the latest state; it's edited into the message at most
+/// once per interval, so rapid updates can't run into Discord's rate limits. Finish with
+/// [`finish`](Self::finish) or [`fail`](Self::fail). A reporter dropped without finishing makes
+/// a best-effort edit marking the message as stopped.
+///
+/// The webhook has to return the created message, which Discord does, so it can be edited.
+pub struct ProgressReporter<S: WebhookSender + Send + Sync + 'static = HttpSender> {
+    shared: Arc<Shared<S>>,
+    flusher: JoinHandle<()>,
+    finished: bool,
+}
+
+struct Shared<S> {
+    sender: S,
+    webhook: Webhook,
+    message_id: String,
+    started_at: Instant,
+    progress: Mutex<Progress>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Progress {
+    done: u64,
+    total: u64,
+    status: String,
+    /// Whether there's an update the message doesn't show yet.
+    dirty: bool,
+}
+
+impl ProgressReporter {
+    /// Sends the initial message for `webhook`, editing it at most once per `interval`.
+    pub async fn start(webhook: Webhook, interval: Duration) -> Result<Self, WebhookError> {
+        Self::start_with(HttpSender::new(), webhook, interval).await
+    }
+}
+
+impl<S: WebhookSender + Send + Sync + 'static> ProgressReporter<S> {
+    pub async fn start_with(sender: S, webhook: Webhook, interval: Duration) -> Result<Self, WebhookError> {
+        let message = sender.send(&webhook.clone().set_content("⏳ Starting…").prepared()).await?;
+        if message.id.is_empty() {
+            return Err(WebhookError::InvalidMessageId(message.id));
+        }
+        let shared = Arc::new(Shared {
+            sender,
+            webhook,
+            message_id: message.id,
+            started_at: Instant::now(),
+            progress: Mutex::new(Progress::default()),
+        });
+
+        let flusher = tokio::spawn({
+            let shared = Arc::clone(&shared);
+            async move {
+                let mut ticks = tokio::time::interval_at(Instant::now() + interval, interval);
+                ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                loop {
+                    ticks.tick().await;
+                    // held through the edit so `finish` can't overtake it
+                    let mut progress = shared.progress.lock().await;
+                    if progress.dirty {
+                        progress.dirty = false;
+                        let content = progress.render(shared.started_at.elapsed());
+                        let _ = shared.edit(content).await;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            shared,
+            flusher,
+            finished: false,
+        })
+    }
+
+    pub fn sender(&self) -> &S {
+        &self.shared.sender
+    }
+
+    /// Records the job's progress, to be shown at the next edit.
+    pub async fn update<T: AsRef<str>>(&self, done: u64, total: u64, status: T) {
+        let mut progress = self.shared.progress.lock().await;
+        *progress = Progress {
+            done,
+            total,
+            status: status.as_ref().to_string(),
+            dirty: true,
+        };
+    }
+
+    /// Marks the job as complete with a summary.
+    pub async fn finish<T: AsRef<str>>(mut self, summary: T) -> Result<WebhookMessage, WebhookError> {
+        let content = format!(
+            "✅ {}\n`{}` done in {}",
+            summary.as_ref(),
+            progress_bar(1, 1, BAR_WIDTH),
+            format_duration(self.shared.started_at.elapsed())
+        );
+        self.finalize(content).await
+    }
+
+    /// Marks the job as failed, keeping the progress it reached.
+    pub async fn fail<E: Display>(mut self, error: E) -> Result<WebhookMessage, WebhookError> {
+        let progress = self.shared.progress.lock().await.clone();
+        let content = format!(
+            "❌ {}\n`{}` {}/{}",
+            error,
+            progress_bar(progress.done, progress.total, BAR_WIDTH),
+            progress.done,
+            progress.total
+        );
+        self.finalize(content).await
+    }
+
+    async fn finalize(&mut self, content: String) -> Result<WebhookMessage, WebhookError> {
+        self.finished = true;
+        let _progress = self.shared.progress.lock().await;
+        self.flusher.abort();
+        self.shared.edit(content).await
+    }
+}
+
+impl<S: WebhookSender> Shared<S> {
+    async fn edit(&self, content: String) -> Result<WebhookMessage, WebhookError> {
+        let webhook = self.webhook.clone().set_content(content);
+        self.sender.edit(&webhook.prepared(), &self.message_id).await
+    }
+}
+
+impl<S: WebhookSender + Send + Sync + 'static> Drop for ProgressReporter<S> {
+    fn drop(&mut self) {
+        self.flusher.abort();
+        if self.finished {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let shared = Arc::clone(&self.shared);
+        runtime.spawn(async move {
+            let progress = shared.progress.lock().await.clone();
+            let content = format!(
+                "⚠️ {}\n`{}` {}/{} · stopped reporting",
+                progress.status,
+                progress_bar(progress.done, progress.total, BAR_WIDTH),
+                progress.done,
+                progress.total
+            );
+            let _ = shared.edit(content).await;
+        });
+    }
+}
+
+impl Progress {
+    fn render(&self, elapsed: Duration) -> String {
+        let mut content = format!(
+            "⏳ {}\n`{}` {}/{}",
+            self.status,
+            progress_bar(self.done, self.total, BAR_WIDTH),
+            self.done,
+            self.total
+        );
+        if self.done > 0 && self.done < self.total {
+            let remaining = elapsed.mul_f64((self.total - self.done) as f64 / self.done as f64);
+            content.push_str(&format!(" · ETA {}", format_duration(remaining)));
+        }
+        content
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::{progress_bar, ProgressReporter};
+    use crate::{MockSender, Webhook};
+
+    const INTERVAL: Duration = Duration::from_secs(5);
+
+    async fn reporter() -> ProgressReporter<Arc<MockSender>> {
+        let webhook = Webhook::new("https://discord.com/api/webhooks/1/token");
+        ProgressReporter::start_with(Arc::new(MockSender::new()), webhook, INTERVAL).await.unwrap()
+    }
+
+    fn edit_contents(sender: &MockSender) -> Vec<String> {
+        sender.edits().into_iter().map(|(_, webhook)| webhook.content.unwrap()).collect()
+    }
+
+    #[test]
+    fn renders_bars() {
+        assert_eq!(progress_bar(0, 100, 10), "░░░░░░░░░░ 0%");
+        assert_eq!(progress_bar(42, 100, 10), "▓▓▓▓░░░░░░ 42%");
+        assert_eq!(progress_bar(7, 5, 4), "▓▓▓▓ 100%");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn coalesces_updates_into_one_edit_per_interval() {
+        let reporter = reporter().await;
+        let sender = Arc::clone(reporter.sender());
+        assert_eq!(sender.sent_webhooks().len(), 1);
+
+        tokio::time::sleep(Duration::from_secs(12)).await;
+        for done in 1..=25 {
+            reporter.update(done, 100, "building images").await;
+        }
+        assert!(sender.edits().is_empty());
+
+        tokio::time::sleep(INTERVAL).await;
+        assert_eq!(
+            edit_contents(&sender),
+            ["⏳ building images\n`▓▓▓▓▓░░░░░░░░░░░░░░░ 25%` 25/100 · ETA 45s"]
+        );
+
+        // nothing new to show, so no edit
+        tokio::time::sleep(INTERVAL * 3).await;
+        assert_eq!(sender.edits().len(), 1);
+        assert!(sender.edits().iter().all(|(id, _)| id == "1"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn final_states() {
+        let reporter = reporter().await;
+        let sender = Arc::clone(reporter.sender());
+        tokio::time::sleep(Duration::from_secs(76)).await;
+        reporter.update(99, 100, "pushing").await;
+        reporter.finish("Deployed v1.2.3").await.unwrap();
+        assert_eq!(edit_contents(&sender), ["✅ Deployed v1.2.3\n`▓▓▓▓▓▓▓▓▓▓▓▓▓▓▓▓▓▓▓▓ 100%` done in 1m 16s"]);
+
+        let reporter = ProgressReporter::start_with(Arc::clone(&sender), Webhook::new("https://discord.com/api/webhooks/1/token"), INTERVAL)
+            .await
+            .unwrap();
+        reporter.update(3, 4, "testing").await;
+        reporter.fail("tests failed").await.unwrap();
+        assert_eq!(edit_contents(&sender)[1], "❌ tests failed\n`▓▓▓▓▓▓▓▓▓▓▓▓▓▓▓░░░░░ 75%` 3/4");
+
+        let reporter = ProgressReporter::start_with(Arc::clone(&sender), Webhook::new("https://discord.com/api/webhooks/1/token"), INTERVAL)
+            .await
+            .unwrap();
+        reporter.update(1, 2, "halfway").await;
+        drop(reporter);
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        assert_eq!(edit_contents(&sender)[2], "⚠️ halfway\n`▓▓▓▓▓▓▓▓▓▓░░░░░░░░░░ 50%` 1/2 · stopped reporting");
+        assert_eq!(sender.edits().len(), 3);
+    }
+}
@@ -0,0 +1,492 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::reqwest::header::HeaderMap;
+use crate::{HttpSender, Webhook, WebhookError, WebhookMessage};
+
+/// The `X-RateLimit-*` headers Discord sends with every response.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct RateLimitHeaders {
+    pub limit: Option<u32>,
+    pub remaining: Option<u32>,
+    pub reset_after: Option<Duration>,
+    pub bucket: Option<String>,
+}
+
+impl RateLimitHeaders {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        Self {
+            limit: header("x-ratelimit-limit").and_then(|v| v.parse().ok()),
+            remaining: header("x-ratelimit-remaining").and_then(|v| v.parse().ok()),
+            reset_after: header("x-ratelimit-reset-after")
+                .and_then(|v| v.parse::<f64>().ok())
+                .and_then(|secs| Duration::try_from_secs_f64(secs).ok()),
+            bucket: header("x-ratelimit-bucket").map(str::to_string),
+        }
+    }
+}
+
+/// Discord's rate limit buckets, tracked per route from the `X-RateLimit-*` headers of each
+/// response, so a send waits for an exhausted bucket to reset instead of getting a 429.
+///
+/// Routes that Discord reports in the same `X-RateLimit-Bucket` share their state. Clones share
+/// the same buckets. Every response is recorded, including 429s and other errors.
+///
+/// Buckets are opt-in: [`Webhook::send`], [`HttpSender::new`] and the queue's default sender
+/// don't track them. Attach them with [`HttpSender::rate_limit_buckets`], and send through the
+/// sender with [`Webhook::send_with`] or hand it to the queue's `with_sender`.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimitBuckets {
+    state: Arc<std::sync::Mutex<Buckets>>,
+}
+
+#[derive(Default)]
+struct Buckets {
+    /// The bucket id Discord reported for each route.
+    routes: HashMap<String, String>,
+    /// Keyed by bucket id, or by route until Discord names the bucket.
+    buckets: HashMap<String, BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    remaining: u32,
+    reset_at: Instant,
+}
+
+// routes hold the webhook token, so only their number is shown
+impl fmt::Debug for Buckets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Buckets").field("routes", &self.routes.len()).field("buckets", &self.buckets.len()).finish()
+    }
+}
+
+impl Buckets {
+    fn key<'a>(&'a self, route: &'a str) -> &'a str {
+        self.routes.get(route).map_or(route, String::as_str)
+    }
+}
+
+impl RateLimitBuckets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How long a request to `url` with `method` would wait for its bucket to reset.
+    pub fn wait_time(&self, method: &str, url: &str) -> Option<Duration> {
+        let state = self.state.lock().unwrap();
+        let bucket = state.buckets.get(state.key(&route(method, url)))?;
+        let now = Instant::now();
+        (bucket.remaining == 0 && bucket.reset_at > now).then(|| bucket.reset_at - now)
+    }
+
+    /// Waits until the route's bucket has a request left, then takes it.
+    pub(crate) async fn acquire(&self, method: &str, url: &str) {
+        let route = route(method, url);
+        loop {
+            let ready_at = {
+                let mut state = self.state.lock().unwrap();
+                let key = state.key(&route).to_string();
+                let now = Instant::now();
+                match state.buckets.get_mut(&key) {
+                    Some(bucket) if bucket.reset_at <= now => {
+                        state.buckets.remove(&key);
+                        return;
+                    },
+                    Some(bucket) if bucket.remaining == 0 => bucket.reset_at,
+                    Some(bucket) => {
+                        bucket.remaining -= 1;
+                        return;
+                    },
+                    None => return,
+                }
+            };
+            tokio::time::sleep_until(ready_at).await;
+        }
+    }
+
+    /// Records the bucket a response reported for the route.
+    pub(crate) fn observe(&self, method: &str, url: &str, headers: &RateLimitHeaders) {
+        let (Some(remaining), Some(reset_after)) = (headers.remaining, headers.reset_after) else {
+            return;
+        };
+        let route = route(method, url);
+        let mut state = self.state.lock().unwrap();
+        let key = match &headers.bucket {
+            Some(bucket) => {
+                state.buckets.remove(&route);
+                state.routes.insert(route, bucket.clone());
+                bucket.clone()
+            },
+            None => route,
+        };
+        let reset_at = Instant::now() + reset_after;
+        state.buckets.insert(key, BucketState { remaining, reset_at });
+    }
+}
+
+/// Edits and deletes of different messages share a route, as they share Discord's bucket.
+fn route(method: &str, url: &str) -> String {
+    match url.split_once("/messages/") {
+        Some((webhook, _)) => format!("{} {}/messages/:id", method, webhook),
+        None => format!("{} {}", method, url),
+    }
+}
+
+/// How many requests are allowed per interval, all of which can be sent at once.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RateLimitConfig {
+    pub per_interval: u32,
+    pub interval: Duration,
+}
+
+impl Default for RateLimitConfig {
+    /// Discord's per-webhook limit of 5 requests per 2 seconds.
+    fn default() -> Self {
+        Self {
+            per_interval: 5,
+            interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// A token bucket for pacing ad-hoc sends without adopting the queue.
+///
+/// Clones share the same bucket, so one limiter can pace sends across tasks.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    per_second: f64,
+    capacity: f64,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    /// Set when the server reports the bucket exhausted; nothing is allowed before it.
+    paused_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    /// Allows `per_second` acquisitions per second, with no bursting.
+    ///
+    /// Panics if `per_second` isn't a positive, finite number.
+    pub fn new(per_second: f64) -> Self {
+        Self::with_burst(per_second, 1)
+    }
+
+    /// Allows `per_second` acquisitions per second, with up to `burst` available at once.
+    ///
+    /// Panics if `per_second` isn't a positive, finite number.
+    pub fn with_burst(per_second: f64, burst: u32) -> Self {
+        assert!(per_second > 0.0 && per_second.is_finite(), "rate limit must be positive and finite, got {}", per_second);
+        let capacity = f64::from(burst.max(1));
+        Self {
+            per_second,
+            capacity,
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: capacity,
+                refilled_at: Instant::now(),
+                paused_until: None,
+            })),
+        }
+    }
+
+    /// Paces to Discord's per-webhook limit of 5 requests per 2 seconds.
+    pub fn per_webhook() -> Self {
+        Self::with_config(RateLimitConfig::default())
+    }
+
+    /// Allows `config.per_interval` acquisitions per interval, all at once if need be.
+    ///
+    /// Panics if `config.interval` is zero, which would allow unlimited sends.
+    pub fn with_config(config: RateLimitConfig) -> Self {
+        assert!(!config.interval.is_zero(), "rate limit interval must not be zero");
+        let burst = config.per_interval.max(1);
+        Self::with_burst(f64::from(burst) / config.interval.as_secs_f64(), burst)
+    }
+
+    /// Waits until a send is allowed, then consumes it.
+    pub async fn acquire(&self) {
+        while let Err(ready_at) = self.try_acquire().await {
+            tokio::time::sleep_until(ready_at).await;
+        }
+    }
+
+    /// Consumes a send if one is allowed now, otherwise returns when to try again.
+    pub(crate) async fn try_acquire(&self) -> Result<(), Instant> {
+        let mut bucket = self.bucket.lock().await;
+        let now = Instant::now();
+        match bucket.paused_until {
+            Some(until) if until > now => return Err(until),
+            Some(_) => bucket.paused_until = None,
+            None => {}
+        }
+        let elapsed = now.saturating_duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.per_second).min(self.capacity);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(now + Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_second))
+    }
+
+    /// Sends the webhook without waiting, then observes the rate limit Discord reports.
+    pub(crate) async fn deliver(&self, sender: &HttpSender, webhook: &Webhook) -> Result<WebhookMessage, WebhookError> {
+        let webhook = webhook.prepared();
+        let headers = webhook.header_map()?;
+        let delivery = sender
+            .for_webhook(&webhook)
+            .execute_webhook(crate::reqwest::Method::POST, webhook.url()?, &webhook.query_params(), &headers, &webhook)
+            .await?;
+        self.observe(&delivery.rate_limit).await;
+        delivery.message()
+    }
+
+    /// Feeds the limiter with a response's rate limit headers, pausing it until the reset when
+    /// the server says the bucket is exhausted.
+    pub async fn observe(&self, headers: &RateLimitHeaders) {
+        if let (Some(0), Some(reset_after)) = (headers.remaining, headers.reset_after) {
+            self.pause_for(reset_after).await;
+        }
+    }
+
+    /// Allows nothing through for `duration`, after which the bucket is full again.
+    pub async fn pause_for(&self, duration: Duration) {
+        let mut bucket = self.bucket.lock().await;
+        let until = bucket.paused_until.into_iter().fold(Instant::now() + duration, Instant::max);
+        bucket.tokens = self.capacity;
+        bucket.refilled_at = until;
+        bucket.paused_until = Some(until);
+    }
+}
+
+/// A webhook that paces its own sends to stay within Discord's per-webhook rate limit
+/// (5 requests per 2 seconds), adjusting to the limits Discord reports.
+///
+/// Clones share the same pacing state, so concurrent tasks sending through clones coordinate.
+#[derive(Debug, Clone)]
+pub struct RateLimitedWebhook {
+    webhook: Webhook,
+    limiter: RateLimiter,
+    sender: HttpSender,
+}
+
+impl RateLimitedWebhook {
+    pub fn new(webhook: Webhook) -> Self {
+        Self::with_limiter(webhook, RateLimiter::per_webhook())
+    }
+
+    /// Uses an existing limiter, e.g. one shared with other payloads for the same webhook url.
+    pub fn with_limiter(webhook: Webhook, limiter: RateLimiter) -> Self {
+        Self {
+            webhook,
+            limiter,
+            sender: HttpSender::new(),
+        }
+    }
+
+    pub fn webhook(&self) -> &Webhook {
+        &self.webhook
+    }
+
+    pub fn limiter(&self) -> &RateLimiter {
+        &self.limiter
+    }
+
+    /// Waits until the rate limit allows a request, then sends the webhook.
+    pub async fn send(&self) -> Result<WebhookMessage, WebhookError> {
+        self.limiter.acquire().await;
+        self.limiter.deliver(&self.sender, &self.webhook).await
+    }
+}
+
+impl Webhook {
+    /// Wraps the webhook so its sends are paced to Discord's rate limit.
+    pub fn rate_limited(self) -> RateLimitedWebhook {
+        RateLimitedWebhook::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    use super::{RateLimitBuckets, RateLimitConfig, RateLimitHeaders, RateLimiter};
+    use crate::{HttpSender, Webhook};
+
+    #[tokio::test(start_paused = true)]
+    async fn paces_acquisitions() {
+        let limiter = RateLimiter::new(2.0);
+        let start = Instant::now();
+        for _ in 0..5 {
+            limiter.acquire().await;
+        }
+        // the first is immediate, the remaining four are spaced 500ms apart
+        assert_eq!(start.elapsed(), Duration::from_millis(2000));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn allows_bursts() {
+        let limiter = RateLimiter::with_burst(1.0, 3);
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire().await;
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        limiter.clone().acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn rejects_rates_that_never_refill() {
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(std::panic::catch_unwind(|| RateLimiter::new(rate)).is_err(), "{}", rate);
+        }
+        let unlimited = RateLimitConfig { per_interval: 5, interval: Duration::ZERO };
+        assert!(std::panic::catch_unwind(|| RateLimiter::with_config(unlimited)).is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn observing_an_exhausted_bucket_pauses() {
+        let limiter = RateLimiter::with_burst(10.0, 10);
+        let start = Instant::now();
+        limiter.observe(&RateLimitHeaders {
+            remaining: Some(0),
+            reset_after: Some(Duration::from_millis(1500)),
+            ..RateLimitHeaders::default()
+        }).await;
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_millis(1500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn buckets_wait_for_exhausted_routes() {
+        let buckets = RateLimitBuckets::new();
+        let url = "https://discord.com/api/webhooks/1/token";
+        let exhausted = |bucket: Option<&str>| RateLimitHeaders {
+            remaining: Some(0),
+            reset_after: Some(Duration::from_secs(1)),
+            bucket: bucket.map(str::to_string),
+            ..RateLimitHeaders::default()
+        };
+        buckets.observe("POST", url, &exhausted(None));
+        assert_eq!(buckets.wait_time("POST", url), Some(Duration::from_secs(1)));
+        assert_eq!(buckets.wait_time("PATCH", &format!("{}/messages/2", url)), None);
+
+        // edits of any message share a route, and routes in one bucket share its state
+        buckets.observe("PATCH", &format!("{}/messages/2", url), &exhausted(Some("abc")));
+        buckets.observe("DELETE", &format!("{}/messages/3", url), &exhausted(Some("abc")));
+        assert_eq!(buckets.wait_time("PATCH", &format!("{}/messages/9", url)), Some(Duration::from_secs(1)));
+
+        let start = Instant::now();
+        buckets.acquire("DELETE", &format!("{}/messages/4", url)).await;
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+        assert_eq!(buckets.wait_time("POST", url), None);
+    }
+
+    #[tokio::test]
+    async fn senders_wait_for_the_reported_reset() {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(crate::message::MESSAGE_JSON, "application/json")
+                    .insert_header("x-ratelimit-remaining", "0")
+                    .insert_header("x-ratelimit-reset-after", "0.3"),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let sender = HttpSender::new().rate_limit_buckets(RateLimitBuckets::new());
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri())).set_content("Content Text");
+        let start = Instant::now();
+        webhook.send_with(&sender).await.unwrap();
+        webhook.send_with(&sender).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(300), "took {:?}", start.elapsed());
+    }
+
+    #[tokio::test]
+    async fn error_responses_are_recorded() {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(
+                ResponseTemplate::new(404)
+                    .set_body_string(r#"{"message": "Unknown Webhook", "code": 10015}"#)
+                    .insert_header("x-ratelimit-remaining", "0")
+                    .insert_header("x-ratelimit-reset-after", "5"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let buckets = RateLimitBuckets::new();
+        let sender = HttpSender::new().rate_limit_buckets(buckets.clone());
+        let url = format!("{}/api/webhooks/1/token", server.uri());
+        assert!(Webhook::new(&url).set_content("Content Text").send_with(&sender).await.is_err());
+        assert!(buckets.wait_time("POST", &url).is_some());
+    }
+
+    /// Answers like Discord with a 5 requests per 2 seconds bucket.
+    struct Bucket(Mutex<Vec<std::time::Instant>>);
+
+    impl Respond for Bucket {
+        fn respond(&self, _: &Request) -> ResponseTemplate {
+            let window = Duration::from_secs(2);
+            let now = std::time::Instant::now();
+            let mut sent = self.0.lock().unwrap();
+            sent.retain(|at| now.duration_since(*at) < window);
+            if sent.len() >= 5 {
+                return ResponseTemplate::new(429).insert_header("x-ratelimit-reset-after", "1");
+            }
+            sent.push(now);
+            let reset_after = window - now.duration_since(sent[0]);
+            ResponseTemplate::new(200)
+                .set_body_raw(crate::message::MESSAGE_JSON, "application/json")
+                .insert_header("x-ratelimit-limit", "5")
+                .insert_header("x-ratelimit-remaining", (5 - sent.len()).to_string().as_str())
+                .insert_header("x-ratelimit-reset-after", format!("{:.3}", reset_after.as_secs_f64() + 0.001).as_str())
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_sends_stay_within_the_bucket() {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(Bucket(Mutex::new(Vec::new())))
+            .mount(&server)
+            .await;
+
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri()))
+            .set_content("Content Text")
+            .rate_limited();
+        let start = Instant::now();
+        let tasks = (0..10)
+            .map(|_| {
+                let webhook = webhook.clone();
+                tokio::spawn(async move { webhook.send().await })
+            })
+            .collect::<Vec<_>>();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_secs(2) && elapsed < Duration::from_secs(4), "took {:?}", elapsed);
+        assert_eq!(server.received_requests().await.unwrap().len(), 10);
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use crate::{Webhook, WebhookError};
+
+/// Retries requests that failed for reasons that may pass, waiting twice as long after each
+/// failed attempt.
+///
+/// Connection errors and the retryable statuses (500, 502, 503 and 504 by default) are retried;
+/// rejected payloads and missing webhooks are not. Rate limits are handled separately, see
+/// [`HttpSender::max_retries`](crate::HttpSender::max_retries).
+///
+/// Attach one with [`HttpSender::retry_policy`](crate::HttpSender::retry_policy) or
+/// [`Webhook::set_retry_policy`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+    retryable_statuses: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    /// 3 attempts, waiting about 500ms then 1s.
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+            retryable_statuses: vec![500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Makes up to `max_attempts` requests in total, including the first.
+    pub fn new(max_attempts: u32) -> Self {
+        Self::default().max_attempts(max_attempts)
+    }
+
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// The wait after the first failed attempt, doubled after each one after it.
+    pub fn base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    /// The longest wait between attempts.
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Waits a random time between half and all of each delay, so clients that failed together
+    /// don't retry together. On by default.
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Replaces the response statuses worth retrying.
+    pub fn retryable_statuses<I: IntoIterator<Item = u16>>(mut self, statuses: I) -> Self {
+        self.retryable_statuses = statuses.into_iter().collect();
+        self
+    }
+
+    /// How long to wait after failed attempt number `attempt`, counting from 1.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let delay = self.base_delay.saturating_mul(1 << exponent).min(self.max_delay);
+        if !self.jitter {
+            return delay;
+        }
+        let fraction = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        delay.mul_f64(0.5 + fraction / 2.0)
+    }
+
+    /// Whether another attempt is allowed after `attempt` attempts failed with `error`.
+    pub(crate) fn should_retry(&self, attempt: u32, error: &WebhookError) -> bool {
+        if attempt >= self.max_attempts {
+            return false;
+        }
+        match error {
+            WebhookError::Http(_) => true,
+            WebhookError::Api { status, .. } => self.retryable_statuses.contains(status),
+            _ => false,
+        }
+    }
+}
+
+impl Webhook {
+    /// Overrides the sender's retry policy, see [`HttpSender::retry_policy`](crate::HttpSender::retry_policy).
+    pub fn set_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::RetryPolicy;
+    use crate::{HttpSender, Webhook, WebhookError};
+
+    #[test]
+    fn delays_grow_to_the_cap() {
+        let policy = RetryPolicy::new(10).base_delay(Duration::from_millis(100)).max_delay(Duration::from_secs(1)).jitter(false);
+        let delays: Vec<Duration> = (1..=6).map(|attempt| policy.delay(attempt)).collect();
+        assert_eq!(delays, [100, 200, 400, 800, 1000, 1000].map(Duration::from_millis));
+
+        let jittered = RetryPolicy::default();
+        for _ in 0..100 {
+            let delay = jittered.delay(2);
+            assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_secs(1), "{:?}", delay);
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri())).set_content("Content Text");
+        let policy = RetryPolicy::new(3).base_delay(Duration::from_millis(10));
+        webhook.send_with(&HttpSender::new().retry_policy(policy.clone())).await.unwrap();
+
+        // the webhook's own policy wins, and statuses outside the policy aren't retried
+        server.reset().await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(500)).expect(1).mount(&server).await;
+        let err = webhook
+            .set_retry_policy(policy.retryable_statuses([502]))
+            .send_with(&HttpSender::new().retry_policy(RetryPolicy::new(5)))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, WebhookError::Api { status: 500, .. }));
+    }
+}
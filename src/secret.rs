@@ -0,0 +1,81 @@
+use std::borrow::Borrow;
+use std::fmt;
+
+/// Webhook url storage that keeps the token out of `Debug`/`Display` output and, with the
+/// `zeroize` feature, scrubs the url from memory when dropped.
+#[derive(Clone, Default, Eq, PartialEq, Hash)]
+pub(crate) struct SecretUrl(String);
+
+impl SecretUrl {
+    pub(crate) fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The url with its scheme and host replaced by `origin`, keeping the path and query.
+    pub(crate) fn with_origin(&self, origin: &str) -> Option<SecretUrl> {
+        let (_, rest) = self.0.split_once("://")?;
+        let path = &rest[rest.find(['/', '?'])?..];
+        Some(SecretUrl(format!("{}{}", origin.trim_end_matches('/'), path)))
+    }
+
+    /// The url with its token segment replaced, safe to log.
+    pub(crate) fn redacted(&self) -> String {
+        let url = &self.0;
+        let token_start = url
+            .find("/webhooks/")
+            .map(|i| i + "/webhooks/".len())
+            .and_then(|i| url[i..].find('/').map(|j| i + j + 1));
+        match token_start {
+            Some(start) => {
+                let end = url[start..].find(['/', '?', '#']).map_or(url.len(), |i| start + i);
+                format!("{}[redacted]{}", &url[..start], &url[end..])
+            }
+            None => String::from("[redacted]"),
+        }
+    }
+}
+
+impl From<String> for SecretUrl {
+    fn from(url: String) -> Self {
+        SecretUrl(url)
+    }
+}
+
+impl From<&str> for SecretUrl {
+    fn from(url: &str) -> Self {
+        SecretUrl(url.to_string())
+    }
+}
+
+impl AsRef<str> for SecretUrl {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+// keyed lookups by plain url, e.g. per-url state in a `HashMap<SecretUrl, _>`
+impl Borrow<str> for SecretUrl {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for SecretUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.redacted())
+    }
+}
+
+impl fmt::Display for SecretUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.redacted())
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for SecretUrl {
+    fn drop(&mut self) {
+        use zeroize::Zeroize;
+        self.0.zeroize();
+    }
+}
@@ -0,0 +1,955 @@
+use std::borrow::Cow;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+#[cfg(feature = "gzip")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use crate::reqwest::header::{HeaderMap, CONTENT_TYPE};
+use serde::Serialize;
+
+use crate::attachment::Body;
+#[cfg(feature = "retry")]
+use crate::RetryPolicy;
+use crate::{reqwest, CircuitBreaker, DiscordErrorCode, RateLimitBuckets, RateLimitHeaders, Webhook, WebhookError, WebhookMessage};
+
+/// Delivers a [`Webhook`] payload somewhere.
+///
+/// [`HttpSender`] posts to Discord and is what [`Webhook::send`] uses; other implementations
+/// can be swapped in with [`Webhook::send_with`].
+pub trait WebhookSender {
+    /// Sends the webhook as a new message.
+    fn send(&self, webhook: &Webhook) -> impl Future<Output = Result<WebhookMessage, WebhookError>> + Send;
+
+    /// Replaces a message previously sent through the webhook with its current payload.
+    fn edit(&self, webhook: &Webhook, message_id: &str) -> impl Future<Output = Result<WebhookMessage, WebhookError>> + Send;
+
+    /// Deletes a message previously sent through the webhook.
+    fn delete(&self, webhook: &Webhook, message_id: &str) -> impl Future<Output = Result<(), WebhookError>> + Send;
+}
+
+/// Shares one sender, e.g. between a [`ProgressReporter`](crate::ProgressReporter) and the caller.
+impl<T: WebhookSender + Send + Sync> WebhookSender for Arc<T> {
+    fn send(&self, webhook: &Webhook) -> impl Future<Output = Result<WebhookMessage, WebhookError>> + Send {
+        (**self).send(webhook)
+    }
+
+    fn edit(&self, webhook: &Webhook, message_id: &str) -> impl Future<Output = Result<WebhookMessage, WebhookError>> + Send {
+        (**self).edit(webhook, message_id)
+    }
+
+    fn delete(&self, webhook: &Webhook, message_id: &str) -> impl Future<Output = Result<(), WebhookError>> + Send {
+        (**self).delete(webhook, message_id)
+    }
+}
+
+/// Connection tuning for the HTTP client the crate builds.
+///
+/// Unset options keep the HTTP client's defaults. Discord closes idle connections, so a
+/// `pool_idle_timeout` shorter than Discord's avoids paying for a dead connection after a
+/// quiet period.
+#[derive(Debug, Clone, Default)]
+pub struct ClientConfig {
+    pub pool_idle_timeout: Option<Duration>,
+    pub pool_max_idle_per_host: Option<usize>,
+    pub tcp_keepalive: Option<Duration>,
+    pub http2_keep_alive_interval: Option<Duration>,
+    /// How long to wait for a connection, including DNS resolution.
+    pub connect_timeout: Option<Duration>,
+    /// How long to wait for each read from the connection.
+    pub read_timeout: Option<Duration>,
+    /// The longest a whole request may take, from connecting to reading the response. Overridden
+    /// by [`HttpSender::timeout`].
+    pub timeout: Option<Duration>,
+    /// Hosts resolved to fixed addresses instead of through DNS, see [`ClientConfig::resolve`].
+    pub resolve_overrides: Vec<(String, SocketAddr)>,
+    /// Sends every request through this proxy, such as `http://proxy.internal:3128`. Without one,
+    /// the `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY` environment variables are used.
+    /// `socks5://` proxies need the `socks` feature.
+    pub proxy: Option<String>,
+    /// Hosts reached directly instead of through [`ClientConfig::proxy`], comma separated as in
+    /// `NO_PROXY`, e.g. `localhost,.internal`. Building fails without a `proxy`; set `NO_PROXY`
+    /// to exclude hosts from a proxy taken from the environment.
+    pub no_proxy: Option<String>,
+    /// The source address requests are sent from. Binding an address the host doesn't own
+    /// fails each request with [`WebhookError::Http`].
+    pub local_address: Option<IpAddr>,
+    /// The `User-Agent` header sent with every request, [`DEFAULT_USER_AGENT`] if unset.
+    pub user_agent: Option<String>,
+}
+
+impl ClientConfig {
+    /// Resolves `host` to `addr` without a DNS lookup. The port still comes from the url.
+    pub fn resolve<S: AsRef<str>>(mut self, host: S, addr: SocketAddr) -> Self {
+        self.resolve_overrides.push((host.as_ref().to_string(), addr));
+        self
+    }
+
+    /// Builds a client with this configuration, e.g. for [`HttpSender::with_client`].
+    pub fn build(&self) -> Result<reqwest::Client, WebhookError> {
+        let mut builder = reqwest::Client::builder().user_agent(self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT));
+        if let Some(timeout) = self.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+        if let Some(max) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        if let Some(keepalive) = self.tcp_keepalive {
+            builder = builder.tcp_keepalive(keepalive);
+        }
+        if let Some(interval) = self.http2_keep_alive_interval {
+            builder = builder.http2_keep_alive_interval(interval);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.read_timeout {
+            builder = builder.read_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        for (host, addr) in &self.resolve_overrides {
+            builder = builder.resolve(host, *addr);
+        }
+        if let Some(addr) = self.local_address {
+            builder = builder.local_address(addr);
+        }
+        if let Some(url) = &self.proxy {
+            // the proxy url may hold credentials, so it isn't repeated in the error
+            let proxy = reqwest::Proxy::all(url.as_str()).map_err(|_| WebhookError::InvalidUrl(String::from("proxy url")))?;
+            let no_proxy = self.no_proxy.as_deref().and_then(reqwest::NoProxy::from_string);
+            builder = builder.proxy(proxy.no_proxy(no_proxy));
+        } else if self.no_proxy.is_some() {
+            // reqwest can't add exclusions to the environment's proxy
+            return Err(WebhookError::InvalidUrl(String::from("no_proxy without a proxy")));
+        }
+        Ok(builder.build()?)
+    }
+}
+
+/// Sends webhooks to Discord over HTTP.
+///
+/// Senders made with [`HttpSender::new`] share one client, and with it their connections, so
+/// [`Webhook::send`] doesn't open a new connection per message. Senders made from a config or
+/// a client reuse that client's connections, so long-lived callers should keep one around.
+///
+/// The shared client is process-wide, but its pooled connections belong to the tokio runtime
+/// that opened them. A program that sends from several runtimes in turn, e.g. one created per
+/// job and dropped afterwards, can fail with "dispatch task is gone" on a connection left over
+/// from a dropped runtime. Give each runtime its own sender with [`HttpSender::with_client`]
+/// or [`HttpSender::with_config`] instead.
+#[derive(Debug, Clone)]
+pub struct HttpSender {
+    client: reqwest::Client,
+    circuit_breaker: Option<CircuitBreaker>,
+    rate_limit_buckets: Option<RateLimitBuckets>,
+    timeout: Option<Duration>,
+    #[cfg(feature = "retry")]
+    max_retries: u32,
+    #[cfg(feature = "retry")]
+    retry_policy: Option<RetryPolicy>,
+    #[cfg(feature = "gzip")]
+    compression: Compression,
+}
+
+#[cfg(feature = "gzip")]
+#[derive(Debug, Clone)]
+struct Compression {
+    enabled: bool,
+    threshold: usize,
+    /// Set once Discord rejects a compressed body, shared between clones of the sender.
+    rejected: Arc<AtomicBool>,
+}
+
+#[cfg(feature = "gzip")]
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: 8 * 1024,
+            rejected: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl Default for HttpSender {
+    /// A sender on the process-wide shared client, see [`HttpSender`].
+    fn default() -> Self {
+        Self::with_client(shared_client().clone())
+    }
+}
+
+/// The `User-Agent` sent unless [`ClientConfig::user_agent`] says otherwise, e.g. `blurple_hook/0.3.10`.
+pub const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// The client behind every default [`HttpSender`]. Clones share its connection pool.
+///
+/// Building it only fails when the TLS backend can't be initialized, which no request could
+/// get past either, so it panics rather than falling back to a client without the user agent.
+fn shared_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .user_agent(DEFAULT_USER_AGENT)
+            .build()
+            .expect("the TLS backend failed to initialize")
+    })
+}
+
+impl HttpSender {
+    /// A sender on the shared client, see [`HttpSender`] for its caveat with several runtimes.
+    ///
+    /// Panics if the TLS backend can't be initialized; [`HttpSender::with_config`] reports that
+    /// as an error instead.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(config: &ClientConfig) -> Result<Self, WebhookError> {
+        Ok(Self::with_client(config.build()?))
+    }
+
+    pub fn with_client(client: reqwest::Client) -> Self {
+        Self {
+            client,
+            circuit_breaker: None,
+            rate_limit_buckets: None,
+            timeout: None,
+            #[cfg(feature = "retry")]
+            max_retries: 3,
+            #[cfg(feature = "retry")]
+            retry_policy: None,
+            #[cfg(feature = "gzip")]
+            compression: Compression::default(),
+        }
+    }
+
+    /// The client requests go through, e.g. to make other requests over the same connections.
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// Fails requests that take longer than `timeout` with [`WebhookError::Http`], instead of
+    /// waiting on an unresponsive server indefinitely. Each retry gets the full timeout.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how many times a rate limited request is retried (3 by default) before failing
+    /// with [`WebhookError::RateLimited`].
+    #[cfg(feature = "retry")]
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Retries connection errors and server errors, see [`RetryPolicy`]. Without one, only rate
+    /// limits are retried.
+    #[cfg(feature = "retry")]
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Short-circuits requests to webhook urls that keep failing, see [`CircuitBreaker`].
+    pub fn circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(breaker);
+        self
+    }
+
+    /// Waits out buckets that Discord reports exhausted before sending, see [`RateLimitBuckets`].
+    /// Senders don't track buckets unless given some, so they only learn of a rate limit from a
+    /// 429.
+    pub fn rate_limit_buckets(mut self, buckets: RateLimitBuckets) -> Self {
+        self.rate_limit_buckets = Some(buckets);
+        self
+    }
+
+    /// Gzip-encodes JSON bodies larger than the compression threshold (8 KiB by default).
+    ///
+    /// If Discord rejects a compressed body with a 400 or 415, the request is resent
+    /// uncompressed and compression stays off for this sender.
+    #[cfg(feature = "gzip")]
+    pub fn compress_requests(mut self, compress: bool) -> Self {
+        self.compression.enabled = compress;
+        self
+    }
+
+    /// Sets the serialized size in bytes above which bodies are compressed.
+    #[cfg(feature = "gzip")]
+    pub fn compression_threshold(mut self, threshold: usize) -> Self {
+        self.compression.threshold = threshold;
+        self
+    }
+
+    #[cfg(feature = "gzip")]
+    fn compressed_body(&self, body: &[u8]) -> Option<Vec<u8>> {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let compression = &self.compression;
+        if !compression.enabled || body.len() <= compression.threshold || compression.rejected.load(Ordering::Relaxed) {
+            return None;
+        }
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(body).ok()?;
+        encoder.finish().ok()
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    fn compressed_body(&self, _body: &[u8]) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// This sender with the webhook's own timeout, retry limit and policy, if it sets them.
+    pub(crate) fn for_webhook(&self, webhook: &Webhook) -> Cow<'_, Self> {
+        #[cfg(feature = "retry")]
+        let overrides = webhook.timeout.is_some() || webhook.max_retries.is_some() || webhook.retry_policy.is_some();
+        #[cfg(not(feature = "retry"))]
+        let overrides = webhook.timeout.is_some();
+        if !overrides {
+            return Cow::Borrowed(self);
+        }
+        let mut sender = self.clone();
+        sender.timeout = webhook.timeout.or(self.timeout);
+        #[cfg(feature = "retry")]
+        {
+            sender.max_retries = webhook.max_retries.unwrap_or(self.max_retries);
+            sender.retry_policy = webhook.retry_policy.clone().or(sender.retry_policy);
+        }
+        Cow::Owned(sender)
+    }
+
+    pub(crate) async fn execute<T: Serialize>(&self, method: reqwest::Method, url: &str, query: &[(&str, String)], headers: &HeaderMap, payload: &T) -> Result<Delivery, WebhookError> {
+        let body = Body::json(payload)?;
+        self.dispatch(method, url, query, headers, Some(body)).await
+    }
+
+    /// Sends a webhook's payload, as multipart when it has files.
+    pub(crate) async fn execute_webhook(&self, method: reqwest::Method, url: &str, query: &[(&str, String)], headers: &HeaderMap, webhook: &Webhook) -> Result<Delivery, WebhookError> {
+        let body = Body::webhook(webhook)?;
+        self.dispatch(method, url, query, headers, Some(body)).await
+    }
+
+    /// Sends an already serialized body, e.g. the same one to several urls.
+    pub(crate) async fn execute_body(&self, method: reqwest::Method, url: &str, query: &[(&str, String)], headers: &HeaderMap, body: Body) -> Result<Delivery, WebhookError> {
+        self.dispatch(method, url, query, headers, Some(body)).await
+    }
+
+    /// Makes a request without a body, such as a delete.
+    pub(crate) async fn execute_empty(&self, method: reqwest::Method, url: &str, query: &[(&str, String)], headers: &HeaderMap) -> Result<Delivery, WebhookError> {
+        self.dispatch(method, url, query, headers, None).await
+    }
+
+    async fn dispatch(&self, method: reqwest::Method, url: &str, query: &[(&str, String)], headers: &HeaderMap, body: Option<Body>) -> Result<Delivery, WebhookError> {
+        #[cfg(feature = "retry")]
+        if let Some(policy) = &self.retry_policy {
+            let mut attempt = 1;
+            loop {
+                match self.attempt(method.clone(), url, query, headers, body.clone()).await {
+                    Err(err) if policy.should_retry(attempt, &err) => {
+                        let delay = policy.delay(attempt);
+                        log::warn!("Webhook request failed, retrying in {} seconds: {}", delay.as_secs_f64(), err);
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                    },
+                    result => return result,
+                }
+            }
+        }
+        self.attempt(method, url, query, headers, body).await
+    }
+
+    async fn attempt(&self, method: reqwest::Method, url: &str, query: &[(&str, String)], headers: &HeaderMap, body: Option<Body>) -> Result<Delivery, WebhookError> {
+        let Some(breaker) = &self.circuit_breaker else {
+            return self.paced_request(method, url, query, headers, body).await;
+        };
+        breaker.check(url)?;
+        let result = self.paced_request(method, url, query, headers, body).await;
+        breaker.record(url, &result);
+        result
+    }
+
+    async fn paced_request(&self, method: reqwest::Method, url: &str, query: &[(&str, String)], headers: &HeaderMap, body: Option<Body>) -> Result<Delivery, WebhookError> {
+        let Some(buckets) = &self.rate_limit_buckets else {
+            return self.request(method, url, query, headers, body).await;
+        };
+        buckets.acquire(method.as_str(), url).await;
+        self.request(method, url, query, headers, body).await
+    }
+
+    async fn request(&self, method: reqwest::Method, url: &str, query: &[(&str, String)], headers: &HeaderMap, body: Option<Body>) -> Result<Delivery, WebhookError> {
+        #[cfg(feature = "retry")]
+        let (mut retries, mut waited) = (0, Duration::ZERO);
+
+        // only loops to resend after a rejected compressed body or a rate limit
+        #[allow(clippy::never_loop)]
+        loop {
+            // files are mostly already compressed, so only JSON bodies are worth it
+            let compressed = body.as_ref().filter(|body| body.is_json()).and_then(|body| self.compressed_body(&body.bytes));
+            #[cfg(feature = "gzip")]
+            let is_compressed = compressed.is_some();
+
+            let mut request = self.client
+                .request(method.clone(), url)
+                .query(query)
+                .headers(headers.clone());
+            if let Some(timeout) = self.timeout {
+                request = request.timeout(timeout);
+            }
+            let request = match (compressed, &body) {
+                (Some(bytes), _) => request
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(reqwest::header::CONTENT_ENCODING, "gzip")
+                    .body(bytes),
+                (None, Some(body)) => request.header(CONTENT_TYPE, body.content_type.as_str()).body(body.bytes.clone()),
+                (None, None) => request,
+            };
+            let resp = request.send().await?;
+            let rate_limit = RateLimitHeaders::from_headers(resp.headers());
+            // errors and 429s report the bucket too
+            if let Some(buckets) = &self.rate_limit_buckets {
+                buckets.observe(method.as_str(), url, &rate_limit);
+            }
+
+            match resp.status() {
+                reqwest::StatusCode::NO_CONTENT | reqwest::StatusCode::OK => {
+                    return Ok(Delivery {
+                        rate_limit,
+                        body: resp.text().await?,
+                    });
+                },
+                #[cfg(feature = "gzip")]
+                reqwest::StatusCode::BAD_REQUEST | reqwest::StatusCode::UNSUPPORTED_MEDIA_TYPE if is_compressed => {
+                    self.compression.rejected.store(true, Ordering::Relaxed);
+                },
+                #[cfg(feature = "retry")]
+                reqwest::StatusCode::TOO_MANY_REQUESTS => {
+                    let (retry_after, body) = retry_after(resp).await;
+                    if retries >= self.max_retries {
+                        return Err(WebhookError::RateLimited { attempts: retries + 1, waited, retry_after, body });
+                    }
+                    retries += 1;
+                    waited += retry_after;
+                    log::warn!("Webhook rate limited, retrying in {} seconds", retry_after.as_secs_f64());
+                    tokio::time::sleep(retry_after).await;
+                },
+                _ => return Err(api_error(resp).await),
+            }
+        }
+    }
+}
+
+/// How long to wait after a 429 that doesn't say, or says something unusable.
+#[cfg(any(feature = "retry", feature = "middleware"))]
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// How long a 429 response asks to wait: Discord's `retry_after` body field, falling back to the
+/// `Retry-After` and `X-RateLimit-Reset-After` headers, then [`DEFAULT_RETRY_AFTER`]. Also
+/// returns the body.
+#[cfg(any(feature = "retry", feature = "middleware"))]
+async fn retry_after(resp: reqwest::Response) -> (Duration, String) {
+    let header = |name: &str| {
+        resp.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<f64>().ok())
+    };
+    let from_headers = header("retry-after").or_else(|| header("x-ratelimit-reset-after"));
+    let body = resp.text().await.unwrap_or_default();
+    let from_body = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|value| value.get("retry_after")?.as_f64());
+    // negative, NaN and absurdly large waits are ignored rather than trusted
+    let duration = |secs: f64| Duration::try_from_secs_f64(secs).ok();
+    let retry_after = from_body.and_then(duration).or_else(|| from_headers.and_then(duration));
+    (retry_after.unwrap_or(DEFAULT_RETRY_AFTER), body)
+}
+
+/// What a successful request told us.
+#[derive(Debug)]
+pub(crate) struct Delivery {
+    pub(crate) rate_limit: RateLimitHeaders,
+    pub(crate) body: String,
+}
+
+impl Delivery {
+    /// Reads the message Discord returns for `?wait=true` sends and edits.
+    pub(crate) fn message(&self) -> Result<WebhookMessage, WebhookError> {
+        if self.body.trim().is_empty() {
+            return Ok(WebhookMessage::default());
+        }
+        serde_json::from_str(&self.body).map_err(WebhookError::InvalidResponse)
+    }
+}
+
+/// Reads a response made outside an [`HttpSender`], such as through middleware. A 429 isn't
+/// retried, so it's [`WebhookError::RateLimited`] after one attempt.
+#[cfg(feature = "middleware")]
+pub(crate) async fn read_response(resp: reqwest::Response) -> Result<Delivery, WebhookError> {
+    let rate_limit = RateLimitHeaders::from_headers(resp.headers());
+    match resp.status() {
+        reqwest::StatusCode::NO_CONTENT | reqwest::StatusCode::OK => Ok(Delivery {
+            rate_limit,
+            body: resp.text().await?,
+        }),
+        reqwest::StatusCode::TOO_MANY_REQUESTS => {
+            let (retry_after, body) = retry_after(resp).await;
+            Err(WebhookError::RateLimited { attempts: 1, waited: Duration::ZERO, retry_after, body })
+        },
+        _ => Err(api_error(resp).await),
+    }
+}
+
+/// Reads a failed response into [`WebhookError::Api`].
+pub(crate) async fn api_error(resp: reqwest::Response) -> WebhookError {
+    let status = resp.status().as_u16();
+    let body = resp.text().await.unwrap_or(String::from(""));
+    WebhookError::api(status, body)
+}
+
+impl WebhookSender for HttpSender {
+    async fn send(&self, webhook: &Webhook) -> Result<WebhookMessage, WebhookError> {
+        let headers = webhook.header_map()?;
+        self.for_webhook(webhook)
+            .execute_webhook(reqwest::Method::POST, webhook.url()?, &webhook.query_params(), &headers, webhook)
+            .await?
+            .message()
+    }
+
+    async fn edit(&self, webhook: &Webhook, message_id: &str) -> Result<WebhookMessage, WebhookError> {
+        let headers = webhook.header_map()?;
+        self.for_webhook(webhook)
+            .execute_body(reqwest::Method::PATCH, &webhook.message_url(message_id)?, &webhook.edit_query_params(), &headers, Body::edit(webhook)?)
+            .await
+            .map_err(|err| message_not_found(err, message_id))?
+            .message()
+    }
+
+    async fn delete(&self, webhook: &Webhook, message_id: &str) -> Result<(), WebhookError> {
+        let headers = webhook.header_map()?;
+        self.for_webhook(webhook)
+            .execute_empty(reqwest::Method::DELETE, &webhook.message_url(message_id)?, &webhook.thread_query_params(), &headers)
+            .await
+            .map_err(|err| message_not_found(err, message_id))?;
+        Ok(())
+    }
+}
+
+/// Turns Discord's unknown message response into [`WebhookError::MessageNotFound`].
+fn message_not_found(err: WebhookError, message_id: &str) -> WebhookError {
+    match err {
+        WebhookError::Api { status: 404, code: Some(DiscordErrorCode::UnknownMessage), .. } => {
+            WebhookError::MessageNotFound { message_id: message_id.to_string() }
+        },
+        err => err,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::{ClientConfig, HttpSender, DEFAULT_USER_AGENT};
+    use crate::message::MESSAGE_JSON;
+    use crate::{Webhook, WebhookError};
+
+    /// A keep-alive HTTP server answering every request with a message and counting connections.
+    async fn counting_server() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&connections);
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 4096];
+                    loop {
+                        let n = match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => n,
+                        };
+                        buf.extend_from_slice(&chunk[..n]);
+                        while let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                            let head = String::from_utf8_lossy(&buf[..end]).to_ascii_lowercase();
+                            let length = head
+                                .lines()
+                                .find_map(|l| l.strip_prefix("content-length:"))
+                                .map_or(0, |l| l.trim().parse::<usize>().unwrap());
+                            if buf.len() < end + 4 + length {
+                                break;
+                            }
+                            buf.drain(..end + 4 + length);
+                            let response = format!(
+                                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                                MESSAGE_JSON.len(),
+                                MESSAGE_JSON
+                            );
+                            stream.write_all(response.as_bytes()).await.unwrap();
+                        }
+                    }
+                });
+            }
+        });
+        (format!("http://{}/api/webhooks/1/token", addr), connections)
+    }
+
+    #[tokio::test]
+    async fn slow_responses_time_out() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(crate::message::MESSAGE_JSON, "application/json")
+                    .set_delay(Duration::from_millis(500)),
+            )
+            .mount(&server)
+            .await;
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri())).set_content("Content Text");
+        let timed_out = |result: Result<_, WebhookError>| matches!(result, Err(WebhookError::Http(err)) if err.is_timeout());
+
+        assert!(timed_out(webhook.send_with(&HttpSender::new().timeout(Duration::from_millis(50))).await));
+        let config = ClientConfig { timeout: Some(Duration::from_millis(50)), ..Default::default() };
+        assert!(timed_out(webhook.send_with(&HttpSender::with_config(&config).unwrap()).await));
+        // the webhook's own timeout wins over the sender's
+        let patient = webhook.clone().set_timeout(Duration::from_secs(5));
+        assert!(patient.send_with(&HttpSender::new().timeout(Duration::from_millis(50))).await.is_ok());
+        assert!(timed_out(webhook.set_timeout(Duration::from_millis(50)).send().await));
+    }
+
+    #[tokio::test]
+    async fn sends_through_a_proxy() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let proxy = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/webhooks/1/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&proxy)
+            .await;
+
+        // the host doesn't resolve, so only the proxy can deliver it
+        let webhook = Webhook::new("http://discord.invalid/api/webhooks/1/token").set_content("Content Text");
+        let config = ClientConfig { proxy: Some(proxy.uri()), ..Default::default() };
+        webhook.send_with(&HttpSender::with_config(&config).unwrap()).await.unwrap();
+
+        let config = ClientConfig { no_proxy: Some(String::from("localhost,discord.invalid")), ..config };
+        assert!(matches!(webhook.send_with(&HttpSender::with_config(&config).unwrap()).await, Err(WebhookError::Http(_))));
+
+        let config = ClientConfig { proxy: Some(String::from("not a proxy")), ..Default::default() };
+        assert!(matches!(HttpSender::with_config(&config), Err(WebhookError::InvalidUrl(url)) if url == "proxy url"));
+
+        let config = ClientConfig { no_proxy: Some(String::from("localhost")), ..Default::default() };
+        assert!(matches!(HttpSender::with_config(&config), Err(WebhookError::InvalidUrl(url)) if url == "no_proxy without a proxy"));
+    }
+
+    #[tokio::test]
+    async fn sends_the_user_agent() {
+        use wiremock::matchers::header;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        for user_agent in [DEFAULT_USER_AGENT, "deploy-bot/2.1 (ops@example.com)"] {
+            Mock::given(header("user-agent", user_agent))
+                .respond_with(ResponseTemplate::new(200).set_body_raw(MESSAGE_JSON, "application/json"))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri())).set_content("Content Text");
+        webhook.send().await.unwrap();
+        let config = ClientConfig { user_agent: Some(String::from("deploy-bot/2.1 (ops@example.com)")), ..Default::default() };
+        webhook.send_with(&HttpSender::with_config(&config).unwrap()).await.unwrap();
+        assert!(DEFAULT_USER_AGENT.starts_with("blurple_hook/"));
+    }
+
+    #[tokio::test]
+    async fn configured_sender_reuses_connections() {
+        let (url, connections) = counting_server().await;
+        let config = ClientConfig {
+            pool_idle_timeout: Some(Duration::from_secs(30)),
+            pool_max_idle_per_host: Some(2),
+            tcp_keepalive: Some(Duration::from_secs(15)),
+            http2_keep_alive_interval: Some(Duration::from_secs(15)),
+            ..Default::default()
+        };
+        let sender = HttpSender::with_config(&config).unwrap();
+
+        let webhook = Webhook::new(url).set_content("Content Text");
+        for _ in 0..3 {
+            webhook.send_with(&sender).await.unwrap();
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn default_senders_share_a_client() {
+        let (url, connections) = counting_server().await;
+        let webhook = Webhook::new(url).set_content("Content Text");
+        for _ in 0..3 {
+            webhook.send().await.unwrap();
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        let (url, connections) = counting_server().await;
+        let client = ClientConfig::default().build().unwrap();
+        let webhook = Webhook::new(url).set_content("Content Text");
+        for _ in 0..3 {
+            webhook.send_with_client(&client).await.unwrap();
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        let (url, connections) = counting_server().await;
+        let webhook = Webhook::new(url).set_content("Content Text");
+        webhook.send().await.unwrap();
+        webhook.send_with_client(HttpSender::new().client()).await.unwrap();
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn resolves_overridden_hosts() {
+        use wiremock::matchers::{header, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("Host", format!("discord.com:{}", server.address().port()).as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let config = ClientConfig {
+            connect_timeout: Some(Duration::from_secs(1)),
+            ..Default::default()
+        }
+        .resolve("discord.com", *server.address());
+        let sender = HttpSender::with_config(&config).unwrap();
+
+        let url = format!("http://discord.com:{}/api/webhooks/1/token", server.address().port());
+        Webhook::new(url).set_content("Content Text").send_with(&sender).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn sends_from_the_local_address() {
+        use std::net::{IpAddr, Ipv4Addr};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri())).set_content("Content Text");
+
+        let bound = ClientConfig {
+            local_address: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            ..Default::default()
+        };
+        webhook.send_with(&HttpSender::with_config(&bound).unwrap()).await.unwrap();
+
+        // 192.0.2.0/24 is reserved for documentation, so no host owns it
+        let unowned = ClientConfig {
+            local_address: Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))),
+            connect_timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+        let result = webhook.send_with(&HttpSender::with_config(&unowned).unwrap()).await;
+        assert!(matches!(result, Err(crate::WebhookError::Http(_))), "{:?}", result);
+    }
+
+    struct Unserializable;
+
+    impl serde::Serialize for Unserializable {
+        fn serialize<S: serde::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("poisoned field"))
+        }
+    }
+
+    #[tokio::test]
+    async fn serialization_errors_are_returned() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(204))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let result = HttpSender::new()
+            .execute(crate::reqwest::Method::POST, &server.uri(), &[], &Default::default(), &Unserializable)
+            .await;
+        assert!(matches!(result, Err(crate::WebhookError::Serialization(_))));
+    }
+
+    #[tokio::test]
+    async fn accepts_responses_without_a_message() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let message = Webhook::new(server.uri()).set_content("Content Text").send().await.unwrap();
+        assert_eq!(message, crate::WebhookMessage::default());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn compresses_large_bodies() {
+        use std::io::Read;
+        use wiremock::matchers::{header, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("Content-Encoding", "gzip"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let sender = HttpSender::new().compress_requests(true).compression_threshold(256);
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri())).set_content("a".repeat(300));
+        webhook.send_with(&sender).await.unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(&requests[0].body[..]).read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, serde_json::to_string(&webhook).unwrap());
+
+        // bodies under the threshold are sent as-is
+        Webhook::new(format!("{}/api/webhooks/1/token", server.uri()))
+            .set_content("small")
+            .send_with(&sender)
+            .await
+            .unwrap_err();
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn falls_back_when_compression_is_rejected() {
+        use wiremock::matchers::{header, header_exists, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header_exists("Content-Encoding"))
+            .respond_with(ResponseTemplate::new(415))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(header("Content-Type", "application/json"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(MESSAGE_JSON, "application/json"))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let sender = HttpSender::new().compress_requests(true).compression_threshold(64);
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri())).set_content("a".repeat(100));
+        webhook.send_with(&sender).await.unwrap();
+        // the rejection is remembered, so this goes out uncompressed straight away
+        webhook.send_with(&sender.clone()).await.unwrap();
+    }
+
+    #[cfg(feature = "retry")]
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("retry-after", "1")
+                    .set_body_string(r#"{"message": "You are being rate limited.", "retry_after": 0.02, "global": false}"#),
+            )
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let sender = HttpSender::new().max_retries(2);
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri())).set_content("Content Text");
+        let err = webhook.send_with(&sender).await.unwrap_err();
+        // the body's retry_after wins over the rounded header
+        assert!(matches!(
+            &err,
+            crate::WebhookError::RateLimited { attempts: 3, waited, retry_after, body }
+                if *waited == Duration::from_millis(40) && *retry_after == Duration::from_millis(20) && body.contains("rate limited")
+        ), "{:?}", err);
+        assert_eq!(err.to_string(), "Still rate limited after 3 attempts, waited 40ms");
+    }
+
+    #[cfg(feature = "retry")]
+    #[tokio::test]
+    async fn rate_limits_without_retry_after_keep_the_body() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).set_body_string(r#"{"message": "You are being rate limited."}"#))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri())).set_content("Content Text");
+        let err = webhook.send_with(&HttpSender::new().max_retries(0)).await.unwrap_err();
+        assert!(matches!(
+            &err,
+            crate::WebhookError::RateLimited { attempts: 1, retry_after, body, .. }
+                if *retry_after == Duration::from_secs(1) && body.contains("rate limited")
+        ), "{:?}", err);
+    }
+
+    #[cfg(feature = "retry")]
+    #[tokio::test]
+    async fn out_of_range_retry_after_falls_back_to_the_header() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("retry-after", "0")
+                    .set_body_string(r#"{"message": "You are being rate limited.", "retry_after": 1e300, "global": false}"#),
+            )
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri())).set_content("Content Text");
+        webhook.send().await.unwrap();
+    }
+}
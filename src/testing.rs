@@ -0,0 +1,168 @@
+//! Assertions for tests that check the payloads your code builds.
+//!
+//! Matching is partial, so tests only break when something they care about changes:
+//!
+//! ```
+//! use blurple_hook::{assert_payload_matches, Embed, Webhook};
+//! use serde_json::json;
+//!
+//! let embed = Embed::new().set_title("v1.2.3").add_field("Env", "prod", true);
+//! blurple_hook::testing::assert_embed_field(&embed, "Env", "prod");
+//!
+//! let webhook = Webhook::new("https://discord.com/api/webhooks/1/token")
+//!     .set_content("Deployed")
+//!     .add_embed(embed);
+//! assert_payload_matches!(webhook, json!({"embeds": [{"title": "v1.2.3"}]}));
+//! ```
+
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::{Embed, Webhook};
+
+/// Something with a JSON payload to match against: a [`Webhook`], an [`Embed`], or a captured
+/// JSON body.
+pub trait Payload {
+    fn payload(&self) -> Value;
+}
+
+impl Payload for Webhook {
+    /// The payload as it would be sent.
+    fn payload(&self) -> Value {
+        self.to_value().expect("webhook payload should serialize")
+    }
+}
+
+impl Payload for Embed {
+    fn payload(&self) -> Value {
+        serde_json::to_value(self).expect("embed should serialize")
+    }
+}
+
+impl Payload for Value {
+    fn payload(&self) -> Value {
+        self.clone()
+    }
+}
+
+impl<T: Payload + ?Sized> Payload for &T {
+    fn payload(&self) -> Value {
+        (**self).payload()
+    }
+}
+
+/// A difference between the expected and actual payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    /// Where the difference is, such as `embeds[0].title`.
+    pub path: String,
+    pub expected: Value,
+    /// `None` when the key is missing.
+    pub actual: Option<Value>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() { "(root)" } else { &self.path };
+        match &self.actual {
+            Some(actual) => write!(f, "{}: expected {}, found {}", path, self.expected, actual),
+            None => write!(f, "{}: expected {}, found nothing", path, self.expected),
+        }
+    }
+}
+
+/// Compares `actual` against the parts of the payload `expected` mentions.
+///
+/// Objects only need the expected keys; arrays must have the same length, with each element
+/// matched partially. Other values must be equal.
+pub fn mismatches(actual: &Value, expected: &Value) -> Vec<Mismatch> {
+    let mut found = Vec::new();
+    compare(String::new(), actual, expected, &mut found);
+    found
+}
+
+fn compare(path: String, actual: &Value, expected: &Value, found: &mut Vec<Mismatch>) {
+    let mismatch = |path: String, actual: Option<&Value>| Mismatch {
+        path,
+        expected: expected.clone(),
+        actual: actual.cloned(),
+    };
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => {
+            for (key, expected) in expected {
+                let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                match actual.get(key) {
+                    Some(actual) => compare(path, actual, expected, found),
+                    None => found.push(Mismatch { path, expected: expected.clone(), actual: None }),
+                }
+            }
+        },
+        (Value::Array(items), Value::Array(expected_items)) if items.len() == expected_items.len() => {
+            for (i, (actual, expected)) in items.iter().zip(expected_items).enumerate() {
+                compare(format!("{}[{}]", path, i), actual, expected, found);
+            }
+        },
+        (actual, expected) if actual == expected => {},
+        (actual, _) => found.push(mismatch(path, Some(actual))),
+    }
+}
+
+/// Panics with every mismatching path unless `payload` matches the parts of `expected` it
+/// mentions. Used by [`assert_payload_matches!`](crate::assert_payload_matches).
+#[track_caller]
+pub fn assert_matches<P: Payload>(payload: &P, expected: &Value) {
+    let found = mismatches(&payload.payload(), expected);
+    if !found.is_empty() {
+        let lines: Vec<String> = found.iter().map(|m| format!("  {}", m)).collect();
+        panic!("payload does not match:\n{}", lines.join("\n"));
+    }
+}
+
+/// Asserts that a payload contains everything in the expected JSON.
+///
+/// Works on a [`Webhook`], an [`Embed`], or a captured `serde_json::Value`.
+#[macro_export]
+macro_rules! assert_payload_matches {
+    ($payload:expr, $expected:expr $(,)?) => {
+        $crate::testing::assert_matches(&$payload, &$expected)
+    };
+}
+
+/// Asserts that the embed has a field called `name` with the given value.
+#[track_caller]
+pub fn assert_embed_field(embed: &Embed, name: &str, expected_value: &str) {
+    match embed.fields.iter().find(|field| field.name == name) {
+        Some(field) => assert_eq!(field.value, expected_value, "field {:?} has the wrong value", name),
+        None => {
+            let names: Vec<&str> = embed.fields.iter().map(|field| field.name.as_str()).collect();
+            panic!("no field {:?}, the embed has {:?}", name, names);
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::mismatches;
+
+    #[test]
+    fn reports_paths_of_mismatches() {
+        let actual = json!({"content": "Hi", "embeds": [{"title": "Deployed", "fields": []}]});
+        let expected = json!({"content": "Hi", "embeds": [{"title": "Deploy", "color": 5}], "tts": false});
+
+        let found: Vec<String> = mismatches(&actual, &expected).iter().map(ToString::to_string).collect();
+        assert_eq!(found, [
+            r#"embeds[0].color: expected 5, found nothing"#,
+            r#"embeds[0].title: expected "Deploy", found "Deployed""#,
+            r#"tts: expected false, found nothing"#,
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "payload does not match:\n  (root): expected [1], found [1,2]")]
+    fn arrays_must_have_the_same_length() {
+        crate::assert_payload_matches!(json!([1, 2]), json!([1]));
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt;
+
+use crate::{Embed, WebhookError};
+
+pub const MAX_CONTENT: usize = 2000;
+pub const MAX_USERNAME: usize = 80;
+pub const MAX_THREAD_NAME: usize = 100;
+pub const MAX_EMBEDS: usize = 10;
+/// The combined characters of all embeds in a message, see [`Embed::character_count`].
+pub const MAX_EMBED_CHARACTERS: usize = 6000;
+pub const MAX_TITLE: usize = 256;
+pub const MAX_DESCRIPTION: usize = 4096;
+pub const MAX_FIELDS: usize = 25;
+pub const MAX_FIELD_NAME: usize = 256;
+pub const MAX_FIELD_VALUE: usize = 1024;
+pub const MAX_FOOTER: usize = 2048;
+pub const MAX_AUTHOR_NAME: usize = 256;
+pub const MAX_FILES: usize = 10;
+pub const MAX_ACTION_ROWS: usize = 5;
+pub const MAX_ROW_COMPONENTS: usize = 5;
+pub const MAX_BUTTON_LABEL: usize = 80;
+pub const MAX_CUSTOM_ID: usize = 100;
+pub const MAX_SELECT_OPTIONS: usize = 25;
+pub const MAX_SELECT_PLACEHOLDER: usize = 150;
+pub const MAX_SELECT_OPTION_TEXT: usize = 100;
+/// Components in an `IS_COMPONENTS_V2` message, counting nested ones.
+pub const MAX_COMPONENTS_V2: usize = 40;
+pub const MAX_SECTION_TEXT: usize = 3;
+pub const MAX_GALLERY_ITEMS: usize = 10;
+pub const MAX_TEXT_DISPLAY: usize = 4000;
+
+/// A Discord payload limit that was exceeded.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ValidationError {
+    /// Text longer than Discord allows, measured in characters.
+    TooLong { field: &'static str, length: usize, limit: usize },
+    /// More items than Discord allows.
+    TooMany { field: &'static str, count: usize, limit: usize },
+    /// A combination of values Discord doesn't accept.
+    Invalid { field: &'static str, reason: &'static str },
+}
+
+impl ValidationError {
+    /// Wraps the error with the builder method that triggered it.
+    pub(crate) fn in_setter(self, setter: &'static str) -> WebhookError {
+        WebhookError::InvalidSetter { setter, error: self }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::TooLong { field, length, limit } => {
+                write!(f, "{} is {} characters, the limit is {}", field, length, limit)
+            },
+            ValidationError::TooMany { field, count, limit } => {
+                write!(f, "{} has {} items, the limit is {}", field, count, limit)
+            },
+            ValidationError::Invalid { field, reason } => write!(f, "invalid {}, {}", field, reason),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Breaks `text` into pieces of at most `limit` characters, e.g. to spread content over several
+/// messages. Each piece ends at its last line break, or its last space if it has none, and only
+/// splits a word that is longer than `limit` on its own. The whitespace broken at is dropped.
+pub fn split_text(text: &str, limit: usize) -> Vec<String> {
+    let limit = limit.max(1);
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while let Some((end, _)) = rest.char_indices().nth(limit) {
+        let window = &rest[..end];
+        let at = |separator: char| {
+            if rest[end..].starts_with(separator) {
+                Some(end)
+            } else {
+                window.rfind(separator).filter(|&i| i > 0)
+            }
+        };
+        let (piece, next) = match at('\n').or_else(|| at(' ')) {
+            Some(i) => (&rest[..i], &rest[i + 1..]),
+            None => (window, &rest[end..]),
+        };
+        pieces.push(piece.to_string());
+        rest = next;
+    }
+    if !rest.is_empty() || pieces.is_empty() {
+        pieces.push(rest.to_string());
+    }
+    pieces
+}
+
+pub(crate) fn check_length(field: &'static str, text: &str, limit: usize) -> Result<(), ValidationError> {
+    let length = text.chars().count();
+    if length > limit {
+        return Err(ValidationError::TooLong { field, length, limit });
+    }
+    Ok(())
+}
+
+pub(crate) fn check_count(field: &'static str, count: usize, limit: usize) -> Result<(), ValidationError> {
+    if count > limit {
+        return Err(ValidationError::TooMany { field, count, limit });
+    }
+    Ok(())
+}
+
+/// Checks every per-embed limit.
+pub(crate) fn check_embed(embed: &Embed) -> Result<(), ValidationError> {
+    if let Some(title) = &embed.title {
+        check_length("title", title, MAX_TITLE)?;
+    }
+    if let Some(description) = &embed.description {
+        check_length("description", description, MAX_DESCRIPTION)?;
+    }
+    check_count("fields", embed.fields.len(), MAX_FIELDS)?;
+    for field in &embed.fields {
+        check_length("field name", &field.name, MAX_FIELD_NAME)?;
+        check_length("field value", &field.value, MAX_FIELD_VALUE)?;
+    }
+    if let Some(footer) = &embed.footer {
+        check_length("footer text", &footer.text, MAX_FOOTER)?;
+    }
+    if let Some(author) = &embed.author {
+        check_length("author name", &author.name, MAX_AUTHOR_NAME)?;
+    }
+    check_length_total("embed", embed.character_count())
+}
+
+/// Checks a character total against the combined embed limit.
+pub(crate) fn check_length_total(field: &'static str, length: usize) -> Result<(), ValidationError> {
+    if length > MAX_EMBED_CHARACTERS {
+        return Err(ValidationError::TooLong { field, length, limit: MAX_EMBED_CHARACTERS });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_text;
+    use super::ValidationError::{self, TooLong, TooMany};
+    use crate::{Embed, Webhook, WebhookError};
+
+    fn webhook() -> Webhook {
+        Webhook::new("https://discord.com/api/webhooks/1/token")
+    }
+
+    fn with_embed(embed: Embed) -> Webhook {
+        webhook().add_embed(embed)
+    }
+
+    /// Checks that `at(limit)` passes and `at(limit + 1)` fails with `error(limit + 1)`.
+    fn boundary(limit: usize, at: impl Fn(usize) -> Webhook, error: impl Fn(usize) -> ValidationError) {
+        assert_eq!(at(limit).validate(), Ok(()), "at the limit");
+        assert_eq!(at(limit + 1).validate(), Err(error(limit + 1)), "one over the limit");
+    }
+
+    #[test]
+    fn text_limits() {
+        let text = |n: usize| "a".repeat(n);
+        let too_long = |field: &'static str, limit: usize| move |length| TooLong { field, length, limit };
+
+        boundary(2000, |n| webhook().set_content(text(n)), too_long("content", 2000));
+        boundary(80, |n| webhook().set_username(text(n)), too_long("username", 80));
+        boundary(100, |n| webhook().set_thread_name(text(n)), too_long("thread name", 100));
+        boundary(256, |n| with_embed(Embed::new().set_title(text(n))), too_long("title", 256));
+        boundary(4096, |n| with_embed(Embed::new().set_description(text(n))), too_long("description", 4096));
+        boundary(256, |n| with_embed(Embed::new().add_field(text(n), "v", false)), too_long("field name", 256));
+        boundary(1024, |n| with_embed(Embed::new().add_field("n", text(n), false)), too_long("field value", 1024));
+        boundary(2048, |n| with_embed(Embed::new().set_footer(text(n), None::<&str>, None::<&str>)), too_long("footer text", 2048));
+        boundary(256, |n| with_embed(Embed::new().set_author(text(n), None::<&str>, None::<&str>, None::<&str>)), too_long("author name", 256));
+    }
+
+    #[test]
+    fn splits_text_on_line_and_word_boundaries() {
+        assert_eq!(split_text("one two\nthree four five", 14), ["one two", "three four", "five"]);
+        assert_eq!(split_text("one two three", 7), ["one two", "three"]);
+        assert_eq!(split_text("abcdefghij klm", 4), ["abcd", "efgh", "ij", "klm"]);
+        assert_eq!(split_text("ééééé", 2), ["éé", "éé", "é"]);
+        assert_eq!(split_text("short", 2000), ["short"]);
+
+        let log = (0..500).map(|i| format!("line {}", i)).collect::<Vec<_>>().join("\n");
+        let pieces = split_text(&log, 2000);
+        assert!(pieces.iter().all(|piece| piece.chars().count() <= 2000 && !piece.starts_with('\n')));
+        assert_eq!(pieces.join("\n"), log);
+    }
+
+    #[test]
+    fn count_limits() {
+        let too_many = |field: &'static str, limit: usize| move |count| TooMany { field, count, limit };
+        let fields = |n: usize| (0..n).fold(Embed::new(), |embed, i| embed.add_field(i.to_string(), "v", true));
+        boundary(25, |n| with_embed(fields(n)), too_many("fields", 25));
+        boundary(10, |n| (0..n).fold(webhook(), |webhook, _| webhook.add_embed(Embed::new().set_title("t"))), too_many("embeds", 10));
+    }
+
+    #[test]
+    fn combined_embed_limit() {
+        // each embed is within its own limits; only the total is over
+        let embeds = |n: usize| {
+            webhook()
+                .add_embed(Embed::new().set_description("a".repeat(3000)))
+                .add_embed(Embed::new().set_description("a".repeat(n - 3000)))
+        };
+        boundary(6000, embeds, |length| TooLong { field: "embeds", length, limit: 6000 });
+    }
+
+    #[tokio::test]
+    async fn send_validates_before_requesting() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let result = Webhook::new(format!("{}/api/webhooks/1/token", server.uri()))
+            .set_content("a".repeat(2001))
+            .send()
+            .await;
+        assert!(matches!(result, Err(WebhookError::Validation(TooLong { field: "content", .. }))));
+
+        let result = Webhook::new(format!("{}/api/webhooks/1/token", server.uri()))
+            .set_thread_id(1)
+            .set_thread_name("Release 1.2")
+            .send()
+            .await;
+        assert!(matches!(result, Err(WebhookError::Validation(ValidationError::Invalid { field: "thread", .. }))));
+    }
+
+    #[tokio::test]
+    async fn validation_can_be_skipped() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_body_raw(crate::message::MESSAGE_JSON, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let webhook = Webhook::new(format!("{}/api/webhooks/1/token", server.uri()))
+            .set_content("a".repeat(2001))
+            .skip_validation(true);
+        assert!(webhook.validate().is_err());
+        webhook.send().await.unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::macros::parse_webhook_url;
+use crate::secret::SecretUrl;
+use crate::{Webhook, WebhookError};
+
+/// A Discord webhook url, checked when it's parsed rather than when the first message fails.
+///
+/// Urls on `discord.com`, `discordapp.com`, `ptb.discord.com` and `canary.discord.com`, with or
+/// without an api version, are accepted and normalized to
+/// `https://discord.com/api/webhooks/{id}/{token}`. Like the url inside a [`Webhook`], the token
+/// is redacted from `Debug` and `Display` output.
+///
+/// ```
+/// use blurple_hook::WebhookUrl;
+///
+/// let url: WebhookUrl = "https://canary.discord.com/api/v10/webhooks/123/abc-DEF_456".parse().unwrap();
+/// assert_eq!(url.id(), 123);
+/// assert_eq!(url.as_str(), "https://discord.com/api/webhooks/123/abc-DEF_456");
+/// assert!("https://dicsord.com/api/webhooks/123/abc".parse::<WebhookUrl>().is_err());
+/// ```
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct WebhookUrl {
+    url: SecretUrl,
+    id: u64,
+}
+
+impl WebhookUrl {
+    /// Parses `url`, ignoring surrounding whitespace and a trailing `/`. Fails with
+    /// [`WebhookError::InvalidUrl`] saying what's wrong with it.
+    pub fn parse<S: AsRef<str>>(url: S) -> Result<Self, WebhookError> {
+        let url = url.as_ref().trim();
+        let url = url.strip_suffix('/').unwrap_or(url);
+        let invalid = |reason: &str| WebhookError::InvalidUrl(format!("{} ({})", reason, SecretUrl::from(url)));
+
+        let (id_start, token_start) = parse_webhook_url(url).map_err(invalid)?;
+        let id = url[id_start..token_start - 1].parse().map_err(|_| invalid("webhook id is too large"))?;
+        let token = &url[token_start..];
+        Ok(Self {
+            url: format!("https://discord.com/api/webhooks/{}/{}", id, token).into(),
+            id,
+        })
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn token(&self) -> &str {
+        let url = self.url.as_str();
+        &url[url.rfind('/').map_or(0, |i| i + 1)..]
+    }
+
+    /// The normalized url, including the token.
+    pub fn as_str(&self) -> &str {
+        self.url.as_str()
+    }
+
+    /// The url with its token redacted, safe to log.
+    pub fn redacted(&self) -> String {
+        self.url.redacted()
+    }
+}
+
+impl FromStr for WebhookUrl {
+    type Err = WebhookError;
+
+    fn from_str(url: &str) -> Result<Self, Self::Err> {
+        Self::parse(url)
+    }
+}
+
+impl AsRef<str> for WebhookUrl {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for WebhookUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WebhookUrl({:?})", self.url)
+    }
+}
+
+impl fmt::Display for WebhookUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.url)
+    }
+}
+
+/// The variable [`Webhook::from_default_env`] reads.
+pub const DEFAULT_WEBHOOK_ENV: &str = "WEBHOOK_URL";
+
+impl Webhook {
+    /// Like [`Webhook::new`], but fails straight away if `webhook_url` isn't a Discord webhook
+    /// url, see [`WebhookUrl`]. The url is normalized.
+    pub fn try_new<S: AsRef<str>>(webhook_url: S) -> Result<Webhook, WebhookError> {
+        Ok(Webhook::new(WebhookUrl::parse(webhook_url)?))
+    }
+
+    /// A webhook for the url in the environment variable `name`, checked as in
+    /// [`Webhook::try_new`]. Fails with [`WebhookError::MissingEnvVar`] if it isn't set.
+    pub fn from_env<S: AsRef<str>>(name: S) -> Result<Webhook, WebhookError> {
+        let name = name.as_ref();
+        let url = match env::var(name) {
+            Ok(url) => url,
+            Err(env::VarError::NotPresent) => return Err(WebhookError::MissingEnvVar(name.to_string())),
+            Err(env::VarError::NotUnicode(_)) => return Err(WebhookError::InvalidUrl(format!("${} is not valid unicode", name))),
+        };
+        Webhook::try_new(url).map_err(|err| match err {
+            WebhookError::InvalidUrl(reason) => WebhookError::InvalidUrl(format!("${}, {}", name, reason)),
+            err => err,
+        })
+    }
+
+    /// [`Webhook::from_env`] with `$WEBHOOK_URL`, the variable `blurple-send` reads too.
+    pub fn from_default_env() -> Result<Webhook, WebhookError> {
+        Webhook::from_env(DEFAULT_WEBHOOK_ENV)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WebhookUrl;
+    use crate::{Webhook, WebhookError};
+
+    #[test]
+    fn parses_and_normalizes_webhook_urls() {
+        for url in [
+            "https://discord.com/api/webhooks/123/abc-DEF_456",
+            "https://discordapp.com/api/webhooks/123/abc-DEF_456",
+            "https://ptb.discord.com/api/v10/webhooks/123/abc-DEF_456/",
+            "  https://canary.discord.com/api/webhooks/123/abc-DEF_456\n",
+        ] {
+            let parsed = WebhookUrl::parse(url).unwrap();
+            assert_eq!(parsed.as_str(), "https://discord.com/api/webhooks/123/abc-DEF_456", "{:?}", url);
+            assert_eq!((parsed.id(), parsed.token()), (123, "abc-DEF_456"));
+        }
+
+        let url = WebhookUrl::parse("https://discord.com/api/webhooks/123/secret-token").unwrap();
+        assert_eq!(url.to_string(), "https://discord.com/api/webhooks/123/[redacted]");
+        assert!(!format!("{:?}", url).contains("secret-token"));
+    }
+
+    #[test]
+    fn rejects_malformed_urls_up_front() {
+        let cases = [
+            ("http://discord.com/api/webhooks/123/secret", "must start with https://"),
+            ("https://dicsord.com/api/webhooks/123/secret", "must be on discord.com"),
+            ("https://discord.com/api/webhooks/123", "missing the token"),
+            ("https://discord.com/api/webhooks/123/secret?wait=true", "after the token"),
+            ("https://discord.com/api/webhooks/99999999999999999999/secret", "too large"),
+        ];
+        for (url, reason) in cases {
+            match Webhook::try_new(url) {
+                Err(WebhookError::InvalidUrl(message)) => {
+                    assert!(message.contains(reason), "{}: {}", url, message);
+                    assert!(!message.contains("secret"), "{}", message);
+                }
+                other => panic!("{}: {:?}", url, other),
+            }
+        }
+    }
+
+    #[test]
+    fn reads_urls_from_the_environment() {
+        std::env::set_var("BLURPLE_TEST_WEBHOOK", "https://discord.com/api/v10/webhooks/123/secret/");
+        let webhook = Webhook::from_env("BLURPLE_TEST_WEBHOOK").unwrap();
+        assert_eq!(webhook.webhook_url.as_str(), "https://discord.com/api/webhooks/123/secret");
+
+        std::env::set_var("BLURPLE_TEST_WEBHOOK_TYPO", "https://discord.com/api/webhook/123/secret");
+        let err = Webhook::from_env("BLURPLE_TEST_WEBHOOK_TYPO").unwrap_err();
+        assert!(err.to_string().starts_with("Invalid url, $BLURPLE_TEST_WEBHOOK_TYPO, webhook url path"), "{}", err);
+        assert!(!err.to_string().contains("secret"));
+
+        let err = Webhook::from_env("BLURPLE_TEST_WEBHOOK_UNSET").unwrap_err();
+        assert!(matches!(&err, WebhookError::MissingEnvVar(name) if name == "BLURPLE_TEST_WEBHOOK_UNSET"));
+        assert_eq!(err.to_string(), "Environment variable BLURPLE_TEST_WEBHOOK_UNSET is not set");
+    }
+}
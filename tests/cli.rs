@@ -0,0 +1,150 @@
+#![cfg(feature = "cli")]
+
+use assert_cmd::Command;
+use predicates::str::contains;
+use serde_json::json;
+use wiremock::matchers::{body_json, header_regex, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn run(args: Vec<String>) -> assert_cmd::assert::Assert {
+    tokio::task::spawn_blocking(move || {
+        Command::cargo_bin("blurple-send").unwrap().env_remove("WEBHOOK_URL").args(args).assert()
+    })
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn sends_built_embed() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_json(json!({
+            "content": "Deployed",
+            "embeds": [{
+                "title": "v1.2.3",
+                "type": "rich",
+                "color": 0x5865F2,
+                "fields": [{"name": "Env", "value": "prod", "inline": false}]
+            }]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "1100000000000000001",
+            "channel_id": "1000000000000000001",
+            "content": "Deployed",
+            "timestamp": "2024-06-01T12:00:00.000000+00:00"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/api/webhooks/1/token", server.uri());
+    run(vec![
+        "--url".into(), url,
+        "--content".into(), "Deployed".into(),
+        "--title".into(), "v1.2.3".into(),
+        "--colour".into(), "blurple".into(),
+        "--field".into(), "Env=prod".into(),
+    ]).await.success();
+}
+
+#[tokio::test]
+async fn sends_tts_and_silent_messages() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_json(json!({"content": "Standup in 5", "tts": true, "flags": 4096})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "1100000000000000001",
+            "channel_id": "1000000000000000001",
+            "content": "Standup in 5",
+            "timestamp": "2024-06-01T12:00:00.000000+00:00"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/api/webhooks/1/token", server.uri());
+    run(vec!["--url".into(), url, "--content".into(), "Standup in 5".into(), "--tts".into(), "--silent".into()])
+        .await
+        .success();
+}
+
+#[tokio::test]
+async fn attaches_files_as_multipart() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(header_regex("content-type", "^multipart/form-data; boundary="))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "1100000000000000001",
+            "channel_id": "1000000000000000001",
+            "content": "Build log",
+            "timestamp": "2024-06-01T12:00:00.000000+00:00"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = std::env::temp_dir().join(format!("blurple-send-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("build.log");
+    std::fs::write(&path, "step 1 ok\n").unwrap();
+
+    let url = format!("{}/api/webhooks/1/token", server.uri());
+    run(vec![
+        "--url".into(), url,
+        "--content".into(), "Build log".into(),
+        "--file".into(), path.display().to_string(),
+    ]).await.success();
+
+    let requests = server.received_requests().await.unwrap();
+    let body = String::from_utf8_lossy(&requests[0].body);
+    assert!(body.contains(r#"name="payload_json""#), "{}", body);
+    assert!(body.contains(r#"name="files[0]"; filename="build.log""#), "{}", body);
+    assert!(body.contains("step 1 ok"), "{}", body);
+
+    run(vec!["--url".into(), "http://127.0.0.1:9/".into(), "--file".into(), dir.join("missing.log").display().to_string()])
+        .await
+        .failure()
+        .stderr(contains("missing.log"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn reports_discord_errors() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({"message": "Unknown Webhook", "code": 10015})))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/api/webhooks/1/token", server.uri());
+    run(vec!["--url".into(), url, "--content".into(), "Hello".into()])
+        .await
+        .failure()
+        .stderr(contains("Discord returned 404: Unknown Webhook (code 10015)"));
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "code": 50035,
+            "errors": {"content": {"_errors": [{"code": "CONTENT_TYPE_INVALID", "message": "Invalid content"}]}},
+            "message": "Invalid Form Body",
+        })))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/api/webhooks/1/token", server.uri());
+    run(vec!["--url".into(), url, "--content".into(), "Hello".into()])
+        .await
+        .failure()
+        .stderr(contains("Discord returned 400: Invalid Form Body (code 50035)\n  content: Invalid content (CONTENT_TYPE_INVALID)"));
+}
+
+#[tokio::test]
+async fn catches_mistakes_locally() {
+    run(vec!["--url".into(), "http://127.0.0.1:9/".into(), "--colour".into(), "not-a-colour".into()])
+        .await
+        .failure()
+        .stderr(contains("unknown colour name"));
+    run(vec!["--field".into(), "missing-equals".into()]).await.failure();
+    run(vec!["--content".into(), "Hello".into()]).await.failure().stderr(contains("$WEBHOOK_URL is not set"));
+}
@@ -0,0 +1,86 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use blurple_hook::{ColourType, Embed, Field, Webhook};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn count_allocations<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.with(|a| a.get());
+    f();
+    ALLOCATIONS.with(|a| a.get()) - before
+}
+
+fn fields(i: usize) -> Vec<Field> {
+    vec![
+        Field {
+            name: "Run".to_string(),
+            value: i.to_string(),
+            inline: true,
+        },
+        Field {
+            name: "Status".to_string(),
+            value: "OK".to_string(),
+            inline: true,
+        },
+    ]
+}
+
+fn build_embed(i: usize) -> Embed {
+    Embed::new()
+        .set_title("Nightly Report")
+        .set_colour(ColourType::Hex("#5865F2"))
+        .set_footer("Blurple Hook", Some("https://example.com/footer.png"), None::<String>)
+        .set_author("Reporter", Some("https://example.com/"), Some("https://example.com/icon.png"), None::<String>)
+        .add_fields(&mut fields(i))
+}
+
+#[test]
+fn templated_variants_allocate_less_than_rebuilding() {
+    let webhook_url = "https://discord.com/api/webhooks/123/abc";
+
+    let naive = count_allocations(|| {
+        for i in 0..1000 {
+            let webhook = Webhook::new(webhook_url)
+                .set_content(format!("Run {}", i))
+                .add_embed(build_embed(i));
+            std::hint::black_box(webhook);
+        }
+    });
+
+    let template_embed = build_embed(0);
+    let template = Webhook::new(webhook_url);
+    let templated = count_allocations(|| {
+        for i in 0..1000 {
+            let webhook = template
+                .clone_with_content(format!("Run {}", i))
+                .add_embed(template_embed.clone_with_fields(fields(i)));
+            std::hint::black_box(webhook);
+        }
+    });
+
+    assert!(
+        templated < naive,
+        "templated allocations ({}) should be fewer than naive allocations ({})",
+        templated,
+        naive
+    );
+}
@@ -0,0 +1,3 @@
+fn main() {
+    let _ = blurple_hook::webhook_url!("https://dicsord.com/api/webhooks/123/abc");
+}
@@ -0,0 +1,3 @@
+fn main() {
+    let _ = blurple_hook::webhook_url!("https://discord.com/api/webhooks/123");
+}